@@ -0,0 +1,818 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use animation::Animation;
+use atlas::TexElement;
+use group::Group;
+use node::Node;
+use sprite::Sprite;
+use transform::Transform;
+
+// Actionable is anything actions can animate. Types without a property of
+// their own keep the default, e.g. a Node has no alpha to fade.
+pub trait Actionable {
+    fn get_transform(&self) -> Transform;
+    fn set_transform(&mut self, transform: Transform);
+
+    fn get_alpha(&self) -> u8 {
+        255
+    }
+    fn set_alpha(&mut self, _alpha: u8) {}
+
+    fn get_visible(&self) -> bool;
+    fn set_visible(&mut self, visible: bool);
+}
+
+// Ease maps linear progress in [0, 1] to eased progress.
+pub type Ease = Rc<Fn(f32) -> f32>;
+
+// Action changes a target over time. `step` advances by `dt` seconds and
+// returns the part of `dt` left over once the action is done, None while it
+// is still running, so sequences chain without losing time.
+pub trait Action {
+    // start captures the target's state, it is called again on each repeat.
+    fn start(&mut self, target: &mut Actionable);
+
+    fn step(&mut self, target: &mut Actionable, dt: f32) -> Option<f32>;
+
+    // set_ease changes the timing curve of the tweens in the action.
+    fn set_ease(&mut self, _ease: Ease) {}
+}
+
+#[derive(Copy, Clone)]
+enum Prop {
+    MoveTo((f32, f32), (f32, f32)),
+    MoveBy((f32, f32), (f32, f32)),
+    ScaleTo((f32, f32), (f32, f32)),
+    RotateBy(f32, f32),
+    FadeTo(u8, u8),
+    Blink(u32, bool),
+    Delay,
+}
+
+// Tween moves one property from its value at start to a goal, the `from`
+// halves of Prop being filled in by start.
+struct Tween {
+    duration: f32,
+    elapsed: f32,
+    ease: Option<Ease>,
+    prop: Prop,
+}
+
+impl Tween {
+    fn new(duration: f32, prop: Prop) -> Box<Action> {
+        Box::new(Tween {
+            duration: duration.max(0.0),
+            elapsed: 0.0,
+            ease: None,
+            prop: prop,
+        })
+    }
+
+    // advance moves the clock on by `dt` and returns the eased progress.
+    fn advance(&mut self, dt: f32) -> f32 {
+        self.elapsed += dt;
+        let t = self.progress();
+        match self.ease {
+            Some(ref ease) if t < 1.0 => ease(t),
+            _ => t,
+        }
+    }
+
+    fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            (self.elapsed / self.duration).min(1.0)
+        } else {
+            1.0
+        }
+    }
+
+    // left is the time past the end once the tween is done.
+    fn left(&self) -> Option<f32> {
+        if self.progress() >= 1.0 {
+            Some(self.elapsed - self.duration)
+        } else {
+            None
+        }
+    }
+
+    fn apply(&self, target: &mut Actionable, t: f32) {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let mut tr = target.get_transform();
+        match self.prop {
+            Prop::MoveTo(from, to) |
+            Prop::MoveBy(from, to) => {
+                tr.x = lerp(from.0, to.0);
+                tr.y = lerp(from.1, to.1);
+            }
+            Prop::ScaleTo(from, to) => {
+                tr.scale_x = lerp(from.0, to.0);
+                tr.scale_y = lerp(from.1, to.1);
+            }
+            Prop::RotateBy(from, to) => tr.rotation = lerp(from, to),
+            Prop::FadeTo(from, to) => {
+                target.set_alpha(lerp(from as f32, to as f32).round().max(0.0).min(255.0) as u8);
+                return;
+            }
+            Prop::Blink(times, visible) => {
+                // Ends as it started, each blink being a hidden half and a shown half.
+                let phase = (t * times as f32).fract();
+                target.set_visible(if t >= 1.0 { visible } else { phase >= 0.5 });
+                return;
+            }
+            Prop::Delay => return,
+        }
+        target.set_transform(tr);
+    }
+}
+
+impl Action for Tween {
+    fn start(&mut self, target: &mut Actionable) {
+        self.elapsed = 0.0;
+        let tr = target.get_transform();
+        self.prop = match self.prop {
+            Prop::MoveTo(_, to) => Prop::MoveTo((tr.x, tr.y), to),
+            Prop::MoveBy(from, to) => {
+                // `to` minus `from` is the delta, kept across restarts.
+                let d = (to.0 - from.0, to.1 - from.1);
+                Prop::MoveBy((tr.x, tr.y), (tr.x + d.0, tr.y + d.1))
+            }
+            Prop::ScaleTo(_, to) => Prop::ScaleTo((tr.scale_x, tr.scale_y), to),
+            Prop::RotateBy(from, to) => Prop::RotateBy(tr.rotation, tr.rotation + to - from),
+            Prop::FadeTo(_, to) => Prop::FadeTo(target.get_alpha(), to),
+            Prop::Blink(times, _) => Prop::Blink(times, target.get_visible()),
+            Prop::Delay => Prop::Delay,
+        };
+    }
+
+    fn step(&mut self, target: &mut Actionable, dt: f32) -> Option<f32> {
+        let eased = self.advance(dt);
+        self.apply(target, eased);
+        self.left()
+    }
+
+    fn set_ease(&mut self, ease: Ease) {
+        self.ease = Some(ease);
+    }
+}
+
+pub fn move_to(duration: f32, x: f32, y: f32) -> Box<Action> {
+    Tween::new(duration, Prop::MoveTo((0.0, 0.0), (x, y)))
+}
+
+pub fn move_by(duration: f32, dx: f32, dy: f32) -> Box<Action> {
+    Tween::new(duration, Prop::MoveBy((0.0, 0.0), (dx, dy)))
+}
+
+pub fn scale_to(duration: f32, sx: f32, sy: f32) -> Box<Action> {
+    Tween::new(duration, Prop::ScaleTo((1.0, 1.0), (sx, sy)))
+}
+
+pub fn rotate_by(duration: f32, degrees: f32) -> Box<Action> {
+    Tween::new(duration, Prop::RotateBy(0.0, degrees))
+}
+
+pub fn fade_to(duration: f32, alpha: u8) -> Box<Action> {
+    Tween::new(duration, Prop::FadeTo(255, alpha))
+}
+
+pub fn fade_in(duration: f32) -> Box<Action> {
+    fade_to(duration, 255)
+}
+
+pub fn fade_out(duration: f32) -> Box<Action> {
+    fade_to(duration, 0)
+}
+
+// blink toggles visibility `times` times over `duration`.
+pub fn blink(duration: f32, times: u32) -> Box<Action> {
+    Tween::new(duration, Prop::Blink(times.max(1), true))
+}
+
+pub fn delay(duration: f32) -> Box<Action> {
+    Tween::new(duration, Prop::Delay)
+}
+
+// Progress hands its eased progress to a function instead of a target's
+// property, for animating values actions know nothing about.
+struct Progress<F: FnMut(f32)> {
+    tween: Tween,
+    f: F,
+}
+
+impl<F: FnMut(f32)> Action for Progress<F> {
+    fn start(&mut self, _target: &mut Actionable) {
+        self.tween.elapsed = 0.0;
+    }
+
+    fn step(&mut self, _target: &mut Actionable, dt: f32) -> Option<f32> {
+        let eased = self.tween.advance(dt);
+        (self.f)(eased);
+        self.tween.left()
+    }
+
+    fn set_ease(&mut self, ease: Ease) {
+        self.tween.ease = Some(ease);
+    }
+}
+
+// progress calls `f` every step with the progress from 0 to 1 over
+// `duration`, e.g. to count a score up.
+pub fn progress<F: FnMut(f32) + 'static>(duration: f32, f: F) -> Box<Action> {
+    Box::new(Progress {
+        tween: Tween {
+            duration: duration.max(0.0),
+            elapsed: 0.0,
+            ease: None,
+            prop: Prop::Delay,
+        },
+        f: f,
+    })
+}
+
+// ease sets the timing curve of an action, for sequences and spawns that of
+// every tween inside them.
+pub fn ease(mut action: Box<Action>, ease: Ease) -> Box<Action> {
+    action.set_ease(ease);
+    action
+}
+
+// Instant actions finish on their first step without using any time.
+struct Instant<F: FnMut(&mut Actionable)> {
+    f: F,
+}
+
+impl<F: FnMut(&mut Actionable)> Action for Instant<F> {
+    fn start(&mut self, _target: &mut Actionable) {}
+
+    fn step(&mut self, target: &mut Actionable, dt: f32) -> Option<f32> {
+        (self.f)(target);
+        Some(dt)
+    }
+}
+
+pub fn show() -> Box<Action> {
+    Box::new(Instant { f: |t: &mut Actionable| t.set_visible(true) })
+}
+
+pub fn hide() -> Box<Action> {
+    Box::new(Instant { f: |t: &mut Actionable| t.set_visible(false) })
+}
+
+// call_func runs `f` when reached. The target is borrowed while actions run,
+// so `f` must not borrow it again.
+pub fn call_func<F: FnMut() + 'static>(mut f: F) -> Box<Action> {
+    Box::new(Instant { f: move |_: &mut Actionable| f() })
+}
+
+// Sequence runs actions one after another.
+struct Sequence {
+    actions: Vec<Box<Action>>,
+    current: usize,
+}
+
+impl Action for Sequence {
+    fn start(&mut self, target: &mut Actionable) {
+        self.current = 0;
+        if let Some(first) = self.actions.first_mut() {
+            first.start(target);
+        }
+    }
+
+    fn step(&mut self, target: &mut Actionable, dt: f32) -> Option<f32> {
+        let mut dt = dt;
+        while self.current < self.actions.len() {
+            match self.actions[self.current].step(target, dt) {
+                None => return None,
+                Some(left) => {
+                    dt = left;
+                    self.current += 1;
+                    if let Some(next) = self.actions.get_mut(self.current) {
+                        next.start(target);
+                    }
+                }
+            }
+        }
+        Some(dt)
+    }
+
+    fn set_ease(&mut self, ease: Ease) {
+        for a in &mut self.actions {
+            a.set_ease(ease.clone());
+        }
+    }
+}
+
+pub fn sequence(actions: Vec<Box<Action>>) -> Box<Action> {
+    Box::new(Sequence {
+        actions: actions,
+        current: 0,
+    })
+}
+
+// Spawn runs actions side by side, finishing with the longest.
+struct Spawn {
+    actions: Vec<(Box<Action>, Option<f32>)>,
+}
+
+impl Action for Spawn {
+    fn start(&mut self, target: &mut Actionable) {
+        for &mut (ref mut a, ref mut done) in &mut self.actions {
+            a.start(target);
+            *done = None;
+        }
+    }
+
+    fn step(&mut self, target: &mut Actionable, dt: f32) -> Option<f32> {
+        let mut left = Some(dt);
+        for &mut (ref mut a, ref mut done) in &mut self.actions {
+            if done.is_none() {
+                *done = a.step(target, dt);
+            }
+            left = match (left, *done) {
+                (Some(l), Some(d)) => Some(l.min(d)),
+                _ => None,
+            };
+        }
+        left
+    }
+
+    fn set_ease(&mut self, ease: Ease) {
+        for &mut (ref mut a, _) in &mut self.actions {
+            a.set_ease(ease.clone());
+        }
+    }
+}
+
+pub fn spawn(actions: Vec<Box<Action>>) -> Box<Action> {
+    Box::new(Spawn { actions: actions.into_iter().map(|a| (a, None)).collect() })
+}
+
+// Repeat runs an action `times` times, or forever for None.
+struct Repeat {
+    action: Box<Action>,
+    times: Option<u32>,
+    count: u32,
+}
+
+impl Action for Repeat {
+    fn start(&mut self, target: &mut Actionable) {
+        self.count = 0;
+        self.action.start(target);
+    }
+
+    fn step(&mut self, target: &mut Actionable, dt: f32) -> Option<f32> {
+        let mut dt = dt;
+        loop {
+            let left = match self.action.step(target, dt) {
+                None => return None,
+                Some(left) => left,
+            };
+            self.count += 1;
+            if self.times.map_or(false, |n| self.count >= n) {
+                return Some(left);
+            }
+            self.action.start(target);
+            // An instant action repeated forever would never return.
+            if left >= dt {
+                return None;
+            }
+            dt = left;
+        }
+    }
+
+    fn set_ease(&mut self, ease: Ease) {
+        self.action.set_ease(ease);
+    }
+}
+
+pub fn repeat(action: Box<Action>, times: u32) -> Box<Action> {
+    Box::new(Repeat {
+        action: action,
+        times: Some(times),
+        count: 0,
+    })
+}
+
+pub fn repeat_forever(action: Box<Action>) -> Box<Action> {
+    Box::new(Repeat {
+        action: action,
+        times: None,
+        count: 0,
+    })
+}
+
+pub type Target = Rc<RefCell<Actionable>>;
+
+// ActionId names a running action, for stopping it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ActionId(u64);
+
+struct Running {
+    id: ActionId,
+    target: Target,
+    action: Box<Action>,
+    started: bool,
+    paused: bool,
+}
+
+// same_target compares targets by address, fat pointers to the same object
+// may carry different vtables.
+fn same_target(a: &Target, b: &Target) -> bool {
+    a.as_ptr() as *const u8 == b.as_ptr() as *const u8
+}
+
+// ActionManager runs actions on their targets, scenes own one and step it
+// every frame. A target may run several actions at once.
+pub struct ActionManager {
+    running: Vec<Running>,
+    next_id: u64,
+    paused: bool,
+}
+
+impl ActionManager {
+    pub fn new() -> ActionManager {
+        ActionManager {
+            running: Vec::new(),
+            next_id: 0,
+            paused: false,
+        }
+    }
+
+    // run starts `action` on `target` with the next update.
+    pub fn run(&mut self, target: Target, action: Box<Action>) -> ActionId {
+        self.next_id += 1;
+        let id = ActionId(self.next_id);
+        self.running.push(Running {
+            id: id,
+            target: target,
+            action: action,
+            started: false,
+            paused: false,
+        });
+        id
+    }
+
+    pub fn stop(&mut self, id: ActionId) {
+        self.running.retain(|r| r.id != id);
+    }
+
+    pub fn stop_all_for(&mut self, target: &Target) {
+        self.running.retain(|r| !same_target(&r.target, target));
+    }
+
+    pub fn stop_all(&mut self) {
+        self.running.clear();
+    }
+
+    pub fn pause_target(&mut self, target: &Target) {
+        for r in self.running.iter_mut().filter(|r| same_target(&r.target, target)) {
+            r.paused = true;
+        }
+    }
+
+    pub fn resume_target(&mut self, target: &Target) {
+        for r in self.running.iter_mut().filter(|r| same_target(&r.target, target)) {
+            r.paused = false;
+        }
+    }
+
+    // pause freezes every action, e.g. while the game is paused.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_running(&self, id: ActionId) -> bool {
+        self.running.iter().any(|r| r.id == id)
+    }
+
+    // is_animating tells whether `target` has an action running.
+    pub fn is_animating(&self, target: &Target) -> bool {
+        self.running.iter().any(|r| same_target(&r.target, target))
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if self.paused {
+            return;
+        }
+        let mut i = 0;
+        while i < self.running.len() {
+            let done = {
+                let r = &mut self.running[i];
+                if r.paused {
+                    false
+                } else {
+                    let mut target = r.target.borrow_mut();
+                    if !r.started {
+                        r.started = true;
+                        r.action.start(&mut *target);
+                    }
+                    r.action.step(&mut *target, dt).is_some()
+                }
+            };
+            if done {
+                self.running.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+impl Actionable for Sprite {
+    fn get_transform(&self) -> Transform {
+        Sprite::get_transform(self)
+    }
+    fn set_transform(&mut self, transform: Transform) {
+        Sprite::set_transform(self, transform);
+    }
+    fn get_alpha(&self) -> u8 {
+        Sprite::get_alpha(self)
+    }
+    fn set_alpha(&mut self, alpha: u8) {
+        Sprite::set_alpha(self, alpha);
+    }
+    fn get_visible(&self) -> bool {
+        Sprite::get_visible(self)
+    }
+    fn set_visible(&mut self, visible: bool) {
+        if visible {
+            self.show();
+        } else {
+            self.hide();
+        }
+    }
+}
+
+impl Actionable for Animation {
+    fn get_transform(&self) -> Transform {
+        Animation::get_transform(self)
+    }
+    fn set_transform(&mut self, transform: Transform) {
+        Animation::set_transform(self, transform);
+    }
+    fn get_alpha(&self) -> u8 {
+        Animation::get_alpha(self)
+    }
+    fn set_alpha(&mut self, alpha: u8) {
+        Animation::set_alpha(self, alpha);
+    }
+    fn get_visible(&self) -> bool {
+        Animation::get_visible(self)
+    }
+    fn set_visible(&mut self, visible: bool) {
+        if visible {
+            self.show();
+        } else {
+            self.hide();
+        }
+    }
+}
+
+// A TexElement has no position of its own, only its scale and angle move;
+// wrap it in a Sprite to move it around.
+impl Actionable for TexElement {
+    fn get_transform(&self) -> Transform {
+        let mut t = Transform::identity();
+        let (sx, sy) = self.get_scale();
+        t.scale_x = sx;
+        t.scale_y = sy;
+        t.rotation = self.get_angle() as f32;
+        t
+    }
+    fn set_transform(&mut self, transform: Transform) {
+        self.set_scale(transform.scale_x, transform.scale_y);
+        self.set_angle(transform.rotation as f64);
+    }
+    fn get_alpha(&self) -> u8 {
+        TexElement::get_alpha(self)
+    }
+    fn set_alpha(&mut self, alpha: u8) {
+        TexElement::set_alpha(self, alpha);
+    }
+    fn get_visible(&self) -> bool {
+        TexElement::get_visible(self)
+    }
+    fn set_visible(&mut self, visible: bool) {
+        TexElement::set_visible(self, visible);
+    }
+}
+
+impl Actionable for Group {
+    fn get_transform(&self) -> Transform {
+        Group::get_transform(self)
+    }
+    fn set_transform(&mut self, transform: Transform) {
+        Group::set_transform(self, transform);
+    }
+    fn get_visible(&self) -> bool {
+        Group::get_visible(self)
+    }
+    fn set_visible(&mut self, visible: bool) {
+        if visible {
+            self.show();
+        } else {
+            self.hide();
+        }
+    }
+}
+
+impl Actionable for Node {
+    fn get_transform(&self) -> Transform {
+        Node::get_transform(self)
+    }
+    fn set_transform(&mut self, transform: Transform) {
+        Node::set_transform(self, transform);
+    }
+    fn get_visible(&self) -> bool {
+        Node::get_visible(self)
+    }
+    fn set_visible(&mut self, visible: bool) {
+        if visible {
+            self.show();
+        } else {
+            self.hide();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::cell::Cell;
+
+    const EPSILON: f32 = 1e-4;
+
+    struct Dot {
+        transform: Transform,
+        alpha: u8,
+        visible: bool,
+    }
+
+    impl Actionable for Dot {
+        fn get_transform(&self) -> Transform {
+            self.transform
+        }
+        fn set_transform(&mut self, transform: Transform) {
+            self.transform = transform;
+        }
+        fn get_alpha(&self) -> u8 {
+            self.alpha
+        }
+        fn set_alpha(&mut self, alpha: u8) {
+            self.alpha = alpha;
+        }
+        fn get_visible(&self) -> bool {
+            self.visible
+        }
+        fn set_visible(&mut self, visible: bool) {
+            self.visible = visible;
+        }
+    }
+
+    fn dot() -> Dot {
+        Dot {
+            transform: Transform::identity(),
+            alpha: 255,
+            visible: true,
+        }
+    }
+
+    fn at(d: &Dot) -> (f32, f32) {
+        (d.transform.x, d.transform.y)
+    }
+
+    fn close(a: Option<f32>, b: f32) -> bool {
+        a.map_or(false, |a| (a - b).abs() < EPSILON)
+    }
+
+    #[test]
+    fn test_tween_timing() {
+        let mut d = dot();
+        let mut a = move_to(2.0, 10.0, 20.0);
+        a.start(&mut d);
+        assert_eq!(a.step(&mut d, 0.5), None);
+        assert_eq!(at(&d), (2.5, 5.0));
+        // Past the end the tween lands on its goal and hands back the rest.
+        assert!(close(a.step(&mut d, 2.0), 0.5));
+        assert_eq!(at(&d), (10.0, 20.0));
+    }
+
+    #[test]
+    fn test_tween_ease() {
+        let mut d = dot();
+        let mut a = ease(move_by(1.0, 100.0, 0.0), Rc::new(|t: f32| t * t));
+        a.start(&mut d);
+        a.step(&mut d, 0.5);
+        assert_eq!(at(&d), (25.0, 0.0));
+        a.step(&mut d, 0.5);
+        assert_eq!(at(&d), (100.0, 0.0));
+    }
+
+    #[test]
+    fn test_zero_duration() {
+        let mut d = dot();
+        let mut a = fade_out(0.0);
+        a.start(&mut d);
+        assert!(close(a.step(&mut d, 0.25), 0.25));
+        assert_eq!(d.alpha, 0);
+    }
+
+    #[test]
+    fn test_sequence_carries_time_over() {
+        let mut d = dot();
+        let mut a = sequence(vec![move_by(1.0, 10.0, 0.0), delay(0.5), move_by(1.0, 0.0, 10.0)]);
+        a.start(&mut d);
+        assert_eq!(a.step(&mut d, 1.25), None);
+        assert_eq!(at(&d), (10.0, 0.0));
+        // Half of the step finishes the delay, the other half moves down.
+        assert_eq!(a.step(&mut d, 0.5), None);
+        assert_eq!(at(&d), (10.0, 2.5));
+        assert!(close(a.step(&mut d, 1.0), 0.25));
+        assert_eq!(at(&d), (10.0, 10.0));
+    }
+
+    #[test]
+    fn test_spawn_ends_with_longest() {
+        let mut d = dot();
+        let mut a = spawn(vec![move_by(1.0, 10.0, 0.0), fade_out(2.0)]);
+        a.start(&mut d);
+        assert_eq!(a.step(&mut d, 1.5), None);
+        assert_eq!(at(&d), (10.0, 0.0));
+        assert!(close(a.step(&mut d, 1.0), 0.5));
+        assert_eq!(d.alpha, 0);
+    }
+
+    #[test]
+    fn test_repeat_counts() {
+        let mut d = dot();
+        let mut a = repeat(move_by(1.0, 10.0, 0.0), 3);
+        a.start(&mut d);
+        // Moves by are relative, each repeat going on from the last.
+        assert_eq!(a.step(&mut d, 2.5), None);
+        assert_eq!(at(&d), (25.0, 0.0));
+        assert!(close(a.step(&mut d, 1.0), 0.5));
+        assert_eq!(at(&d), (30.0, 0.0));
+    }
+
+    #[test]
+    fn test_repeat_forever() {
+        let mut d = dot();
+        let mut a = repeat_forever(sequence(vec![move_by(0.5, 0.0, 4.0), move_by(0.5, 0.0, -4.0)]));
+        a.start(&mut d);
+        for _ in 0..9 {
+            assert_eq!(a.step(&mut d, 0.25), None);
+        }
+        // A quarter into the third bob, halfway down.
+        assert!((d.transform.y - 2.0).abs() < EPSILON);
+
+        // An instant action repeated forever gives the time back every step.
+        let mut a = repeat_forever(hide());
+        a.start(&mut d);
+        assert_eq!(a.step(&mut d, 0.1), None);
+        assert!(!d.visible);
+    }
+
+    #[test]
+    fn test_progress() {
+        let seen = Rc::new(Cell::new(0.0));
+        let mut d = dot();
+        let mut a = {
+            let seen = seen.clone();
+            progress(2.0, move |t| seen.set(t))
+        };
+        a.start(&mut d);
+        a.step(&mut d, 0.5);
+        assert_eq!(seen.get(), 0.25);
+        assert!(close(a.step(&mut d, 2.0), 0.5));
+        assert_eq!(seen.get(), 1.0);
+    }
+
+    #[test]
+    fn test_manager() {
+        let d = Rc::new(RefCell::new(dot()));
+        let mut actions = ActionManager::new();
+        let id = actions.run(d.clone(), move_to(1.0, 10.0, 0.0));
+        let target: Target = d.clone();
+        assert!(actions.is_animating(&target));
+
+        actions.update(0.5);
+        assert_eq!(at(&d.borrow()), (5.0, 0.0));
+        actions.pause();
+        actions.update(0.5);
+        assert_eq!(at(&d.borrow()), (5.0, 0.0));
+        actions.resume();
+        actions.update(0.5);
+        assert_eq!(at(&d.borrow()), (10.0, 0.0));
+        assert!(!actions.is_running(id));
+
+        actions.run(d.clone(), move_to(1.0, 0.0, 0.0));
+        actions.stop_all_for(&target);
+        assert!(!actions.is_animating(&target));
+    }
+}
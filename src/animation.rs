@@ -14,13 +14,14 @@ use std::rc::Rc;
 use std::cell::RefCell;
 
 use display::{Displayable, Float, FloatToDuration};
-use atlas::TexElement;
+use atlas::{TexElement, Tint};
 use sprite::Sprite;
+use transform::Transform;
 
 // #[derive(Debug)]
 pub struct Animation {
-    x: i32,
-    y: i32,
+    transform: Transform,
+    tint: Tint,
     visible: bool,
     running: bool,
     interval: f32,
@@ -37,8 +38,8 @@ impl Animation {
             texs.push(Rc::new(texture));
         }
         Animation {
-            x: 0,
-            y: 0,
+            transform: Transform::identity(),
+            tint: Tint::white(),
             visible: true,
             running: true,
             interval: 0.0,
@@ -50,8 +51,8 @@ impl Animation {
 
     pub fn new_from_texture(texs: Vec<Rc<TexElement>>) -> Animation {
         Animation {
-            x: 0,
-            y: 0,
+            transform: Transform::identity(),
+            tint: Tint::white(),
             visible: true,
             running: true,
             interval: 0.0,
@@ -60,6 +61,11 @@ impl Animation {
             texs: texs,
         }
     }
+    // get_textures shares the frames, e.g. with a second animation.
+    pub fn get_textures(&self) -> Vec<Rc<TexElement>> {
+        self.texs.clone()
+    }
+
     pub fn get_size(&self) -> (u32, u32) {
         self.texs[0].as_ref().get_size()
 
@@ -74,12 +80,46 @@ impl Animation {
     }
 
     pub fn set_position(&mut self, x: i32, y: i32) {
-        self.x = x;
-        self.y = y;
+        self.transform.x = x as f32;
+        self.transform.y = y as f32;
     }
 
     pub fn get_position(&self) -> (i32, i32) {
-        (self.x, self.y)
+        (self.transform.x as i32, self.transform.y as i32)
+    }
+
+    pub fn set_scale(&mut self, sx: f32, sy: f32) {
+        self.transform.scale_x = sx;
+        self.transform.scale_y = sy;
+    }
+
+    pub fn get_scale(&self) -> (f32, f32) {
+        (self.transform.scale_x, self.transform.scale_y)
+    }
+
+    pub fn set_rotation(&mut self, degrees: f32) {
+        self.transform.rotation = degrees;
+    }
+
+    pub fn get_rotation(&self) -> f32 {
+        self.transform.rotation
+    }
+
+    pub fn set_anchor(&mut self, ax: f32, ay: f32) {
+        self.transform.anchor_x = ax;
+        self.transform.anchor_y = ay;
+    }
+
+    pub fn get_anchor(&self) -> (f32, f32) {
+        (self.transform.anchor_x, self.transform.anchor_y)
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    pub fn get_transform(&self) -> Transform {
+        self.transform
     }
 
     pub fn start(&mut self) {
@@ -134,6 +174,36 @@ impl Animation {
         self.visible = true;
         self
     }
+
+    pub fn get_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_color(&mut self, r: u8, g: u8, b: u8) {
+        self.tint.r = r;
+        self.tint.g = g;
+        self.tint.b = b;
+    }
+
+    pub fn get_color(&self) -> (u8, u8, u8) {
+        (self.tint.r, self.tint.g, self.tint.b)
+    }
+
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.tint.a = alpha;
+    }
+
+    pub fn get_alpha(&self) -> u8 {
+        self.tint.a
+    }
+
+    pub fn set_tint(&mut self, tint: Tint) {
+        self.tint = tint;
+    }
+
+    pub fn get_tint(&self) -> Tint {
+        self.tint
+    }
 }
 
 impl Displayable for Animation {
@@ -146,10 +216,13 @@ impl Displayable for Animation {
     }
 
     fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
         if self.visible {
             let idx = self.cursor as usize % self.texs.len();
-            self.texs[idx].paint_ex(renderer,
-                                    Rect::new(self.x, self.y, self.width(), self.height()));
+            self.texs[idx].paint_tinted(renderer, &parent.then(&self.transform), &self.tint);
         }
     }
 
@@ -1,12 +1,13 @@
 extern crate sdl2;
 
 use sdl2::rect::{Rect, Point};
+use sdl2::pixels::Color;
 use sdl2::render::{Renderer, Texture, BlendMode};
 use sdl2::image::LoadTexture;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 
-use std::cell::RefCell;
+use std::cell::{Ref, RefCell};
 use std::collections::HashMap;
 use std::fmt;
 use std::io::{BufReader, BufRead};
@@ -16,6 +17,7 @@ use std::path::Path;
 
 use display::Displayable;
 use node::Node;
+use transform::Transform;
 
 pub trait Element {
     fn hide(&mut self);
@@ -43,27 +45,76 @@ pub trait Element {
     fn get_visible(&self) -> bool;
 }
 
+// Tint is a per-instance color modulation and opacity, multiplied into the
+// texture's colors at paint time. White and opaque leaves them untouched.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Tint {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Tint {
+    pub fn white() -> Tint {
+        Tint::rgba(255, 255, 255, 255)
+    }
+
+    pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Tint {
+        Tint {
+            r: r,
+            g: g,
+            b: b,
+            a: a,
+        }
+    }
+
+    pub fn from_color(color: Color) -> Tint {
+        let (r, g, b, a) = color.rgba();
+        Tint::rgba(r, g, b, a)
+    }
+
+    // modulate combines two tints the way SDL combines color and alpha mods.
+    pub fn modulate(&self, other: &Tint) -> Tint {
+        fn mul(x: u8, y: u8) -> u8 {
+            ((x as u32 * y as u32 + 127) / 255) as u8
+        }
+        Tint::rgba(mul(self.r, other.r),
+                   mul(self.g, other.g),
+                   mul(self.b, other.b),
+                   mul(self.a, other.a))
+    }
+}
+
 // #[derive(Debug)]
 pub struct TexElement {
     flip_h: bool,
     flip_v: bool,
     angle: f64,
+    scale_x: f32,
+    scale_y: f32,
+    tint: Tint,
+    blend: BlendMode,
     visible_rect: Rect,
     visible: bool,
-    center: Point,
+    // Rotation pivot as a fraction of the size, the transform anchor if unset.
+    pivot: Option<(f32, f32)>,
     rect: Rect,
-    texture: Rc<Texture>,
+    // Atlas elements share one texture. Color, alpha and blend mode are set
+    // on it right before each copy, so one element never leaks into another.
+    texture: Rc<RefCell<Texture>>,
 }
 
 impl fmt::Display for TexElement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f,
-               "TexElement {} {} {} {} {:?} {:?}",
+               "TexElement {} {} {} {} {:?} {:?} {:?}",
                self.flip_h,
                self.flip_v,
                self.angle,
                self.visible,
-               self.center,
+               self.pivot,
+               self.tint,
                self.rect)
     }
 }
@@ -73,14 +124,18 @@ impl TexElement {
         renderer.load_texture(Path::new(path))
     }
 
-    pub fn new_from_texture(texture: Rc<Texture>, rect: Rect) -> TexElement {
+    pub fn new_from_texture(texture: Rc<RefCell<Texture>>, rect: Rect) -> TexElement {
         TexElement {
             flip_v: false,
             flip_h: false,
             angle: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            tint: Tint::white(),
+            blend: BlendMode::Blend,
             visible_rect: rect,
             visible: true,
-            center: rect.center(),
+            pivot: None,
             rect: rect,
             texture: texture,
         }
@@ -92,16 +147,7 @@ impl TexElement {
                                   .unwrap();
         let tquery = texture.query();
         let rect = Rect::new(0, 0, tquery.width, tquery.height);
-        TexElement {
-            flip_v: false,
-            flip_h: false,
-            angle: 0.0,
-            visible_rect: rect,
-            visible: true,
-            center: rect.center(),
-            rect: rect,
-            texture: Rc::new(texture),
-        }
+        TexElement::new_from_texture(Rc::new(RefCell::new(texture)), rect)
     }
 
     pub fn get_size(&self) -> (u32, u32) {
@@ -173,7 +219,7 @@ impl TexElement {
         self
     }
 
-    pub fn get_angle(&self, angle: f64) -> f64 {
+    pub fn get_angle(&self) -> f64 {
         self.angle
     }
 
@@ -187,56 +233,166 @@ impl TexElement {
         (self.flip_h, self.flip_v)
     }
 
+    // set_center sets the rotation pivot in pixels from the top-left corner.
     pub fn set_center<'a>(&'a mut self, x: i32, y: i32) -> &'a mut TexElement {
-        self.center = Point::new(x, y);
+        let size = self.get_size();
+        self.pivot = Some((x as f32 / size.0 as f32, y as f32 / size.1 as f32));
+        self
+    }
+
+    pub fn get_center(&self) -> Option<(i32, i32)> {
+        let size = self.get_size();
+        self.pivot.map(|(px, py)| ((px * size.0 as f32) as i32, (py * size.1 as f32) as i32))
+    }
+
+    // set_pivot sets the rotation pivot as a fraction of the size,
+    // (0.5, 0.5) being the middle of the element.
+    pub fn set_pivot<'a>(&'a mut self, px: f32, py: f32) -> &'a mut TexElement {
+        self.pivot = Some((px, py));
+        self
+    }
+
+    pub fn get_pivot(&self) -> Option<(f32, f32)> {
+        self.pivot
+    }
+
+    pub fn set_scale<'a>(&'a mut self, sx: f32, sy: f32) -> &'a mut TexElement {
+        self.scale_x = sx;
+        self.scale_y = sy;
+        self
+    }
+
+    pub fn get_scale(&self) -> (f32, f32) {
+        (self.scale_x, self.scale_y)
+    }
+
+    pub fn set_color<'a>(&'a mut self, r: u8, g: u8, b: u8) -> &'a mut TexElement {
+        self.tint.r = r;
+        self.tint.g = g;
+        self.tint.b = b;
         self
     }
 
-    pub fn get_center(&self) -> (i32, i32) {
-        (self.center.x(), self.center.y())
+    pub fn get_color(&self) -> (u8, u8, u8) {
+        (self.tint.r, self.tint.g, self.tint.b)
+    }
+
+    // set_alpha is the opacity, only visible with the Blend and Add blend modes.
+    pub fn set_alpha<'a>(&'a mut self, alpha: u8) -> &'a mut TexElement {
+        self.tint.a = alpha;
+        self
+    }
+
+    pub fn get_alpha(&self) -> u8 {
+        self.tint.a
+    }
+
+    pub fn set_tint(&mut self, tint: Tint) {
+        self.tint = tint;
+    }
+
+    pub fn get_tint(&self) -> Tint {
+        self.tint
+    }
+
+    pub fn set_blend_mode(&mut self, blend: BlendMode) {
+        self.blend = blend;
+    }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.blend
     }
 
     pub fn blend_mode_none(&mut self) {
-        Rc::get_mut(&mut self.texture)
-            .unwrap()
-            .set_blend_mode(BlendMode::None);
+        self.blend = BlendMode::None;
     }
     pub fn blend_mode_add(&mut self) {
-        Rc::get_mut(&mut self.texture)
-            .unwrap()
-            .set_blend_mode(BlendMode::Add);
+        self.blend = BlendMode::Add;
     }
     pub fn blend_mode_mod(&mut self) {
-        Rc::get_mut(&mut self.texture)
-            .unwrap()
-            .set_blend_mode(BlendMode::Mod);
+        self.blend = BlendMode::Mod;
     }
     pub fn blend_mode_blend(&mut self) {
-        Rc::get_mut(&mut self.texture)
-            .unwrap()
-            .set_blend_mode(BlendMode::Blend);
-    }
-
-    pub fn get_texture(&self) -> &Texture {
-        &self.texture
-    }
+        self.blend = BlendMode::Blend;
+    }
+
+    pub fn get_texture(&self) -> Ref<Texture> {
+        self.texture.borrow()
+    }
+
+    // paint_ex paints the element with its resolved world transform.
+    // A negative scale mirrors the element on that axis.
+    pub fn paint_ex(&self, renderer: &mut Renderer, world: &Transform) {
+        self.paint_tinted(renderer, world, &Tint::white());
+    }
+
+    // paint_tinted paints like paint_ex with `tint` modulated over the element's own,
+    // so sprites sharing an element can still be tinted one by one.
+    pub fn paint_tinted(&self, renderer: &mut Renderer, world: &Transform, tint: &Tint) {
+        self.paint_blended(renderer, world, tint, self.blend);
+    }
+
+    // paint_blended paints like paint_tinted with another blend mode, so a
+    // shared atlas element can be drawn additively without changing it.
+    pub fn paint_blended(&self,
+                         renderer: &mut Renderer,
+                         world: &Transform,
+                         tint: &Tint,
+                         blend: BlendMode) {
+        let size = self.get_size();
+        let mut local = Transform::identity();
+        local.scale_x = self.scale_x;
+        local.scale_y = self.scale_y;
+        local.anchor_x = world.anchor_x;
+        local.anchor_y = world.anchor_y;
+        let world = world.then(&local);
+        let (rect, anchor) = world.dest_rect(size.0, size.1);
+        let pivot = match self.pivot {
+            Some((px, py)) => Point::new((px * rect.width() as f32) as i32,
+                                         (py * rect.height() as f32) as i32),
+            None => anchor,
+        };
 
-    pub fn paint_ex(&self, renderer: &mut Renderer, rect: Rect) {
-        renderer.copy_ex(&self.texture,
+        let texture = self.prepare(tint, blend);
+        renderer.copy_ex(&texture,
                           Some(self.visible_rect),
-                          Some(rect), self.angle, Some(self.center), self.flip_h, self.flip_v)
-                          // Some(Rect::new(self.x, self.y, self.w, self.h)))
+                          Some(rect),
+                          self.angle + world.rotation as f64,
+                          Some(pivot),
+                          self.flip_h != (world.scale_x < 0.0),
+                          self.flip_v != (world.scale_y < 0.0))
                     .expect("layer should have rendered.");
 
     }
+
+    // paint_src copies `src` of the texture to `dst` in screen space,
+    // for callers doing their own tiling such as scrolling layers.
+    pub fn paint_src(&self, renderer: &mut Renderer, src: Option<Rect>, dst: Rect, tint: &Tint) {
+        let texture = self.prepare(tint, self.blend);
+        renderer.copy(&texture, src, Some(dst))
+                .expect("background should have rendered.");
+    }
+
+    fn prepare(&self, tint: &Tint, blend: BlendMode) -> Ref<Texture> {
+        let tint = self.tint.modulate(tint);
+        {
+            let mut texture = self.texture.borrow_mut();
+            texture.set_color_mod(tint.r, tint.g, tint.b);
+            texture.set_alpha_mod(tint.a);
+            texture.set_blend_mode(blend);
+        }
+        self.texture.borrow()
+    }
 }
 
 impl Displayable for TexElement {
     fn update(&mut self) {}
     fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
         if self.visible {
-            let size = self.get_size();
-            self.paint_ex(renderer, Rect::new(0, 0, size.0, size.1));
+            self.paint_ex(renderer, parent);
         }
     }
 }
@@ -249,7 +405,7 @@ pub fn TexLoader(renderer: &Renderer,
     let file = File::open(atlpath).unwrap();
     let mut tex = renderer.load_texture(Path::new(texpath))
                           .unwrap();
-    let texture = Rc::new(tex);
+    let texture = Rc::new(RefCell::new(tex));
 
     for line in BufReader::new(file).lines() {
         let l = line.unwrap();
@@ -261,16 +417,7 @@ pub fn TexLoader(renderer: &Renderer,
                              (items.next().unwrap().parse::<f32>().unwrap() * 1024.0 + 0.1) as i32,
                              w,
                              h);
-        let a = TexElement {
-            flip_v: false,
-            flip_h: false,
-            angle: 0.0,
-            visible_rect: rect,
-            visible: true,
-            center: rect.center(),
-            rect: rect,
-            texture: texture.clone(),
-        };
+        let a = TexElement::new_from_texture(texture.clone(), rect);
 
         map.insert(name.to_string(), Rc::new(RefCell::new(a)));
     }
@@ -0,0 +1,446 @@
+extern crate sdl2;
+
+use std::cell::RefCell;
+use std::char;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::rc::Rc;
+
+use sdl2::image::LoadTexture;
+use sdl2::rect::Rect;
+use sdl2::render::Renderer;
+
+use xml::reader::{EventReader, XmlEvent};
+
+use atlas::{TexElement, Tint};
+use transform::Transform;
+
+// CharData is one glyph of a BMFont: where it is on its page, how it sits
+// on the line and how far it moves the pen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharData {
+    pub id: u32,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: i32,
+    pub y_offset: i32,
+    pub x_advance: i32,
+    pub page: usize,
+}
+
+// FontData is an AngelCode BMFont description, before its pages are loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontData {
+    pub line_height: i32,
+    pub base: i32,
+    // The number of pages the common tag announces, page ids are below it.
+    pub page_count: usize,
+    pub pages: Vec<String>,
+    pub chars: Vec<CharData>,
+    // First and second character, and the pen adjustment between them.
+    pub kernings: Vec<(u32, u32, i32)>,
+}
+
+impl FontData {
+    pub fn new() -> FontData {
+        FontData {
+            line_height: 0,
+            base: 0,
+            page_count: 0,
+            pages: Vec::new(),
+            chars: Vec::new(),
+            kernings: Vec::new(),
+        }
+    }
+
+    // add_record takes one tag of the file, in either format.
+    fn add_record(&mut self, tag: &str, attrs: &[(String, String)]) -> Result<(), String> {
+        let get = |name: &str| attrs.iter().find(|a| a.0 == name).map(|a| a.1.as_str());
+        let int = |name: &str| -> Result<i32, String> {
+            match get(name) {
+                Some(v) => v.parse::<i32>().map_err(|_| format!("bad {} '{}'", name, v)),
+                None => Ok(0),
+            }
+        };
+        // Ids, sizes and page numbers can't be negative.
+        let uint = |name: &str| -> Result<u32, String> {
+            match get(name) {
+                Some(v) => v.parse::<u32>().map_err(|_| format!("bad {} '{}'", name, v)),
+                None => Ok(0),
+            }
+        };
+        match tag {
+            "common" => {
+                self.line_height = int("lineHeight")?;
+                self.base = int("base")?;
+                self.page_count = uint("pages")? as usize;
+            }
+            "page" => {
+                let id = uint("id")? as usize;
+                if id >= self.page_count {
+                    return Err(format!("page {} past the {} pages", id, self.page_count));
+                }
+                let file = get("file").ok_or_else(|| "page without a file".to_string())?;
+                if self.pages.len() <= id {
+                    self.pages.resize(id + 1, String::new());
+                }
+                self.pages[id] = file.to_string();
+            }
+            "char" => {
+                self.chars.push(CharData {
+                    id: uint("id")?,
+                    x: int("x")?,
+                    y: int("y")?,
+                    width: uint("width")?,
+                    height: uint("height")?,
+                    x_offset: int("xoffset")?,
+                    y_offset: int("yoffset")?,
+                    x_advance: int("xadvance")?,
+                    page: uint("page")? as usize,
+                });
+            }
+            "kerning" => {
+                self.kernings.push((uint("first")?, uint("second")?, int("amount")?));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+// parse_line splits a line of the text format, `tag key=value key="a b"`.
+fn parse_line(line: &str) -> (String, Vec<(String, String)>) {
+    let line = line.trim();
+    let (tag, mut rest) = match line.find(' ') {
+        Some(i) => (&line[..i], &line[i + 1..]),
+        None => (line, ""),
+    };
+    let mut attrs = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        let eq = match rest.find('=') {
+            Some(i) => i,
+            None => break,
+        };
+        let key = rest[..eq].trim().to_string();
+        rest = &rest[eq + 1..];
+        let value = if rest.starts_with('"') {
+            let end = rest[1..].find('"').map_or(rest.len(), |i| i + 1);
+            let value = rest[1..end].to_string();
+            rest = &rest[(end + 1).min(rest.len())..];
+            value
+        } else {
+            let end = rest.find(' ').unwrap_or(rest.len());
+            let value = rest[..end].to_string();
+            rest = &rest[end..];
+            value
+        };
+        attrs.push((key, value));
+    }
+    (tag.to_string(), attrs)
+}
+
+// parse_fnt_text parses the text format of BMFont.
+pub fn parse_fnt_text(text: &str) -> Result<FontData, String> {
+    let mut data = FontData::new();
+    for line in text.lines() {
+        let (tag, attrs) = parse_line(line);
+        data.add_record(&tag, &attrs)?;
+    }
+    Ok(data)
+}
+
+// parse_fnt_xml parses the XML format of BMFont.
+pub fn parse_fnt_xml(text: &str) -> Result<FontData, String> {
+    let mut data = FontData::new();
+    for event in EventReader::new(text.as_bytes()) {
+        if let XmlEvent::StartElement { name, attributes, .. } = event.map_err(|e| e.to_string())? {
+            let attrs: Vec<(String, String)> = attributes.into_iter()
+                                                         .map(|a| (a.name.local_name, a.value))
+                                                         .collect();
+            data.add_record(&name.local_name, &attrs)?;
+        }
+    }
+    Ok(data)
+}
+
+// parse_fnt parses either format, telling them apart by the first character.
+pub fn parse_fnt(text: &str) -> Result<FontData, String> {
+    if text.trim_start().starts_with('<') {
+        parse_fnt_xml(text)
+    } else {
+        parse_fnt_text(text)
+    }
+}
+
+// Glyph is a character's image, None for blank ones like the space, and
+// its placement from the pen at the top of the line.
+pub struct Glyph {
+    pub tex: Option<Rc<RefCell<TexElement>>>,
+    pub x_offset: i32,
+    pub y_offset: i32,
+    pub advance: i32,
+}
+
+// BitmapFont draws text from images of its characters, either cut from the
+// pages of a BMFont file or taken from atlas elements. The glyphs of a page
+// are elements of one shared texture, as with the atlas.
+pub struct BitmapFont {
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), i32>,
+    line_height: i32,
+    // Extra pixels between characters.
+    spacing: i32,
+}
+
+impl BitmapFont {
+    pub fn new(line_height: i32) -> BitmapFont {
+        BitmapFont {
+            glyphs: HashMap::new(),
+            kerning: HashMap::new(),
+            line_height: line_height,
+            spacing: 0,
+        }
+    }
+
+    // load reads a BMFont .fnt file in the text or XML format, with its
+    // pages next to it.
+    pub fn load(renderer: &Renderer, path: &str) -> Result<BitmapFont, String> {
+        let mut text = String::new();
+        File::open(path).and_then(|mut f| f.read_to_string(&mut text))
+                        .map_err(|e| format!("{}: {}", path, e))?;
+        let data = parse_fnt(&text)?;
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        let mut pages = Vec::new();
+        for page in &data.pages {
+            let texture = renderer.load_texture(&dir.join(page))?;
+            pages.push(Rc::new(RefCell::new(texture)));
+        }
+
+        let mut font = BitmapFont::new(data.line_height);
+        for c in &data.chars {
+            let ch = match char::from_u32(c.id) {
+                Some(ch) => ch,
+                None => continue,
+            };
+            let page = pages.get(c.page).ok_or_else(|| format!("{}: no page {}", path, c.page))?;
+            let tex = if c.width > 0 && c.height > 0 {
+                let rect = Rect::new(c.x, c.y, c.width, c.height);
+                Some(Rc::new(RefCell::new(TexElement::new_from_texture(page.clone(), rect))))
+            } else {
+                None
+            };
+            font.add_glyph(ch,
+                           Glyph {
+                               tex: tex,
+                               x_offset: c.x_offset,
+                               y_offset: c.y_offset,
+                               advance: c.x_advance,
+                           });
+        }
+        for &(first, second, amount) in &data.kernings {
+            if let (Some(a), Some(b)) = (char::from_u32(first), char::from_u32(second)) {
+                font.set_kerning(a, b, amount);
+            }
+        }
+        Ok(font)
+    }
+
+    // from_atlas makes a font of atlas elements named by the prefix and the
+    // character code, e.g. "font_" and "0123456789" for font_048 to font_057.
+    pub fn from_atlas(atlas: &HashMap<String, Rc<RefCell<TexElement>>>,
+                      prefix: &str,
+                      chars: &str)
+                      -> Result<BitmapFont, String> {
+        let mut font = BitmapFont::new(0);
+        for ch in chars.chars() {
+            let name = format!("{}{:03}", prefix, ch as u32);
+            let tex = atlas.get(&name).ok_or_else(|| format!("no atlas element '{}'", name))?;
+            let (w, h) = tex.borrow().get_size();
+            font.line_height = font.line_height.max(h as i32);
+            font.add_glyph(ch,
+                           Glyph {
+                               tex: Some(tex.clone()),
+                               x_offset: 0,
+                               y_offset: 0,
+                               advance: w as i32,
+                           });
+        }
+        Ok(font)
+    }
+
+    pub fn add_glyph(&mut self, ch: char, glyph: Glyph) {
+        self.glyphs.insert(ch, glyph);
+    }
+
+    // set_kerning moves `second` by `amount` when it follows `first`.
+    pub fn set_kerning(&mut self, first: char, second: char, amount: i32) {
+        self.kerning.insert((first, second), amount);
+    }
+
+    pub fn set_line_height(&mut self, h: i32) {
+        self.line_height = h;
+    }
+
+    pub fn get_line_height(&self) -> i32 {
+        self.line_height
+    }
+
+    pub fn set_spacing(&mut self, spacing: i32) {
+        self.spacing = spacing;
+    }
+
+    // layout walks the text, calling `f` with each glyph and where its
+    // top left goes, unscaled, and returns the size of the text.
+    // Characters the font lacks are skipped.
+    fn layout<F: FnMut(&Glyph, i32, i32)>(&self, text: &str, mut f: F) -> (u32, u32) {
+        let (mut x, mut y, mut w) = (0, 0, 0);
+        let mut prev = None;
+        for ch in text.chars() {
+            if ch == '\n' {
+                x = 0;
+                y += self.line_height;
+                prev = None;
+                continue;
+            }
+            let glyph = match self.glyphs.get(&ch) {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            if let Some(p) = prev {
+                x += self.spacing + self.kerning.get(&(p, ch)).cloned().unwrap_or(0);
+            }
+            f(glyph, x + glyph.x_offset, y + glyph.y_offset);
+            x += glyph.advance;
+            w = w.max(x);
+            prev = Some(ch);
+        }
+        (w.max(0) as u32, (y + self.line_height).max(0) as u32)
+    }
+
+    // measure is the size of the text at scale 1.
+    pub fn measure(&self, text: &str) -> (u32, u32) {
+        self.layout(text, |_, _, _| {})
+    }
+
+    // paint_text paints the text with its top left at (x, y) in the parent.
+    pub fn paint_text(&self,
+                      renderer: &mut Renderer,
+                      parent: &Transform,
+                      text: &str,
+                      x: f32,
+                      y: f32,
+                      scale: f32,
+                      tint: &Tint) {
+        self.layout(text, |glyph, gx, gy| {
+            if let Some(ref tex) = glyph.tex {
+                let mut local = Transform::identity();
+                local.x = x + gx as f32 * scale;
+                local.y = y + gy as f32 * scale;
+                local.scale_x = scale;
+                local.scale_y = scale;
+                tex.borrow().paint_tinted(renderer, &parent.then(&local), tint);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_line_quoted() {
+        let (tag, attrs) = parse_line(r#"info face="Open Sans" size=32 bold=0"#);
+        assert_eq!(tag, "info");
+        assert_eq!(attrs,
+                   vec![("face".to_string(), "Open Sans".to_string()),
+                        ("size".to_string(), "32".to_string()),
+                        ("bold".to_string(), "0".to_string())]);
+    }
+
+    #[test]
+    fn test_parse_text_and_xml_agree() {
+        let text = "common lineHeight=32 base=26 scaleW=256 scaleH=256 pages=1\n\
+                    page id=0 file=\"digits_0.png\"\n\
+                    chars count=1\n\
+                    char id=48 x=2 y=3 width=14 height=20 xoffset=1 yoffset=6 xadvance=16 page=0\n\
+                    kernings count=1\n\
+                    kerning first=49 second=48 amount=-2\n";
+        let xml = r#"<?xml version="1.0"?>
+<font>
+  <common lineHeight="32" base="26" scaleW="256" scaleH="256" pages="1"/>
+  <pages><page id="0" file="digits_0.png"/></pages>
+  <chars count="1">
+    <char id="48" x="2" y="3" width="14" height="20" xoffset="1" yoffset="6" xadvance="16" page="0"/>
+  </chars>
+  <kernings count="1"><kerning first="49" second="48" amount="-2"/></kernings>
+</font>"#;
+        let data = parse_fnt(text).unwrap();
+        assert_eq!(data, parse_fnt(xml).unwrap());
+        assert_eq!(data.line_height, 32);
+        assert_eq!(data.pages, vec!["digits_0.png"]);
+        assert_eq!(data.chars[0].x_advance, 16);
+        assert_eq!(data.kernings, vec![(49, 48, -2)]);
+    }
+
+    #[test]
+    fn test_negative_values_rejected() {
+        assert!(parse_fnt("page id=-1 file=\"digits_0.png\"\n").is_err());
+        assert!(parse_fnt("common pages=1\npage id=1 file=\"digits_1.png\"\n").is_err());
+        assert!(parse_fnt("common pages=1\npage id=4000000000 file=\"digits_0.png\"\n").is_err());
+        assert!(parse_fnt("char id=-1 x=0 y=0 width=4 height=4\n").is_err());
+        assert!(parse_fnt("char id=48 x=0 y=0 width=-4 height=4\n").is_err());
+        assert!(parse_fnt("char id=48 x=0 y=0 width=4 height=-4\n").is_err());
+        assert!(parse_fnt("char id=48 x=0 y=0 width=4 height=4 page=-1\n").is_err());
+        assert!(parse_fnt("kerning first=-49 second=48 amount=-2\n").is_err());
+        // Offsets and kerning amounts may be negative.
+        assert!(parse_fnt("char id=48 x=0 y=0 width=4 height=4 xoffset=-1 yoffset=-2\n").is_ok());
+    }
+
+    // font has a blank 'a' and 'b', kerned closer together, with spacing.
+    fn font() -> BitmapFont {
+        let mut font = BitmapFont::new(10);
+        font.add_glyph('a', Glyph { tex: None, x_offset: 1, y_offset: 2, advance: 5 });
+        font.add_glyph('b', Glyph { tex: None, x_offset: 0, y_offset: -1, advance: 6 });
+        font.set_kerning('a', 'b', -2);
+        font.set_spacing(1);
+        font
+    }
+
+    fn places(font: &BitmapFont, text: &str) -> Vec<(i32, i32)> {
+        let mut out = Vec::new();
+        font.layout(text, |_, x, y| out.push((x, y)));
+        out
+    }
+
+    #[test]
+    fn test_layout_kerning_and_spacing() {
+        let font = font();
+        // Spacing and kerning come between characters, offsets per glyph.
+        assert_eq!(places(&font, "ab"), vec![(1, 2), (4, -1)]);
+        assert_eq!(font.measure("ab"), (10, 10));
+        // Kerning is for one order only.
+        assert_eq!(places(&font, "ba"), vec![(0, -1), (8, 2)]);
+        assert_eq!(font.measure("ba"), (12, 10));
+        // Missing characters take no room.
+        assert_eq!(places(&font, "a?b"), places(&font, "ab"));
+    }
+
+    #[test]
+    fn test_layout_lines() {
+        let mut font = font();
+        // A newline starts over at the left, without kerning across it.
+        assert_eq!(places(&font, "ab\nb"), vec![(1, 2), (4, -1), (0, 9)]);
+        assert_eq!(font.measure("ab\nb"), (10, 20));
+        assert_eq!(font.measure("a\n"), (5, 20));
+        font.set_line_height(16);
+        assert_eq!(places(&font, "a\n\na"), vec![(1, 2), (1, 34)]);
+        assert_eq!(font.measure("a\n\na"), (5, 48));
+    }
+
+}
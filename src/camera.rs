@@ -0,0 +1,368 @@
+use rand::{thread_rng, Rng};
+
+use transform::Transform;
+use display::FRAME_TIME;
+use actions::Ease;
+
+// Pan is a timed camera move, from and to world points.
+struct Pan {
+    from: (f32, f32),
+    to: (f32, f32),
+    elapsed: f32,
+    duration: f32,
+    ease: Ease,
+}
+
+// Camera is a viewport into world space. Its position is the world point
+// shown at the middle of the viewport, which starts out at the middle of the
+// screen so that world and screen pixels line up until the camera moves.
+pub struct Camera {
+    x: f32,
+    y: f32,
+    width: u32,
+    height: u32,
+    zoom: f32,
+
+    target: Option<(f32, f32)>,
+    deadzone: (f32, f32),
+    smoothing: f32,
+    bounds: Option<(f32, f32, f32, f32)>,
+    pan: Option<Pan>,
+
+    // Trauma-based shake: trauma decays linearly, shake is trauma squared.
+    trauma: f32,
+    trauma_decay: f32,
+    max_offset: f32,
+    max_roll: f32,
+    shake: (f32, f32, f32),
+}
+
+impl Camera {
+    pub fn new(w: u32, h: u32) -> Camera {
+        Camera {
+            x: w as f32 / 2.0,
+            y: h as f32 / 2.0,
+            width: w,
+            height: h,
+            zoom: 1.0,
+            target: None,
+            deadzone: (0.0, 0.0),
+            smoothing: 1.0,
+            bounds: None,
+            pan: None,
+            trauma: 0.0,
+            trauma_decay: 0.02,
+            max_offset: 12.0,
+            max_roll: 3.0,
+            shake: (0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+        self.clamp();
+    }
+
+    pub fn get_position(&self) -> (f32, f32) {
+        (self.x, self.y)
+    }
+
+    pub fn set_viewport(&mut self, w: u32, h: u32) {
+        self.width = w;
+        self.height = h;
+        self.clamp();
+    }
+
+    pub fn get_viewport(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(0.01);
+        self.clamp();
+    }
+
+    pub fn get_zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    // follow makes the camera chase a world point, call it every frame with
+    // the target's position.
+    pub fn follow(&mut self, x: f32, y: f32) {
+        self.target = Some((x, y));
+    }
+
+    pub fn unfollow(&mut self) {
+        self.target = None;
+    }
+
+    // set_deadzone sets the size of the box around the middle of the view in
+    // which the target moves without the camera following it.
+    pub fn set_deadzone(&mut self, w: f32, h: f32) {
+        self.deadzone = (w / 2.0, h / 2.0);
+    }
+
+    // set_smoothing is the fraction of the distance to the target covered
+    // each frame, 1.0 snaps to it.
+    pub fn set_smoothing(&mut self, smoothing: f32) {
+        self.smoothing = smoothing.max(0.0).min(1.0);
+    }
+
+    // pan_to glides to a world point over `duration` seconds along an
+    // easing curve, as actions are eased. It takes over from following.
+    pub fn pan_to(&mut self, x: f32, y: f32, duration: f32, ease: Ease) {
+        self.pan = Some(Pan {
+            from: (self.x, self.y),
+            to: (x, y),
+            elapsed: 0.0,
+            duration: duration,
+            ease: ease,
+        });
+    }
+
+    pub fn is_panning(&self) -> bool {
+        self.pan.is_some()
+    }
+
+    // set_bounds keeps the view inside the given world rect.
+    pub fn set_bounds(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        self.bounds = Some((x, y, x + w, y + h));
+        self.clamp();
+    }
+
+    pub fn clear_bounds(&mut self) {
+        self.bounds = None;
+    }
+
+    // add_trauma shakes the camera, 1.0 being the hardest hit.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    pub fn get_trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    pub fn set_shake(&mut self, max_offset: f32, max_roll: f32, decay: f32) {
+        self.max_offset = max_offset;
+        self.max_roll = max_roll;
+        self.trauma_decay = decay;
+    }
+
+    pub fn update(&mut self) {
+        let mut done = false;
+        if let Some(ref mut pan) = self.pan {
+            pan.elapsed += FRAME_TIME;
+            let t = if pan.duration > 0.0 {
+                (pan.elapsed / pan.duration).min(1.0)
+            } else {
+                1.0
+            };
+            let e = (pan.ease)(t);
+            self.x = pan.from.0 + (pan.to.0 - pan.from.0) * e;
+            self.y = pan.from.1 + (pan.to.1 - pan.from.1) * e;
+            done = t >= 1.0;
+        } else if let Some((tx, ty)) = self.target {
+            let (dx, dy) = self.deadzone;
+            let mut want = (self.x, self.y);
+            if tx > self.x + dx {
+                want.0 = tx - dx;
+            } else if tx < self.x - dx {
+                want.0 = tx + dx;
+            }
+            if ty > self.y + dy {
+                want.1 = ty - dy;
+            } else if ty < self.y - dy {
+                want.1 = ty + dy;
+            }
+            self.x += (want.0 - self.x) * self.smoothing;
+            self.y += (want.1 - self.y) * self.smoothing;
+        }
+        if done {
+            self.pan = None;
+        }
+        self.clamp();
+
+        if self.trauma > 0.0 {
+            let shake = self.trauma * self.trauma;
+            let mut rng = thread_rng();
+            self.shake = (self.max_offset * shake * rng.gen_range(-1.0, 1.0),
+                          self.max_offset * shake * rng.gen_range(-1.0, 1.0),
+                          self.max_roll * shake * rng.gen_range(-1.0, 1.0));
+            self.trauma = (self.trauma - self.trauma_decay).max(0.0);
+        } else {
+            self.shake = (0.0, 0.0, 0.0);
+        }
+    }
+
+    // get_transform maps world space to the screen.
+    pub fn get_transform(&self) -> Transform {
+        self.parallax_transform(1.0)
+    }
+
+    // parallax_transform is the camera transform for a layer following the
+    // camera by `factor`: 0.0 stays put on screen, 1.0 moves with the world,
+    // anything in between scrolls slower like a distant background.
+    pub fn parallax_transform(&self, factor: f32) -> Transform {
+        let (ox, oy) = (self.width as f32 / 2.0, self.height as f32 / 2.0);
+        let cx = ox + (self.x - ox) * factor;
+        let cy = oy + (self.y - oy) * factor;
+
+        let mut t = Transform::identity();
+        t.rotation = self.shake.2;
+        t.scale_x = self.zoom;
+        t.scale_y = self.zoom;
+        let (rx, ry) = t.apply(cx, cy);
+        t.x = ox + self.shake.0 - rx;
+        t.y = oy + self.shake.1 - ry;
+        t
+    }
+
+    // world_to_screen maps a world point onto the screen.
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        self.get_transform().apply(x, y)
+    }
+
+    // screen_to_world maps a screen point (e.g. the mouse) into the world.
+    pub fn screen_to_world(&self, x: i32, y: i32) -> (f32, f32) {
+        let t = self.get_transform();
+        let (sx, sy) = (x as f32 - t.x, y as f32 - t.y);
+        let (sin, cos) = (-t.rotation).to_radians().sin_cos();
+        ((sx * cos - sy * sin) / self.zoom, (sx * sin + sy * cos) / self.zoom)
+    }
+
+    fn clamp(&mut self) {
+        if let Some((x0, y0, x1, y1)) = self.bounds {
+            let hw = self.width as f32 / (2.0 * self.zoom);
+            let hh = self.height as f32 / (2.0 * self.zoom);
+            self.x = if x1 - x0 < 2.0 * hw {
+                (x0 + x1) / 2.0
+            } else {
+                self.x.max(x0 + hw).min(x1 - hw)
+            };
+            self.y = if y1 - y0 < 2.0 * hh {
+                (y0 + y1) / 2.0
+            } else {
+                self.y.max(y0 + hh).min(y1 - hh)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use easing::{self, CubicBezier};
+
+    const EPSILON: f32 = 1e-3;
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < EPSILON && (a.1 - b.1).abs() < EPSILON
+    }
+
+    #[test]
+    fn test_starts_at_screen_middle() {
+        let camera = Camera::new(200, 100);
+        assert_eq!(camera.get_position(), (100.0, 50.0));
+        assert!(close(camera.world_to_screen(30.0, 40.0), (30.0, 40.0)));
+    }
+
+    #[test]
+    fn test_deadzone() {
+        let mut camera = Camera::new(200, 100);
+        camera.set_deadzone(40.0, 20.0);
+        // Inside the box the camera stays put.
+        camera.follow(115.0, 42.0);
+        camera.update();
+        assert_eq!(camera.get_position(), (100.0, 50.0));
+        // Past an edge it moves just enough to keep the target on it.
+        camera.follow(130.0, 50.0);
+        camera.update();
+        assert_eq!(camera.get_position(), (110.0, 50.0));
+        camera.follow(70.0, 30.0);
+        camera.update();
+        assert_eq!(camera.get_position(), (90.0, 40.0));
+    }
+
+    #[test]
+    fn test_smoothing() {
+        let mut camera = Camera::new(200, 100);
+        camera.set_smoothing(0.5);
+        camera.follow(140.0, 50.0);
+        camera.update();
+        assert_eq!(camera.get_position(), (120.0, 50.0));
+        camera.update();
+        assert_eq!(camera.get_position(), (130.0, 50.0));
+        camera.unfollow();
+        camera.update();
+        assert_eq!(camera.get_position(), (130.0, 50.0));
+    }
+
+    #[test]
+    fn test_bounds() {
+        let mut camera = Camera::new(200, 100);
+        camera.set_bounds(0.0, 0.0, 400.0, 300.0);
+        camera.set_position(0.0, 0.0);
+        assert_eq!(camera.get_position(), (100.0, 50.0));
+        camera.set_position(1000.0, 1000.0);
+        assert_eq!(camera.get_position(), (300.0, 250.0));
+        camera.set_position(-50.0, 1000.0);
+        assert_eq!(camera.get_position(), (100.0, 250.0));
+        camera.set_position(1000.0, -50.0);
+        assert_eq!(camera.get_position(), (300.0, 50.0));
+        // Zooming in shows less of the world, so the camera gets closer.
+        camera.set_zoom(2.0);
+        camera.set_position(0.0, 0.0);
+        assert_eq!(camera.get_position(), (50.0, 25.0));
+        // Bounds smaller than the view center it.
+        camera.set_zoom(1.0);
+        camera.set_bounds(0.0, 0.0, 100.0, 50.0);
+        assert_eq!(camera.get_position(), (50.0, 25.0));
+        camera.clear_bounds();
+        camera.set_position(0.0, 0.0);
+        assert_eq!(camera.get_position(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_zoom_round_trip() {
+        let mut camera = Camera::new(200, 100);
+        camera.set_position(130.0, 70.0);
+        camera.set_zoom(2.0);
+        let screen = camera.world_to_screen(140.0, 75.0);
+        assert!(close(screen, (120.0, 60.0)));
+        assert!(close(camera.screen_to_world(120, 60), (140.0, 75.0)));
+        assert!(close(camera.screen_to_world(100, 50), (130.0, 70.0)));
+    }
+
+    #[test]
+    fn test_pan_to() {
+        let mut camera = Camera::new(200, 100);
+        camera.follow(0.0, 0.0);
+        let curve = CubicBezier::new(0.3, 0.3, 0.7, 0.7);
+        camera.pan_to(220.0, 50.0, 0.5, Rc::new(move |t| curve.eval(t)));
+        assert!(camera.is_panning());
+        for _ in 0..15 {
+            camera.update();
+        }
+        // The curve is a straight line, half the time is half the way.
+        assert!((camera.get_position().0 - 160.0).abs() < 0.5);
+        let mut frames = 15;
+        while camera.is_panning() {
+            camera.update();
+            frames += 1;
+        }
+        assert!(frames >= 30 && frames <= 31);
+        assert_eq!(camera.get_position(), (220.0, 50.0));
+        // Following takes over again once the pan is over.
+        camera.update();
+        assert_eq!(camera.get_position(), (0.0, 0.0));
+        camera.unfollow();
+        camera.pan_to(100.0, 50.0, 0.0, Rc::new(easing::quad_out));
+        camera.update();
+        assert_eq!(camera.get_position(), (100.0, 50.0));
+    }
+}
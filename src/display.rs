@@ -5,9 +5,15 @@ use std::time::{Duration, SystemTime};
 use sdl2::render::Renderer;
 use sdl2::event::Event;
 
+use transform::Transform;
+
+// The game loop steps once per frame, timed things (particles, actions and
+// the scheduler) assume this much time passes.
+pub const FRAME_TIME: f32 = 1.0 / 60.0;
+
 // Displayable is any type that be updated and rendered to the screen.
 pub trait Displayable {
-    // In the future, could add set_alpha, set_rotation, set_visible, set_blend, etc.
+    // Tint, alpha, scale and rotation are per instance on the concrete types, see atlas::Tint.
 
     // update handles only updating the internal state of a Displayable object.
     fn update(&mut self);
@@ -15,6 +21,18 @@ pub trait Displayable {
     // paint handles the actual painting of the Displayable object against a Renderer.
     fn paint(&self, renderer: &mut Renderer);
 
+    // paint_with paints the object as a child of a node whose world transform is `parent`.
+    // The default ignores the parent for objects that only know absolute positions.
+    fn paint_with(&self, renderer: &mut Renderer, _parent: &Transform) {
+        self.paint(renderer);
+    }
+
+    // get_parallax is how much the object follows a scene camera,
+    // 1.0 moves with the world and 0.0 stays fixed on screen.
+    fn get_parallax(&self) -> f32 {
+        1.0
+    }
+
     // on_key_down handles a key down event with a default implmentation of noop.
     // fn on_key_down(&mut self, _event: &Event) {}
 
@@ -35,6 +53,9 @@ pub trait Displayable {
 
     fn on_touch_down(&mut self, _event: &Event) {}
     fn on_touch_up(&mut self, _event: &Event) {}
+
+    // on_resize lays the object out again for a new logical screen size.
+    fn on_resize(&mut self, _w: u32, _h: u32) {}
 }
 
 pub trait Float {
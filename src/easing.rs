@@ -0,0 +1,436 @@
+// Easing curves, after Robert Penner's. Each maps linear progress t in
+// [0, 1] to eased progress, 0.0 at t = 0 and 1.0 at t = 1; back and elastic
+// overshoot in between and bounce dips back down.
+
+use std::f32::consts::PI;
+
+pub fn linear(t: f32) -> f32 {
+    t
+}
+
+fn power_in(t: f32, n: i32) -> f32 {
+    t.powi(n)
+}
+
+fn power_out(t: f32, n: i32) -> f32 {
+    1.0 - (1.0 - t).powi(n)
+}
+
+fn power_in_out(t: f32, n: i32) -> f32 {
+    if t < 0.5 {
+        2f32.powi(n - 1) * t.powi(n)
+    } else {
+        1.0 - (2.0 - 2.0 * t).powi(n) / 2.0
+    }
+}
+
+pub fn quad_in(t: f32) -> f32 {
+    power_in(t, 2)
+}
+
+pub fn quad_out(t: f32) -> f32 {
+    power_out(t, 2)
+}
+
+pub fn quad_in_out(t: f32) -> f32 {
+    power_in_out(t, 2)
+}
+
+pub fn cubic_in(t: f32) -> f32 {
+    power_in(t, 3)
+}
+
+pub fn cubic_out(t: f32) -> f32 {
+    power_out(t, 3)
+}
+
+pub fn cubic_in_out(t: f32) -> f32 {
+    power_in_out(t, 3)
+}
+
+pub fn quart_in(t: f32) -> f32 {
+    power_in(t, 4)
+}
+
+pub fn quart_out(t: f32) -> f32 {
+    power_out(t, 4)
+}
+
+pub fn quart_in_out(t: f32) -> f32 {
+    power_in_out(t, 4)
+}
+
+pub fn quint_in(t: f32) -> f32 {
+    power_in(t, 5)
+}
+
+pub fn quint_out(t: f32) -> f32 {
+    power_out(t, 5)
+}
+
+pub fn quint_in_out(t: f32) -> f32 {
+    power_in_out(t, 5)
+}
+
+pub fn sine_in(t: f32) -> f32 {
+    1.0 - (t * PI / 2.0).cos()
+}
+
+pub fn sine_out(t: f32) -> f32 {
+    (t * PI / 2.0).sin()
+}
+
+pub fn sine_in_out(t: f32) -> f32 {
+    (1.0 - (t * PI).cos()) / 2.0
+}
+
+pub fn expo_in(t: f32) -> f32 {
+    if t <= 0.0 {
+        0.0
+    } else {
+        2f32.powf(10.0 * t - 10.0)
+    }
+}
+
+pub fn expo_out(t: f32) -> f32 {
+    if t >= 1.0 {
+        1.0
+    } else {
+        1.0 - 2f32.powf(-10.0 * t)
+    }
+}
+
+pub fn expo_in_out(t: f32) -> f32 {
+    if t <= 0.0 {
+        0.0
+    } else if t >= 1.0 {
+        1.0
+    } else if t < 0.5 {
+        2f32.powf(20.0 * t - 10.0) / 2.0
+    } else {
+        (2.0 - 2f32.powf(-20.0 * t + 10.0)) / 2.0
+    }
+}
+
+pub fn circ_in(t: f32) -> f32 {
+    1.0 - (1.0 - t * t).max(0.0).sqrt()
+}
+
+pub fn circ_out(t: f32) -> f32 {
+    (1.0 - (t - 1.0) * (t - 1.0)).max(0.0).sqrt()
+}
+
+pub fn circ_in_out(t: f32) -> f32 {
+    if t < 0.5 {
+        circ_in(2.0 * t) / 2.0
+    } else {
+        (circ_out(2.0 * t - 1.0) + 1.0) / 2.0
+    }
+}
+
+// How far back pulls behind its start, 1.70158 overshoots by 10%.
+const BACK: f32 = 1.70158;
+
+pub fn back_in(t: f32) -> f32 {
+    (BACK + 1.0) * t * t * t - BACK * t * t
+}
+
+pub fn back_out(t: f32) -> f32 {
+    1.0 - back_in(1.0 - t)
+}
+
+pub fn back_in_out(t: f32) -> f32 {
+    let c = BACK * 1.525;
+    if t < 0.5 {
+        (2.0 * t).powi(2) * ((c + 1.0) * 2.0 * t - c) / 2.0
+    } else {
+        ((2.0 * t - 2.0).powi(2) * ((c + 1.0) * (2.0 * t - 2.0) + c) + 2.0) / 2.0
+    }
+}
+
+pub fn elastic_in(t: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        return t.max(0.0).min(1.0);
+    }
+    -2f32.powf(10.0 * t - 10.0) * ((10.0 * t - 10.75) * 2.0 * PI / 3.0).sin()
+}
+
+pub fn elastic_out(t: f32) -> f32 {
+    1.0 - elastic_in(1.0 - t)
+}
+
+pub fn elastic_in_out(t: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        return t.max(0.0).min(1.0);
+    }
+    let wave = ((20.0 * t - 11.125) * 2.0 * PI / 4.5).sin();
+    if t < 0.5 {
+        -2f32.powf(20.0 * t - 10.0) * wave / 2.0
+    } else {
+        2f32.powf(-20.0 * t + 10.0) * wave / 2.0 + 1.0
+    }
+}
+
+pub fn bounce_out(t: f32) -> f32 {
+    let (n, d) = (7.5625, 2.75);
+    if t < 1.0 / d {
+        n * t * t
+    } else if t < 2.0 / d {
+        let t = t - 1.5 / d;
+        n * t * t + 0.75
+    } else if t < 2.5 / d {
+        let t = t - 2.25 / d;
+        n * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / d;
+        n * t * t + 0.984375
+    }
+}
+
+pub fn bounce_in(t: f32) -> f32 {
+    1.0 - bounce_out(1.0 - t)
+}
+
+pub fn bounce_in_out(t: f32) -> f32 {
+    if t < 0.5 {
+        (1.0 - bounce_out(1.0 - 2.0 * t)) / 2.0
+    } else {
+        (1.0 + bounce_out(2.0 * t - 1.0)) / 2.0
+    }
+}
+
+// CubicBezier is a CSS style timing curve through (0, 0), (x1, y1),
+// (x2, y2) and (1, 1). x1 and x2 are clamped to [0, 1] so that each t has
+// a single value; y1 and y2 may leave it to overshoot.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CubicBezier {
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+}
+
+impl CubicBezier {
+    pub fn new(x1: f32, y1: f32, x2: f32, y2: f32) -> CubicBezier {
+        CubicBezier {
+            x1: x1.max(0.0).min(1.0),
+            y1: y1,
+            x2: x2.max(0.0).min(1.0),
+            y2: y2,
+        }
+    }
+
+    fn curve(a: f32, b: f32, s: f32) -> f32 {
+        let r = 1.0 - s;
+        3.0 * r * r * s * a + 3.0 * r * s * s * b + s * s * s
+    }
+
+    fn slope(a: f32, b: f32, s: f32) -> f32 {
+        let r = 1.0 - s;
+        3.0 * r * r * a + 6.0 * r * s * (b - a) + 3.0 * s * s * (1.0 - b)
+    }
+
+    // solve finds the curve parameter whose x is `t`: Newton's method first,
+    // bisection where the slope is too flat for it.
+    fn solve(&self, t: f32) -> f32 {
+        let mut s = t;
+        for _ in 0..8 {
+            let dx = CubicBezier::curve(self.x1, self.x2, s) - t;
+            if dx.abs() < 1e-6 {
+                return s;
+            }
+            let d = CubicBezier::slope(self.x1, self.x2, s);
+            if d.abs() < 1e-6 {
+                break;
+            }
+            s -= dx / d;
+        }
+        let (mut lo, mut hi) = (0.0, 1.0);
+        s = t;
+        for _ in 0..32 {
+            let x = CubicBezier::curve(self.x1, self.x2, s);
+            if (x - t).abs() < 1e-6 {
+                break;
+            }
+            if x < t {
+                lo = s;
+            } else {
+                hi = s;
+            }
+            s = (lo + hi) / 2.0;
+        }
+        s
+    }
+
+    pub fn eval(&self, t: f32) -> f32 {
+        if t <= 0.0 || t >= 1.0 {
+            return t.max(0.0).min(1.0);
+        }
+        CubicBezier::curve(self.y1, self.y2, self.solve(t))
+    }
+}
+
+// by_name looks a curve up by its snake case name, e.g. "back_out", for
+// effects and layouts read from files.
+pub fn by_name(name: &str) -> Option<fn(f32) -> f32> {
+    let f: fn(f32) -> f32 = match name {
+        "linear" => linear,
+        "quad_in" => quad_in,
+        "quad_out" => quad_out,
+        "quad_in_out" => quad_in_out,
+        "cubic_in" => cubic_in,
+        "cubic_out" => cubic_out,
+        "cubic_in_out" => cubic_in_out,
+        "quart_in" => quart_in,
+        "quart_out" => quart_out,
+        "quart_in_out" => quart_in_out,
+        "quint_in" => quint_in,
+        "quint_out" => quint_out,
+        "quint_in_out" => quint_in_out,
+        "sine_in" => sine_in,
+        "sine_out" => sine_out,
+        "sine_in_out" => sine_in_out,
+        "expo_in" => expo_in,
+        "expo_out" => expo_out,
+        "expo_in_out" => expo_in_out,
+        "circ_in" => circ_in,
+        "circ_out" => circ_out,
+        "circ_in_out" => circ_in_out,
+        "back_in" => back_in,
+        "back_out" => back_out,
+        "back_in_out" => back_in_out,
+        "elastic_in" => elastic_in,
+        "elastic_out" => elastic_out,
+        "elastic_in_out" => elastic_in_out,
+        "bounce_in" => bounce_in,
+        "bounce_out" => bounce_out,
+        "bounce_in_out" => bounce_in_out,
+        _ => return None,
+    };
+    Some(f)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn all() -> Vec<(&'static str, fn(f32) -> f32)> {
+        ["linear", "quad_in", "quad_out", "quad_in_out", "cubic_in", "cubic_out", "cubic_in_out",
+         "quart_in", "quart_out", "quart_in_out", "quint_in", "quint_out", "quint_in_out",
+         "sine_in", "sine_out", "sine_in_out", "expo_in", "expo_out", "expo_in_out", "circ_in",
+         "circ_out", "circ_in_out", "back_in", "back_out", "back_in_out", "elastic_in",
+         "elastic_out", "elastic_in_out", "bounce_in", "bounce_out", "bounce_in_out"]
+            .iter()
+            .map(|n| (*n, by_name(n).expect("every curve should be named")))
+            .collect()
+    }
+
+    // Curves that only ever move forward.
+    fn monotonic() -> Vec<(&'static str, fn(f32) -> f32)> {
+        all().into_iter()
+             .filter(|&(n, _)| !n.starts_with("back") && !n.starts_with("elastic") && !n.starts_with("bounce"))
+             .collect()
+    }
+
+    fn samples() -> Vec<f32> {
+        (0..1001).map(|i| i as f32 / 1000.0).collect()
+    }
+
+    #[test]
+    fn test_endpoints() {
+        for (name, f) in all() {
+            assert!(f(0.0).abs() < EPSILON, "{}(0) = {}", name, f(0.0));
+            assert!((f(1.0) - 1.0).abs() < EPSILON, "{}(1) = {}", name, f(1.0));
+        }
+    }
+
+    #[test]
+    fn test_monotonic() {
+        for (name, f) in monotonic() {
+            let ts = samples();
+            for w in ts.windows(2) {
+                assert!(f(w[1]) >= f(w[0]) - EPSILON, "{} goes back at {}", name, w[1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_in_out_symmetry() {
+        for (name, f) in all().into_iter().filter(|&(n, _)| n.ends_with("in_out")) {
+            assert!((f(0.5) - 0.5).abs() < EPSILON, "{}(0.5) = {}", name, f(0.5));
+            for t in samples() {
+                assert!((f(t) + f(1.0 - t) - 1.0).abs() < 1e-3, "{} at {}", name, t);
+            }
+        }
+    }
+
+    #[test]
+    fn test_out_mirrors_in() {
+        let pairs = [("quad_in", "quad_out"), ("cubic_in", "cubic_out"), ("sine_in", "sine_out"),
+                     ("expo_in", "expo_out"), ("circ_in", "circ_out"), ("back_in", "back_out"),
+                     ("elastic_in", "elastic_out"), ("bounce_in", "bounce_out")];
+        for &(i, o) in &pairs {
+            let (fi, fo) = (by_name(i).unwrap(), by_name(o).unwrap());
+            for t in samples() {
+                assert!((fo(t) - (1.0 - fi(1.0 - t))).abs() < 1e-3, "{}/{} at {}", i, o, t);
+            }
+        }
+    }
+
+    #[test]
+    fn test_overshoot() {
+        assert!(back_in(0.2) < 0.0);
+        assert!(back_out(0.8) > 1.0);
+        assert!(elastic_out(0.1) > 1.0);
+        assert!(samples().iter().all(|&t| bounce_out(t) <= 1.0 + EPSILON));
+    }
+
+    #[test]
+    fn test_unknown_name() {
+        assert!(by_name("wobble").is_none());
+    }
+
+    #[test]
+    fn test_cubic_bezier_endpoints_and_linear() {
+        let b = CubicBezier::new(0.25, 0.1, 0.25, 1.0);
+        assert_eq!(b.eval(0.0), 0.0);
+        assert_eq!(b.eval(1.0), 1.0);
+        let line = CubicBezier::new(0.3, 0.3, 0.7, 0.7);
+        for t in samples() {
+            assert!((line.eval(t) - t).abs() < 1e-3, "linear bezier at {}", t);
+        }
+    }
+
+    #[test]
+    fn test_cubic_bezier_monotonic() {
+        // CSS ease, ease-in, ease-out and ease-in-out.
+        let curves = [CubicBezier::new(0.25, 0.1, 0.25, 1.0),
+                      CubicBezier::new(0.42, 0.0, 1.0, 1.0),
+                      CubicBezier::new(0.0, 0.0, 0.58, 1.0),
+                      CubicBezier::new(0.42, 0.0, 0.58, 1.0)];
+        for b in &curves {
+            let ts = samples();
+            for w in ts.windows(2) {
+                assert!(b.eval(w[1]) >= b.eval(w[0]) - EPSILON, "{:?} goes back at {}", b, w[1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cubic_bezier_ease_in_out_shape() {
+        // CSS ease-in-out is slow at both ends and symmetric.
+        let b = CubicBezier::new(0.42, 0.0, 0.58, 1.0);
+        assert!((b.eval(0.5) - 0.5).abs() < 1e-3);
+        assert!(b.eval(0.25) < 0.25);
+        assert!(b.eval(0.75) > 0.75);
+    }
+
+    #[test]
+    fn test_cubic_bezier_clamps_x() {
+        let b = CubicBezier::new(-1.0, 0.0, 2.0, 1.0);
+        assert_eq!(b, CubicBezier::new(0.0, 0.0, 1.0, 1.0));
+    }
+}
@@ -3,25 +3,87 @@ extern crate sdl2;
 use std::ops::{Deref, DerefMut};
 
 use std::path::Path;
-use rand::{thread_rng, Rng};
+use std::mem;
+use rand::{thread_rng, Rng, SeedableRng, StdRng};
 
 use sdl2::rect::{Point, Rect};
 use sdl2::render::Renderer;
 use sdl2::render::Texture;
 use sdl2::image::LoadTexture;
 use sdl2::event::Event;
-use std::cell::{Ref, RefMut, RefCell};
+use std::cell::{Cell, Ref, RefMut, RefCell};
 use std::rc::Rc;
 use std::collections::HashMap;
 use sdl2::keyboard::Keycode;
 
-use display::Displayable;
+use display::{Displayable, FRAME_TIME};
 use animation::Animation;
 use sprite::Sprite;
 use scene::Scene;
 use layer::{Layer, RollMode};
+use parallax::ParallaxBackground;
 use node::Node;
-use atlas::{TexLoader, TexElement};
+use atlas::{TexLoader, TexElement, Tint};
+use replay::Replay;
+use camera::Camera;
+use transform::Transform;
+use tilemap::TileMap;
+use particles::{Emitter, EmitterConfig};
+use easing;
+use actions;
+use script::Script;
+use scheduler::JobId;
+use gameover::{self, GameOver, Choice};
+use pause::{PauseMenu, PauseChoice, Settings};
+use ui::{Ui, Widget, Button, NumberLabel};
+use layout::{Layout, Stack, Anchor};
+use text::TextSystem;
+use locale::Strings;
+
+// The best run is kept next to the binary and raced against as a ghost.
+const BEST_REPLAY: &'static str = "best.replay";
+const GHOST_ALPHA: u8 = 96;
+
+// Scene z-order: parallax layers at the back, overlays always above them.
+const Z_BACKGROUND: i32 = 0;
+const Z_COURSE: i32 = 50;
+const Z_HUD: i32 = 100;
+
+const PARALLAX_LAYERS: &'static str = "res/parallax.txt";
+// Particle presets: feathers on flap, sparkles on scoring, dust on ground impact.
+const FEATHERS: &'static str = "res/particles/feathers.json";
+const SPARKLES: &'static str = "res/particles/sparkles.plist";
+const DUST: &'static str = "res/particles/dust.json";
+// World scroll speed in pixels per frame, ramping up the longer the run
+// lasts: gently at first, reaching MAX_SPEED after RAMP_FRAMES.
+const BASE_SPEED: f32 = 2.0;
+const MAX_SPEED: f32 = 5.0;
+const RAMP_FRAMES: f32 = 3000.0;
+// While getting ready the bird bobs this many pixels up and down, at this
+// many radians per second.
+const HOVER_HEIGHT: f32 = 6.0;
+const HOVER_RATE: f32 = 8.0;
+// The title screen's bird bobs as far, taking this many seconds each way.
+const BOB_TIME: f32 = 0.4;
+// Seconds the ready text and tutorial take to fade once the run starts.
+const READY_FADE: f32 = 0.3;
+// A point for every SCORE_FRAMES survived, about one pipe at the base speed.
+const SCORE_FRAMES: u32 = 90;
+// Where the ready text and the tutorial sit, from the middle of the screen.
+const READY_OFFSET: i32 = -150;
+const TUTORIAL_OFFSET: i32 = 100;
+// Title screen layout: the title's distance from the top, the bird's from
+// the middle, and the buttons' and copyright's from the bottom.
+const TITLE_MARGIN: i32 = 96;
+const TITLE_BIRD_OFFSET: i32 = -100;
+const TITLE_BUTTONS_MARGIN: i32 = 115;
+const TITLE_BUTTON_SPACING: u32 = 24;
+const COPYRIGHT_MARGIN: i32 = 23;
+
+const BIRD_FRAMES: [&'static str; 4] = ["res/imgs/bird_frame_1.png",
+                                        "res/imgs/bird_frame_2.png",
+                                        "res/imgs/bird_frame_3.png",
+                                        "res/imgs/bird_frame_4.png"];
 
 pub struct Bird {
     speed: f32,
@@ -38,11 +100,7 @@ impl Bird {
             speed: 0.0,
             xaccelerate: 0.0,
             yaccelerate: 0.2,
-            animation: Animation::new(renderer,
-                                      &["res/imgs/bird_frame_1.png",
-                                        "res/imgs/bird_frame_2.png",
-                                        "res/imgs/bird_frame_3.png",
-                                        "res/imgs/bird_frame_4.png"]),
+            animation: Animation::new(renderer, &BIRD_FRAMES),
             died: false,
         }
     }
@@ -54,6 +112,24 @@ impl Bird {
     pub fn is_died(&self) -> bool {
         self.died
     }
+
+    pub fn die(&mut self) {
+        self.died = true;
+    }
+
+    pub fn reset(&mut self, x: i32, y: i32) {
+        self.speed = 0.0;
+        self.died = false;
+        self.animation.set_position(x, y);
+    }
+
+    // hover holds the bird at `y` without falling, still flapping its wings.
+    pub fn hover(&mut self, y: i32) {
+        let x = self.animation.get_position().0;
+        self.speed = 0.0;
+        self.animation.set_position(x, y);
+        self.animation.update();
+    }
 }
 
 impl Displayable for Bird {
@@ -78,6 +154,10 @@ impl Displayable for Bird {
     fn paint(&self, renderer: &mut Renderer) {
         self.animation.paint(renderer);
     }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        self.animation.paint_with(renderer, parent);
+    }
 }
 
 impl Deref for Bird {
@@ -95,110 +175,528 @@ impl DerefMut for Bird {
 }
 
 
-#[derive(Debug)]
+// Ghost races a recorded run next to the live bird, replaying its flaps
+// frame by frame with a translucent bird of its own.
+pub struct Ghost {
+    frame: u32,
+    cursor: usize,
+    replay: Option<Replay>,
+    bird: Bird,
+}
+
+impl Ghost {
+    pub fn new(renderer: &Renderer) -> Ghost {
+        let mut bird = Bird::new(renderer);
+        bird.set_interval(0.3);
+        bird.set_alpha(GHOST_ALPHA);
+        bird.hide();
+        Ghost {
+            frame: 0,
+            cursor: 0,
+            replay: None,
+            bird: bird,
+        }
+    }
+
+    pub fn set_replay(&mut self, replay: Option<Replay>) {
+        self.replay = replay;
+    }
+
+    pub fn get_replay(&self) -> Option<&Replay> {
+        self.replay.as_ref()
+    }
+
+    pub fn restart(&mut self, x: i32, y: i32) {
+        self.frame = 0;
+        self.cursor = 0;
+        self.bird.reset(x, y);
+        if self.replay.is_some() {
+            self.bird.show();
+        } else {
+            self.bird.hide();
+        }
+    }
+}
+
+impl Displayable for Ghost {
+    fn update(&mut self) {
+        if !self.bird.get_visible() {
+            return;
+        }
+        if let Some(ref replay) = self.replay {
+            // The recorded bird died here, so does its ghost.
+            if self.frame >= replay.get_frames() {
+                self.bird.hide();
+                return;
+            }
+            let flaps = replay.get_flaps();
+            while self.cursor < flaps.len() && flaps[self.cursor] <= self.frame {
+                if flaps[self.cursor] == self.frame {
+                    self.bird.jump();
+                }
+                self.cursor += 1;
+            }
+        }
+        self.bird.update();
+        self.frame += 1;
+    }
+
+    fn paint(&self, renderer: &mut Renderer) {
+        self.bird.paint(renderer);
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        self.bird.paint_with(renderer, parent);
+    }
+}
+
+
+// new_background is the backdrop both scenes paint first, loaded by the
+// game and shared with the title screen. It stands still
+// until given a scroll step, then wraps around through the image.
+fn new_background(renderer: &Renderer, w: u32, h: u32) -> Rc<RefCell<Layer>> {
+    let mut bg = Layer::new(renderer, w, h, "res/imgs/background.png");
+    bg.set_scroll(RollMode::HorizontalEx);
+    bg.set_scroll_step(0.0);
+    Rc::new(RefCell::new(bg))
+}
+
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 enum GameStatus {
+    // Getting ready: the bird hovers and the world waits for the first flap.
+    READY,
     STOPED,
     PAUSED,
     RUNNING,
 }
 
 pub struct FlappyScene {
-    scroll: bool,
-    scroll_step: u32,
-    scroll_x1: u32,
-    scroll_x2: u32,
-    scroll_w1: u32,
-    scroll_w2: u32,
     width: u32,
     height: u32,
 
     state: GameStatus,
     atlas: HashMap<String, Rc<RefCell<TexElement>>>,
+    ready: Rc<RefCell<Sprite>>,
+    tutorial: Rc<RefCell<Sprite>>,
+    layout: Layout,
+    hover: f32,
+    score: u32,
+    // Painted over everything, the bird and particles included.
+    game_over: GameOver,
+    pause_menu: PauseMenu,
+    settings: Rc<RefCell<Settings>>,
+    // The state to go back to on resume.
+    resume_state: GameStatus,
+    // A choice made outside the game over screen, quitting from the pause menu.
+    choice: Option<Choice>,
     bird: Bird,
+    ghost: Ghost,
+    camera: Rc<RefCell<Camera>>,
+    parallax: ParallaxBackground,
     scene: Scene,
-    background: Sprite,
+    background: Rc<RefCell<Layer>>,
+    seed: usize,
+    frame: u32,
+    record: Replay,
+    pipe_texs: Vec<Rc<RefCell<TexElement>>>,
     pipes: Vec<Rc<Pipe>>,
+    course: Option<Rc<RefCell<TileMap>>>,
+    feathers: Emitter,
+    sparkles: Emitter,
+    dust: Emitter,
 }
 
 impl FlappyScene {
     // add code here
-    pub fn new(renderer: &Renderer, w: u32, h: u32) -> FlappyScene {
+    pub fn new(renderer: &Renderer,
+               text: &mut TextSystem,
+               strings: Rc<RefCell<Strings>>,
+               w: u32,
+               h: u32)
+               -> FlappyScene {
         let mut scene = Scene::new(renderer); // "res/imgs/background.png"
+        let camera = Rc::new(RefCell::new(Camera::new(w, h)));
+        scene.set_camera(Some(camera.clone()));
 
-        {
-            for i in 1..6 {
-                let mut layer = Rc::new(RefCell::new(Layer::new(renderer,w,h,
-                                                                &format!("res/imgs/layer_0{}_1920x1080.png",i)[..]
-                                                                )));
-                if i < 4 {
-                    layer.borrow_mut().set_scroll(RollMode::None);
-                } else {
-                    layer.borrow_mut().set_scroll(RollMode::Horizontal);
-                }
-                scene.add_child(&format!("layer_0{}_1920x1080.png", i)[..], layer);
-            }
-        }
+        let mut parallax = ParallaxBackground::load(renderer, w, h, PARALLAX_LAYERS).unwrap();
+        parallax.set_speed(BASE_SPEED);
+        parallax.attach(&mut scene, "parallax", Z_BACKGROUND);
         let mut pipe = Rc::new(RefCell::new(Layer::new(renderer, w, h, "res/imgs/pipe.png")));
         pipe.borrow_mut().set_scroll(RollMode::Horizontal);
         // scene.add_child("pipe.png", pipe);
 
         // scene.add_child(bird);
-        let mut bg = Sprite::new(renderer, "res/imgs/background.png");
-        bg.set_interval(0.5);
+        let bg = new_background(renderer, w, h);
 
         let atlas = TexLoader(renderer, "res/atlas.txt", "res/atlas.png");
         {
             for (k, v) in &atlas {
                 v.borrow_mut().hide();
-                scene.add_child(&k[..], v.clone());
+                scene.add_child_z(&k[..], Z_HUD, v.clone());
             }
         }
 
-        let mut pipes = Vec::new();
-        // for i in 1..4 {
-        let mut p = Pipe::new(renderer, w, h, "res/imgs/pipe.png");
-        p.set_position(0, 0);
-        pipes.push(Rc::new(p));
-        let mut p = Pipe::new_from_tex(atlas["pipe2_down"].clone(), w, h);
-        p.set_position(0, 0);
-        pipes.push(Rc::new(p));
-        // }
+        // HUD sprites over the shared atlas elements, shown when needed.
+        let hud = |scene: &mut Scene, name: &str, element: &str| {
+            let mut sprite = Sprite::new_from_tex(atlas[element].clone());
+            sprite.set_anchor(0.5, 0.5);
+            sprite.hide();
+            let sprite = Rc::new(RefCell::new(sprite));
+            scene.add_child_z(name, Z_HUD, sprite.clone());
+            sprite
+        };
+        let ready = hud(&mut scene, "hud_ready", "text_ready");
+        let tutorial = hud(&mut scene, "hud_tutorial", "tutorial");
+        let mut layout = Layout::new(w, h);
+        layout.anchor(ready.clone(), Anchor::Center, (0, READY_OFFSET));
+        layout.anchor(tutorial.clone(), Anchor::Center, (0, TUTORIAL_OFFSET));
+
+        // Race the best run on its own seed so the ghost sees the same pipes.
+        let best = Replay::load(BEST_REPLAY).ok();
+        let seed = match best {
+            Some(ref replay) => replay.get_seed(),
+            None => thread_rng().gen::<usize>(),
+        };
+        let mut ghost = Ghost::new(renderer);
+        ghost.set_replay(best);
+
+        let pipe_texs = vec![Rc::new(RefCell::new(TexElement::new(renderer, "res/imgs/pipe.png"))),
+                             atlas["pipe2_down"].clone()];
+        let pipes = FlappyScene::new_pipes(seed, &pipe_texs, w, h);
+
+        let effect = |path| Emitter::new(EmitterConfig::load(renderer, path, &atlas).unwrap());
+        let feathers = effect(FEATHERS);
+        let sparkles = effect(SPARKLES);
+        let dust = effect(DUST);
+        let game_over = GameOver::new(&atlas, w, h);
+        let settings = Rc::new(RefCell::new(Settings::new()));
+        let pause_menu = PauseMenu::new(renderer, text, strings, &atlas, settings.clone(), w, h).unwrap();
 
         FlappyScene {
-            scroll: false,
-            scroll_step: 1,
-            scroll_x1: 0,
-            scroll_x2: w,
-            scroll_w1: 0,
-            scroll_w2: 0,
             width: w,
             height: h,
             state: GameStatus::STOPED,
             atlas: atlas,
+            ready: ready,
+            tutorial: tutorial,
+            layout: layout,
+            hover: 0.0,
+            score: 0,
+            game_over: game_over,
+            pause_menu: pause_menu,
+            settings: settings,
+            resume_state: GameStatus::STOPED,
+            choice: None,
             bird: Bird::new(renderer),
+            ghost: ghost,
+            camera: camera,
+            parallax: parallax,
             scene: scene,
             background: bg,
+            seed: seed,
+            frame: 0,
+            record: Replay::new(seed),
+            pipe_texs: pipe_texs,
             pipes: pipes,
+            course: None,
+            feathers: feathers,
+            sparkles: sparkles,
+            dust: dust,
+        }
+    }
+
+    fn new_pipes(seed: usize,
+                 texs: &[Rc<RefCell<TexElement>>],
+                 w: u32,
+                 h: u32)
+                 -> Vec<Rc<Pipe>> {
+        let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+        let mut pipes = Vec::new();
+        for tex in texs {
+            let mut p = Pipe::new_from_tex(tex.clone(), w, h, &mut rng);
+            p.set_position(0, 0);
+            pipes.push(Rc::new(p));
         }
+        pipes
+    }
+
+    // load_ghost races against a saved replay instead of the best run,
+    // starting from the next restart.
+    pub fn load_ghost(&mut self, path: &str) -> Result<(), String> {
+        let replay = Replay::load(path)?;
+        self.ghost.set_replay(Some(replay));
+        Ok(())
+    }
+
+    // load_course swaps the random pipes for a hand-built Tiled map. Its
+    // tiles may name atlas elements and a "spawn" object places the bird.
+    pub fn load_course(&mut self, renderer: &Renderer, path: &str) -> Result<(), String> {
+        let map = TileMap::load(renderer, path, &self.atlas, self.width, self.height)?;
+        let map = Rc::new(RefCell::new(map));
+        self.scene.remove_child("course");
+        self.scene.add_child_z("course", Z_COURSE, map.clone());
+        self.course = Some(map);
+        self.pipes.clear();
+        Ok(())
+    }
+
+    // scroll_course moves the course past the bird at the foreground's speed,
+    // as the random pipes' layer scrolls.
+    fn scroll_course(&mut self) {
+        if let Some(ref course) = self.course {
+            let mut transform = course.borrow().get_transform();
+            transform.x -= self.parallax.get_speed();
+            course.borrow_mut().set_transform(transform);
+        }
+    }
+
+    // spawn_point is where the bird starts, the course's spawn or the middle.
+    fn spawn_point(&self) -> Point {
+        let spawn = self.course
+                        .as_ref()
+                        .and_then(|c| c.borrow().get_object("spawn").map(|o| Point::new(o.x as i32, o.y as i32)));
+        let (x, y) = self.layout.point(Anchor::Center, (0, 0));
+        spawn.unwrap_or(Point::new(x, y))
+    }
+
+    // sparkle bursts the scoring sparkles at a world point.
+    pub fn sparkle(&mut self, x: i32, y: i32) {
+        self.sparkles.burst_at(x, y, 16);
+    }
+
+    pub fn get_atlas(&self) -> &HashMap<String, Rc<RefCell<TexElement>>> {
+        &self.atlas
+    }
+
+    // relabel renders the menus again after the language changed.
+    pub fn relabel(&mut self, renderer: &Renderer, text: &mut TextSystem) -> Result<(), String> {
+        self.pause_menu.relabel(renderer, text)
+    }
+
+    // share_parallax is a background over the game's parallax layers, for
+    // the title screen to scroll on its own.
+    pub fn share_parallax(&self) -> ParallaxBackground {
+        self.parallax.share()
+    }
+
+    // get_background shares the backdrop.
+    pub fn get_background(&self) -> Rc<RefCell<Layer>> {
+        self.background.clone()
+    }
+
+    // get_bird_frames shares the bird's animation frames.
+    pub fn get_bird_frames(&self) -> Vec<Rc<TexElement>> {
+        self.bird.get_textures()
+    }
+
+    // get_settings shares the game settings, for other menus to change.
+    pub fn get_settings(&self) -> Rc<RefCell<Settings>> {
+        self.settings.clone()
+    }
+
+    pub fn get_score(&self) -> u32 {
+        self.score
+    }
+
+    // get_best_score is the score of the best run so far, the ghost's.
+    pub fn get_best_score(&self) -> u32 {
+        self.ghost.get_replay().map_or(0, |r| r.get_frames() / SCORE_FRAMES)
+    }
+
+    // show_game_over opens the game over screen for the finished run. The
+    // game loop's scheduler calls it after a delay, it signals "game_over"
+    // to the scene's scripts.
+    pub fn show_game_over(&mut self) {
+        let best = self.get_best_score();
+        self.game_over.show(self.score, best);
+        self.scene.signal("game_over");
+    }
+
+    pub fn is_game_over_shown(&self) -> bool {
+        self.game_over.get_visible()
+    }
+
+    // take_choice hands out what the player picked on the game over screen,
+    // or a quit from the pause menu.
+    pub fn take_choice(&mut self) -> Option<Choice> {
+        self.choice.take().or_else(|| self.game_over.take_choice())
+    }
+
+    // pause freezes the game under the pause menu. Only a run, or one
+    // getting ready, can be paused.
+    pub fn pause(&mut self) {
+        if self.state == GameStatus::READY || self.state == GameStatus::RUNNING {
+            self.resume_state = self.state;
+            self.state = GameStatus::PAUSED;
+            self.scene.pause();
+            self.pause_menu.show();
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if self.state == GameStatus::PAUSED {
+            self.state = self.resume_state;
+            self.scene.resume();
+            self.pause_menu.hide();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state == GameStatus::PAUSED
+    }
+
+    fn apply_pause_choice(&mut self) {
+        match self.pause_menu.take_choice() {
+            Some(PauseChoice::Pause) => self.pause(),
+            Some(PauseChoice::Resume) => self.resume(),
+            Some(PauseChoice::Restart) => {
+                self.resume();
+                self.restart();
+            }
+            Some(PauseChoice::Quit) => {
+                self.resume();
+                self.choice = Some(Choice::Menu);
+            }
+            None => {}
+        }
+    }
+
+    // restart starts a new run, keeping the last one as the ghost when it
+    // ended in a crash and beat the best. Runs abandoned from the pause
+    // menu never ended, so they are dropped.
+    pub fn restart(&mut self) {
+        let seed = self.seed;
+        let run = mem::replace(&mut self.record, Replay::new(seed));
+        let better = match self.ghost.get_replay() {
+            Some(best) => run.is_better_than(best),
+            None => true,
+        };
+        if self.state == GameStatus::STOPED && better {
+            let _ = run.save(BEST_REPLAY);
+            self.ghost.set_replay(Some(run));
+        }
+
+        self.seed = match self.ghost.get_replay() {
+            Some(replay) => replay.get_seed(),
+            None => thread_rng().gen::<usize>(),
+        };
+        if self.course.is_none() {
+            self.pipes = FlappyScene::new_pipes(self.seed, &self.pipe_texs, self.width, self.height);
+        }
+        self.scene.restart();
+        self.start();
     }
 
     pub fn start(&mut self) {
         self.bird.set_interval(0.3);
-        let sz = self.bird.get_size();
-        let p = Rect::new(0, 0, self.width, self.height).center();
-        self.bird.set_position(p.x(), p.y());
-        // self.bird.set_position(self.width as i32 / 2 - sz.0 as i32,
-        //                        self.height as i32 / 2 - sz.1 as i32);
+        let p = self.spawn_point();
+        self.bird.reset(p.x(), p.y());
         self.bird.start();
         self.bird.show();
 
+        self.frame = 0;
+        self.score = 0;
+        self.game_over.hide();
+        self.record = Replay::new(self.seed);
+        self.feathers.clear();
+        self.sparkles.clear();
+        self.dust.clear();
+        self.parallax.set_speed(BASE_SPEED);
+        self.parallax.pause();
+        if let Some(ref course) = self.course {
+            course.borrow_mut().set_position(0, 0);
+        }
+        self.ghost.restart(p.x(), p.y());
+        self.get_ready();
+    }
 
+    // get_ready shows the ready text and tutorial until the first flap,
+    // which fades them out and starts the run.
+    fn get_ready(&mut self) {
+        self.state = GameStatus::READY;
+        self.hover = 0.0;
+        for hud in &[&self.ready, &self.tutorial] {
+            let mut hud = hud.borrow_mut();
+            hud.set_alpha(255);
+            hud.show();
+        }
+        let ready = self.ready.clone();
+        let tutorial = self.tutorial.clone();
+        self.scene.run_script(Script::new()
+                                  .wait_event("flap")
+                                  .act_async(ready, actions::fade_out(READY_FADE))
+                                  .act(tutorial, actions::fade_out(READY_FADE)));
+    }
+
+    // begin starts the run: the world scrolls and the bird falls. The bird
+    // leaves the hover's bob at the spawn point, where the ghost starts too,
+    // so a replay follows the run it recorded.
+    fn begin(&mut self) {
+        let p = self.spawn_point();
+        self.bird.reset(p.x(), p.y());
+        self.state = GameStatus::RUNNING;
+        self.parallax.resume();
     }
 
     pub fn stop(&mut self) {
         // TODO
         self.bird.hide();
     }
+
+    // update_with updates the game with the scene's actions and scripts
+    // stepped by `dt`, on the clock of the scheduler owning `jobs`.
+    pub fn update_with(&mut self, dt: f32, jobs: &[JobId]) {
+        // Paused, nothing moves: no scrolling, particles, actions or scripts.
+        if self.is_paused() {
+            return;
+        }
+
+        // if self.background.get_elapsed() >= self.background.get_interval() {
+        //     self.background.cursor_incr();
+        //     self.background.update_time();
+        // }
+
+        self.background.borrow_mut().update();
+        self.camera.borrow_mut().update();
+        if self.state == GameStatus::RUNNING {
+            let ramp = easing::sine_in_out((self.frame as f32 / RAMP_FRAMES).min(1.0));
+            self.parallax.set_speed(BASE_SPEED + (MAX_SPEED - BASE_SPEED) * ramp);
+        }
+        self.parallax.update();
+        if self.state == GameStatus::RUNNING {
+            self.scroll_course();
+        }
+        self.scene.update_with(dt, jobs);
+        if self.state == GameStatus::READY {
+            self.hover += HOVER_RATE * FRAME_TIME;
+            let y = self.spawn_point().y() as f32 + self.hover.sin() * HOVER_HEIGHT;
+            self.bird.hover(y as i32);
+        } else if self.state == GameStatus::RUNNING {
+            self.bird.update();
+            self.ghost.update();
+            self.frame += 1;
+            if self.frame / SCORE_FRAMES > self.score {
+                self.score = self.frame / SCORE_FRAMES;
+                let (x, y) = self.bird.get_position();
+                self.sparkle(x, y);
+            }
+
+            if self.bird.get_position().1 > self.height as i32 {
+                self.bird.die();
+                self.state = GameStatus::STOPED;
+                self.dust.burst_at(self.bird.get_position().0, self.height as i32, 24);
+                self.camera.borrow_mut().add_trauma(0.8);
+                self.parallax.pause();
+                self.record.finish(self.frame);
+                self.scene.set_game_over();
+            }
+        }
+        self.feathers.update();
+        self.sparkles.update();
+        self.dust.update();
+        self.game_over.update();
+    }
 }
 
 
@@ -217,10 +715,62 @@ impl DerefMut for FlappyScene {
 }
 
 impl Displayable for FlappyScene {
+    // The world fills the new logical size, with the camera back over its
+    // middle so world and screen pixels still line up.
+    fn on_resize(&mut self, w: u32, h: u32) {
+        self.width = w;
+        self.height = h;
+        self.background.borrow_mut().on_resize(w, h);
+        self.scene.on_resize(w, h);
+        if self.course.is_none() {
+            self.pipes = FlappyScene::new_pipes(self.seed, &self.pipe_texs, w, h);
+        }
+        {
+            let mut camera = self.camera.borrow_mut();
+            camera.set_viewport(w, h);
+            camera.set_position(w as f32 / 2.0, h as f32 / 2.0);
+        }
+        self.layout.resize(w, h);
+        self.game_over.on_resize(w, h);
+        self.pause_menu.on_resize(w, h);
+    }
+
     fn on_key_down(&mut self, event: &Event) {
+        // The game over screen takes every key while it is up.
+        if self.is_game_over_shown() {
+            self.game_over.on_key_down(event);
+            return;
+        }
+
+        // So does the pause menu, but for P resuming.
+        if self.is_paused() {
+            match event {
+                &Event::KeyDown { keycode: Some(Keycode::P), .. } => self.resume(),
+                _ => {
+                    self.pause_menu.on_key_down(event);
+                    self.apply_pause_choice();
+                }
+            }
+            return;
+        }
+
         match event {
             &Event::KeyDown { keycode: Some(Keycode::P), .. } => {
-                // self.paused = !self.paused;
+                self.pause();
+                return;
+            }
+            _ => {}
+        }
+
+        match event {
+            &Event::KeyDown { keycode: Some(Keycode::Space), .. } if !self.bird.is_died() => {
+                if self.state == GameStatus::READY {
+                    self.begin();
+                }
+                self.record.record_flap(self.frame);
+                let (x, y) = self.bird.get_position();
+                self.feathers.burst_at(x, y, 6);
+                self.scene.signal("flap");
             }
             _ => {}
         }
@@ -230,115 +780,235 @@ impl Displayable for FlappyScene {
         self.bird.on_key_down(event);
     }
     fn update(&mut self) {
+        self.update_with(FRAME_TIME, &[]);
+    }
 
-        // if self.background.get_elapsed() >= self.background.get_interval() {
-        //     self.background.cursor_incr();
-        //     self.background.update_time();
-        // }
-
-        self.background.update();
-        self.scene.update();
-        self.bird.update();
-        if self.scroll {
-            let sz = self.background.get_size();
-            self.scroll_x1 += self.scroll_step;
-            if self.scroll_x1 > sz.0 {
-                self.scroll_x1 = 0;
-            }
-
-            if self.scroll_x1 > (sz.0 - self.width) {
-                self.scroll_w1 = sz.0 - self.scroll_x1;
-            } else {
-                self.scroll_w1 = self.width;
-            }
+    fn on_mouse_down(&mut self, event: &Event) {
+        if self.is_game_over_shown() {
+            self.game_over.on_mouse_down(event);
+            return;
+        }
+        self.pause_menu.on_mouse_down(event);
+        self.apply_pause_choice();
+    }
 
-            self.scroll_x2 += self.scroll_step;
-            if (self.scroll_x2 - self.width) > sz.0 {
-                self.scroll_x2 = self.width;
-            }
+    fn on_mouse_up(&mut self, event: &Event) {
+        self.game_over.on_mouse_up(event);
+        self.pause_menu.on_mouse_up(event);
+        self.apply_pause_choice();
+    }
 
-            self.scroll_w2 = self.width - self.scroll_w1;
+    fn on_mouse_move(&mut self, event: &Event) {
+        if self.is_game_over_shown() {
+            self.game_over.on_mouse_move(event);
+            return;
         }
+        self.pause_menu.on_mouse_move(event);
     }
 
     fn paint(&self, renderer: &mut Renderer) {
 
-        let mut current_texture = self.background.get_texture();
-        renderer.copy(&mut current_texture,
-                      Some(Rect::new(self.scroll_x1 as i32, 0, self.scroll_w1, self.height)),
-                      Some(Rect::new(0, 0, self.scroll_w1, self.height)))
-                .expect("background should have rendered.");
-
-        if self.scroll_w2 > 0 {
-            renderer.copy(&mut current_texture,
-                          Some(Rect::new(0, 0, self.scroll_w2, self.height)),
-                          Some(Rect::new((self.width - self.scroll_w2) as i32,
-                                         0,
-                                         self.scroll_w2,
-                                         self.height)))
-                    .expect("background should have rendered.");
-        }
-        self.background.paint(renderer);
+        self.background.borrow().paint(renderer);
         self.scene.paint(renderer);
         // self.atlas.paint(renderer);
+        let view = self.camera.borrow().get_transform();
         for p in &self.pipes {
-            p.paint(renderer);
+            p.paint_with(renderer, &view);
+        }
+        if self.settings.borrow().ghost {
+            self.ghost.paint_with(renderer, &view);
+        }
+        self.feathers.paint_with(renderer, &view);
+        self.bird.paint_with(renderer, &view);
+        self.sparkles.paint_with(renderer, &view);
+        self.dust.paint_with(renderer, &view);
+        self.game_over.paint(renderer);
+        if self.state != GameStatus::STOPED {
+            self.pause_menu.paint(renderer);
         }
-        self.bird.paint(renderer);
     }
 }
 
 
 
+// The title screen's buttons, left to right.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum TitleButton {
+    Play,
+    Scores,
+    Settings,
+}
+
+// StartScene is the title screen: the title over the scrolling parallax
+// background with the bird bobbing below it, and buttons to play, show the
+// best score or open the settings. Left and right pick a button, Enter or
+// Space presses it, and so does a click.
 pub struct StartScene {
-    scroll: bool,
-    scroll_step: u32,
-    scroll_x1: u32,
-    scroll_x2: u32,
-    scroll_w1: u32,
-    scroll_w2: u32,
-    width: u32,
-    height: u32,
-    // layer: Layer,
     scene: Scene,
-    background: Node,
+    background: Rc<RefCell<Layer>>,
+    parallax: ParallaxBackground,
+    atlas: HashMap<String, Rc<RefCell<TexElement>>>,
+    // Only flaps and bobs, there is nothing for it to fly through.
+    bird: Rc<RefCell<Animation>>,
+    title: Rc<RefCell<Sprite>>,
+    copyright: Rc<RefCell<Sprite>>,
+    buttons: Ui,
+    layout: Layout,
+    // Set by the buttons when clicked.
+    pressed: Rc<Cell<Option<TitleButton>>>,
+    // The best score panel, toggled by the score button.
+    scores: bool,
+    panel: Rc<RefCell<Sprite>>,
+    medal: Option<Sprite>,
+    best: NumberLabel,
+    settings: PauseMenu,
+    play: bool,
 }
 
 impl StartScene {
-    // add code here
-    pub fn new(renderer: &Renderer, w: u32, h: u32) -> StartScene {
-        let mut bird = Rc::new(RefCell::new(Bird::new(renderer)));
-        bird.borrow_mut().set_interval(0.3);
-        let sz = bird.borrow_mut().get_size();
-        bird.borrow_mut().set_position(w as i32 / 2 - sz.0 as i32, h as i32 / 2 - sz.1 as i32);
-        bird.borrow_mut().start();
-
+    // new builds the title screen over the game's own textures: its atlas,
+    // backgrounds and bird, and its settings.
+    pub fn new(renderer: &Renderer,
+               text: &mut TextSystem,
+               strings: Rc<RefCell<Strings>>,
+               game: &FlappyScene,
+               w: u32,
+               h: u32)
+               -> Result<StartScene, String> {
+        let atlas = game.get_atlas();
         let mut scene = Scene::new(renderer);
+        let mut parallax = game.share_parallax();
+        parallax.set_speed(BASE_SPEED);
+        parallax.attach(&mut scene, "parallax", Z_BACKGROUND);
+
+        let sprite = |name: &str| {
+            let mut sprite = Sprite::new_from_tex(atlas[name].clone());
+            sprite.set_anchor(0.5, 0.5);
+            Rc::new(RefCell::new(sprite))
+        };
+        let title = sprite("title");
+        let copyright = sprite("brand_copyright");
+        let panel = sprite("score_panel");
+
+        let mut bird = Animation::new_from_texture(game.get_bird_frames());
+        bird.set_interval(0.3);
+        bird.start();
+
+        // The atlas has no settings button, the rate button's star stands
+        // in for it.
+        let pressed = Rc::new(Cell::new(None));
+        let mut buttons = Ui::new();
+        let mut row = Stack::horizontal(TITLE_BUTTON_SPACING);
+        for &(name, which) in &[("button_play", TitleButton::Play),
+                                ("button_score", TitleButton::Scores),
+                                ("button_rate", TitleButton::Settings)] {
+            let mut button = Button::new(atlas[name].clone());
+            let pressed = pressed.clone();
+            button.on_click(move || pressed.set(Some(which)));
+            row.add(buttons.add(button));
+        }
+        buttons.focus_first();
 
-        scene.add_child("flappy-bird", bird);
-        let mut bg = Node::new(renderer, &["res/imgs/background.png"]);
-        bg.set_interval(0.5);
-
-        StartScene {
-            scroll: false,
-            scroll_step: 1,
-            scroll_x1: 0,
-            scroll_x2: w,
-            scroll_w1: 0,
-            scroll_w2: 0,
-            width: w,
-            height: h,
+        let mut layout = Layout::new(w, h);
+        layout.anchor(title.clone(), Anchor::Top, (0, TITLE_MARGIN));
+        layout.anchor_stack(row, Anchor::Bottom, (0, TITLE_BUTTONS_MARGIN));
+        layout.anchor(copyright.clone(), Anchor::Bottom, (0, COPYRIGHT_MARGIN));
+        layout.anchor(panel.clone(), Anchor::Center, (0, 0));
+
+        let mut best = gameover::score_label(atlas);
+        best.set_position(97, 0);
+
+        let settings = PauseMenu::new(renderer, text, strings, atlas, game.get_settings(), w, h)?;
+        let mut start = StartScene {
             scene: scene,
-            background: bg,
-        }
+            background: game.get_background(),
+            parallax: parallax,
+            atlas: atlas.clone(),
+            bird: Rc::new(RefCell::new(bird)),
+            title: title,
+            copyright: copyright,
+            buttons: buttons,
+            layout: layout,
+            pressed: pressed,
+            scores: false,
+            panel: panel,
+            medal: None,
+            best: best,
+            settings: settings,
+            play: false,
+        };
+        start.place_bird();
+        Ok(start)
+    }
+
+    // relabel renders the settings again after the language changed.
+    pub fn relabel(&mut self, renderer: &Renderer, text: &mut TextSystem) -> Result<(), String> {
+        self.settings.relabel(renderer, text)
+    }
+
+    // place_bird puts the bird above the middle of the screen, bobbing
+    // down and up around there.
+    fn place_bird(&mut self) {
+        let (x, y) = self.layout.point(Anchor::Center, (0, TITLE_BIRD_OFFSET));
+        let w = self.bird.borrow().get_size().0;
+        self.bird.borrow_mut().set_position(x - w as i32 / 2, y - HOVER_HEIGHT as i32);
+        let target: actions::Target = self.bird.clone();
+        self.scene.get_actions().stop_all_for(&target);
+        let bob = actions::sequence(vec![actions::move_by(BOB_TIME, 0.0, 2.0 * HOVER_HEIGHT),
+                                         actions::move_by(BOB_TIME, 0.0, -2.0 * HOVER_HEIGHT)]);
+        let bob = actions::ease(bob, Rc::new(easing::sine_in_out));
+        self.scene.run_action(target, actions::repeat_forever(bob));
+    }
+
+    // set_best_score is the score the score button shows.
+    pub fn set_best_score(&mut self, best: u32) {
+        self.best.set_value(best);
+        let atlas = &self.atlas;
+        let medal = gameover::medal_for(best).map(|name| {
+            let mut sprite = Sprite::new_from_tex(atlas[name].clone());
+            sprite.set_anchor(0.5, 0.5);
+            sprite.set_position(-66, 4);
+            sprite
+        });
+        self.medal = medal;
+    }
+
+    // take_play tells, once, whether the player chose to play.
+    pub fn take_play(&mut self) -> bool {
+        mem::replace(&mut self.play, false)
     }
 
     pub fn start(&mut self) {
-        // TODO
+        self.play = false;
+        self.scores = false;
+        self.pressed.set(None);
+        self.buttons.focus_first();
+        self.place_bird();
+        self.settings.hide();
+        self.parallax.resume();
+        self.bird.borrow_mut().start();
     }
 
     pub fn stop(&mut self) {
-        // TODO
+        self.parallax.pause();
+        self.bird.borrow_mut().stop();
+    }
+
+    // press acts on the button the player pressed, if any.
+    fn press(&mut self) {
+        match self.pressed.take() {
+            Some(TitleButton::Play) => self.play = true,
+            Some(TitleButton::Scores) => self.scores = !self.scores,
+            Some(TitleButton::Settings) => self.settings.show_settings(),
+            None => {}
+        }
+    }
+
+    // close_settings hides the settings once the player is done with them.
+    fn close_settings(&mut self) {
+        if self.settings.take_choice().is_some() {
+            self.settings.hide();
+        }
     }
 }
 
@@ -358,71 +1028,81 @@ impl DerefMut for StartScene {
 }
 
 impl Displayable for StartScene {
-    // add code here
+    fn on_resize(&mut self, w: u32, h: u32) {
+        self.scene.on_resize(w, h);
+        self.layout.resize(w, h);
+        self.place_bird();
+        self.settings.on_resize(w, h);
+    }
 
     fn on_key_down(&mut self, event: &Event) {
-        match event {
-            &Event::KeyDown { keycode: Some(Keycode::P), .. } => {
-                // self.paused = !self.paused;
-            }
-            _ => {}
+        // The settings take every key while they are open.
+        if self.settings.get_visible() {
+            self.settings.on_key_down(event);
+            self.close_settings();
+            return;
         }
-
-        // TODO: allow cancel propagating events based on logic in parent.
-        self.scene.on_key_down(event);
+        self.buttons.on_key_down(event);
+        self.press();
     }
 
-    fn update(&mut self) {
-
-        if self.background.get_elapsed() >= self.background.get_interval() {
-            self.background.cursor_incr();
-            self.background.update_time();
+    fn on_mouse_down(&mut self, event: &Event) {
+        if self.settings.get_visible() {
+            self.settings.on_mouse_down(event);
+            self.close_settings();
+            return;
         }
-        self.scene.update();
-
-        if self.scroll {
-            let sz = self.background.get_texture_size(0).unwrap();
-            self.scroll_x1 += self.scroll_step;
-            if self.scroll_x1 > sz.0 {
-                self.scroll_x1 = 0;
-            }
-
-            if self.scroll_x1 > (sz.0 - self.width) {
-                self.scroll_w1 = sz.0 - self.scroll_x1;
-            } else {
-                self.scroll_w1 = self.width;
-            }
-
-            self.scroll_x2 += self.scroll_step;
-            if (self.scroll_x2 - self.width) > sz.0 {
-                self.scroll_x2 = self.width;
-            }
+        self.buttons.on_mouse_down(event);
+    }
 
-            self.scroll_w2 = self.width - self.scroll_w1;
+    fn on_mouse_up(&mut self, event: &Event) {
+        if self.settings.get_visible() {
+            self.settings.on_mouse_up(event);
+            self.close_settings();
         }
+        self.buttons.on_mouse_up(event);
+        self.press();
     }
 
-    fn paint(&self, renderer: &mut Renderer) {
-
-        let mut current_texture = self.background.get_texture(0).unwrap();
-        renderer.copy(&mut current_texture,
-                      Some(Rect::new(self.scroll_x1 as i32, 0, self.scroll_w1, self.height)),
-                      Some(Rect::new(0, 0, self.scroll_w1, self.height)))
-                .expect("background should have rendered.");
-
-        if self.scroll_w2 > 0 {
-            renderer.copy(&mut current_texture,
-                          Some(Rect::new(0, 0, self.scroll_w2, self.height)),
-                          Some(Rect::new((self.width - self.scroll_w2) as i32,
-                                         0,
-                                         self.scroll_w2,
-                                         self.height)))
-                    .expect("background should have rendered.");
+    fn on_mouse_move(&mut self, event: &Event) {
+        if self.settings.get_visible() {
+            self.settings.on_mouse_move(event);
+            return;
         }
+        self.buttons.on_mouse_move(event);
+    }
 
-        // self.layer.paint(renderer);
+    fn update(&mut self) {
+        self.background.borrow_mut().update();
+        self.parallax.update();
+        self.scene.update();
+        self.bird.borrow_mut().update();
+        self.buttons.update();
+    }
 
+    fn paint(&self, renderer: &mut Renderer) {
+        self.background.borrow().paint(renderer);
         self.scene.paint(renderer);
+
+        let screen = Transform::identity();
+        self.title.borrow().paint_with(renderer, &screen);
+        self.bird.borrow().paint_with(renderer, &screen);
+        self.buttons.paint_with(renderer, &screen);
+        self.copyright.borrow().paint_with(renderer, &screen);
+
+        if self.scores {
+            let panel = self.panel.borrow();
+            panel.paint_with(renderer, &screen);
+            let (x, y) = panel.get_position();
+            let center = Transform::new(x, y);
+            self.best.paint_with(renderer, &center);
+            if let Some(ref medal) = self.medal {
+                medal.paint_with(renderer, &center);
+            }
+        }
+        if self.settings.get_visible() {
+            self.settings.paint(renderer);
+        }
     }
 }
 
@@ -443,18 +1123,18 @@ pub struct Pipe {
 
 
 impl Pipe {
-    pub fn new(renderer: &Renderer, w: u32, h: u32, path: &str) -> Pipe {
+    pub fn new<R: Rng>(renderer: &Renderer, w: u32, h: u32, path: &str, rng: &mut R) -> Pipe {
         let sp = Sprite::new(renderer, path);
         let sz = sp.get_size();
         let mut inverted = false;
         // Add some variation.
-        if thread_rng().gen_range(0, 10) > 5 {
+        if rng.gen_range(0, 10) > 5 {
             inverted = true;
         }
         Pipe {
             x: 0,
             w: sz.0,
-            h: thread_rng().gen_range(sz.1 / 10, sz.1),
+            h: rng.gen_range(sz.1 / 10, sz.1),
             max_w: w,
             max_h: h,
             speed: 0.0,
@@ -464,18 +1144,18 @@ impl Pipe {
             sprite: sp,
         }
     }
-    pub fn new_from_tex(tex: Rc<RefCell<TexElement>>, w: u32, h: u32) -> Pipe {
+    pub fn new_from_tex<R: Rng>(tex: Rc<RefCell<TexElement>>, w: u32, h: u32, rng: &mut R) -> Pipe {
         let mut inverted = false;
         let sz = tex.as_ref().borrow().get_size();
         // Add some variation.
-        if thread_rng().gen_range(0, 10) > 5 {
+        if rng.gen_range(0, 10) > 5 {
             inverted = true;
         }
 
         Pipe {
             x: 0,
             w: sz.0,
-            h: thread_rng().gen_range(sz.1 / 10, sz.1),
+            h: rng.gen_range(sz.1 / 10, sz.1),
 
             max_w: w,
             max_h: h,
@@ -490,19 +1170,22 @@ impl Pipe {
 impl Displayable for Pipe {
     fn paint(&self, renderer: &mut Renderer) {
         // , texture: &Texture) {
-        let mut rect = Rect::new(self.x, self.max_h as i32 - self.h as i32, self.w, self.h);
+        self.paint_with(renderer, &Transform::identity());
+    }
 
-        let mut flip = false;
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        let y = if self.inverted { 0 } else { self.max_h as i32 - self.h as i32 };
+        // The pipe image is stretched over the pipe, upside down when it
+        // hangs from the top. Painting through the element keeps the shared
+        // atlas texture's modes from leaking in.
+        let sz = self.sprite.get_size();
+        let mut world = Transform::new(self.x, y);
+        world.scale_x = self.w as f32 / sz.0 as f32;
+        world.scale_y = self.h as f32 / sz.1 as f32;
         if self.inverted {
-            rect.y = 0;
-            flip = true;
+            world.scale_y = -world.scale_y;
         }
-        let texture = self.sprite.get_texture();
-        let sz = self.sprite.get_size();
-        let pos = self.sprite.get_position();
-        let r = Rect::new(pos.0, pos.1, sz.0, sz.1);
-        renderer.copy_ex(texture, Some(r), Some(rect), 0.0, None, false, flip)
-                .expect("Single pipe should have rendered.");
+        self.sprite.get_tex().borrow().paint_tinted(renderer, &parent.then(&world), &Tint::white());
     }
 
     fn update(&mut self) {
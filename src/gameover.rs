@@ -0,0 +1,295 @@
+extern crate sdl2;
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use sdl2::event::Event;
+use sdl2::render::Renderer;
+
+use display::{Displayable, FRAME_TIME};
+use sprite::Sprite;
+use atlas::TexElement;
+use transform::Transform;
+use ui::{Ui, Widget, Button, NumberLabel, Align};
+use layout::{Layout, Stack, Anchor};
+use actions::{self, ActionManager};
+use easing;
+
+// Medals by the least score earning them: bronze, silver, gold, platinum.
+const MEDALS: [(u32, &'static str); 4] = [(10, "medals_0"),
+                                          (20, "medals_1"),
+                                          (30, "medals_2"),
+                                          (40, "medals_3")];
+// Seconds the title takes to drop into place, the panel to rise into place
+// and then to count the score up.
+const DROP_TIME: f32 = 0.4;
+const SLIDE_TIME: f32 = 0.5;
+const COUNT_TIME: f32 = 1.0;
+// How far above its place the title drops from, and how far below its
+// place the panel rises from.
+const DROP_FROM: f32 = 120.0;
+const SLIDE_FROM: f32 = 300.0;
+// Score digits are drawn at half the size of the atlas font.
+const DIGIT_SCALE: f32 = 0.5;
+// Gaps between the title, panel and buttons, and between the buttons.
+const SPACING: u32 = 36;
+const BUTTON_SPACING: u32 = 24;
+// The whole screen sits this far above the middle.
+const RAISE: i32 = 20;
+
+// Choice is what the player picked on the game over screen.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Choice {
+    Restart,
+    Menu,
+}
+
+// medal_for is the atlas name of the best medal a score earns, if any.
+pub fn medal_for(score: u32) -> Option<&'static str> {
+    MEDALS.iter().rev().find(|&&(min, _)| score >= min).map(|&(_, name)| name)
+}
+
+// score_label is a number in the atlas font sized for the score panel,
+// right aligned to its position.
+pub fn score_label(atlas: &HashMap<String, Rc<RefCell<TexElement>>>) -> NumberLabel {
+    let mut label = NumberLabel::from_atlas(atlas);
+    label.set_align(Align::Right);
+    label.set_scale(DIGIT_SCALE);
+    label
+}
+
+// GameOver is the overlay shown after a crash: the title, a score panel
+// rising from below and counting up to the score, with the best score,
+// a medal and a "new" badge for a new best, then the restart and menu
+// buttons. It stays fixed on screen and is driven by its owner scene,
+// which hands it the events and takes the player's choice.
+pub struct GameOver {
+    visible: bool,
+    score: u32,
+    new_best: bool,
+    // Runs the drop, slide and count up, which set `ready` once done.
+    actions: ActionManager,
+    ready: Rc<Cell<bool>>,
+    title: Rc<RefCell<Sprite>>,
+    panel: Rc<RefCell<Sprite>>,
+    // Digits, medal and badge are laid out from the panel center.
+    shown: Rc<RefCell<NumberLabel>>,
+    best: NumberLabel,
+    badge: Sprite,
+    medals: Vec<(&'static str, Sprite)>,
+    buttons: Ui,
+    layout: Layout,
+    // Set by the buttons when clicked.
+    choice: Rc<Cell<Option<Choice>>>,
+}
+
+impl GameOver {
+    pub fn new(atlas: &HashMap<String, Rc<RefCell<TexElement>>>, w: u32, h: u32) -> GameOver {
+        let sprite = |name: &str, x: i32, y: i32| {
+            let mut sprite = Sprite::new_from_tex(atlas[name].clone());
+            sprite.set_anchor(0.5, 0.5);
+            sprite.set_position(x, y);
+            sprite
+        };
+        let title = Rc::new(RefCell::new(sprite("text_game_over", 0, 0)));
+        let panel = Rc::new(RefCell::new(sprite("score_panel", 0, 0)));
+
+        let mut shown = score_label(atlas);
+        shown.set_position(97, -20);
+        let mut best = score_label(atlas);
+        best.set_position(97, 22);
+
+        let choice = Rc::new(Cell::new(None));
+        let mut buttons = Ui::new();
+        let mut row = Stack::horizontal(BUTTON_SPACING);
+        for &(name, pick) in &[("button_play", Choice::Restart), ("button_menu", Choice::Menu)] {
+            let mut button = Button::new(atlas[name].clone());
+            let choice = choice.clone();
+            button.on_click(move || choice.set(Some(pick)));
+            row.add(buttons.add(button));
+        }
+        let mut stack = Stack::vertical(SPACING);
+        stack.add(title.clone()).add(panel.clone()).add_stack(row);
+        let mut layout = Layout::new(w, h);
+        layout.anchor_stack(stack, Anchor::Center, (0, -RAISE));
+
+        GameOver {
+            visible: false,
+            score: 0,
+            new_best: false,
+            actions: ActionManager::new(),
+            ready: Rc::new(Cell::new(false)),
+            title: title,
+            panel: panel,
+            shown: Rc::new(RefCell::new(shown)),
+            best: best,
+            badge: sprite("new", 50, 0),
+            medals: MEDALS.iter().map(|&(_, name)| (name, sprite(name, -66, 4))).collect(),
+            buttons: buttons,
+            layout: layout,
+            choice: choice,
+        }
+    }
+
+    // show opens the overlay for a finished run.
+    pub fn show(&mut self, score: u32, best: u32) {
+        self.visible = true;
+        self.score = score;
+        self.new_best = score > best;
+        self.shown.borrow_mut().set_value(0);
+        self.best.set_value(best.max(score));
+        self.buttons.hide();
+        self.buttons.focus_first();
+        self.choice.set(None);
+        self.animate();
+    }
+
+    pub fn hide(&mut self) {
+        self.visible = false;
+        self.actions.stop_all();
+    }
+
+    pub fn get_visible(&self) -> bool {
+        self.visible
+    }
+
+    // take_choice hands out the player's choice once, if any was made.
+    pub fn take_choice(&mut self) -> Option<Choice> {
+        self.choice.take()
+    }
+
+    // is_ready tells whether the panel is in place with the score counted,
+    // the buttons only show then.
+    fn is_ready(&self) -> bool {
+        self.ready.get()
+    }
+
+    // animate drops the title in and slides the panel up from their laid
+    // out places, then counts the score up.
+    fn animate(&mut self) {
+        self.actions.stop_all();
+        self.ready.set(false);
+        self.layout.apply();
+
+        let (x, y) = self.title.borrow().get_position();
+        self.title.borrow_mut().set_position(x, y - DROP_FROM as i32);
+        let drop = actions::move_to(DROP_TIME, x as f32, y as f32);
+        self.actions.run(self.title.clone(), actions::ease(drop, Rc::new(easing::bounce_out)));
+
+        let (x, y) = self.panel.borrow().get_position();
+        self.panel.borrow_mut().set_position(x, y + SLIDE_FROM as i32);
+        let slide = actions::move_to(SLIDE_TIME, x as f32, y as f32);
+        let shown = self.shown.clone();
+        let score = self.score as f32;
+        let count = actions::progress(COUNT_TIME, move |t| {
+            shown.borrow_mut().set_value((score * t).round() as u32);
+        });
+        let ready = self.ready.clone();
+        let done = actions::call_func(move || ready.set(true));
+        self.actions.run(self.panel.clone(),
+                         actions::sequence(vec![actions::ease(slide, Rc::new(easing::back_out)),
+                                                actions::ease(count, Rc::new(easing::quad_out)),
+                                                done]));
+    }
+
+    // finish skips to the end of the animation.
+    fn finish(&mut self) {
+        self.actions.stop_all();
+        self.layout.apply();
+        self.shown.borrow_mut().set_value(self.score);
+        self.ready.set(true);
+    }
+}
+
+impl Displayable for GameOver {
+    fn update(&mut self) {
+        if !self.visible {
+            return;
+        }
+        self.actions.update(FRAME_TIME);
+        if self.is_ready() && !self.buttons.get_visible() {
+            self.buttons.show();
+        }
+        self.buttons.update();
+    }
+
+    fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        if !self.visible {
+            return;
+        }
+        self.title.borrow().paint_with(renderer, parent);
+
+        self.panel.borrow().paint_with(renderer, parent);
+        let (x, y) = self.panel.borrow().get_position();
+        let center = parent.then(&Transform::new(x, y));
+        self.shown.borrow().paint_with(renderer, &center);
+        self.best.paint_with(renderer, &center);
+        if self.is_ready() {
+            let medal = medal_for(self.score);
+            for &(_, ref sprite) in self.medals.iter().filter(|m| Some(m.0) == medal) {
+                sprite.paint_with(renderer, &center);
+            }
+            if self.new_best {
+                self.badge.paint_with(renderer, &center);
+            }
+        }
+        self.buttons.paint_with(renderer, parent);
+    }
+
+    // The overlay stays in place while the camera moves or shakes.
+    fn get_parallax(&self) -> f32 {
+        0.0
+    }
+
+    // The tweens head for the old places, so a resize ends them first.
+    fn on_resize(&mut self, w: u32, h: u32) {
+        if self.visible && !self.is_ready() {
+            self.finish();
+        }
+        self.layout.resize(w, h);
+    }
+
+    fn on_key_down(&mut self, event: &Event) {
+        if self.visible {
+            self.buttons.on_key_down(event);
+        }
+    }
+
+    fn on_mouse_down(&mut self, event: &Event) {
+        if self.visible {
+            self.buttons.on_mouse_down(event);
+        }
+    }
+
+    fn on_mouse_move(&mut self, event: &Event) {
+        if self.visible {
+            self.buttons.on_mouse_move(event);
+        }
+    }
+
+    fn on_mouse_up(&mut self, event: &Event) {
+        self.buttons.on_mouse_up(event);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_medal_for() {
+        assert_eq!(medal_for(0), None);
+        assert_eq!(medal_for(9), None);
+        assert_eq!(medal_for(10), Some("medals_0"));
+        assert_eq!(medal_for(19), Some("medals_0"));
+        assert_eq!(medal_for(20), Some("medals_1"));
+        assert_eq!(medal_for(30), Some("medals_2"));
+        assert_eq!(medal_for(40), Some("medals_3"));
+        assert_eq!(medal_for(1000), Some("medals_3"));
+    }
+}
@@ -0,0 +1,99 @@
+extern crate sdl2;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use sdl2::event::Event;
+use sdl2::render::Renderer;
+
+use display::Displayable;
+use transform::Transform;
+
+// Group is an invisible node that only positions its children, e.g. a score
+// panel carrying its medal and digits: moving the group moves them all.
+pub struct Group {
+    visible: bool,
+    transform: Transform,
+    children: Vec<Rc<RefCell<Displayable>>>,
+}
+
+impl Group {
+    pub fn new() -> Group {
+        Group {
+            visible: true,
+            transform: Transform::identity(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn add_child(&mut self, child: Rc<RefCell<Displayable>>) {
+        self.children.push(child);
+    }
+
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        self.transform.x = x as f32;
+        self.transform.y = y as f32;
+    }
+
+    pub fn get_position(&self) -> (i32, i32) {
+        (self.transform.x as i32, self.transform.y as i32)
+    }
+
+    pub fn set_scale(&mut self, sx: f32, sy: f32) {
+        self.transform.scale_x = sx;
+        self.transform.scale_y = sy;
+    }
+
+    pub fn set_rotation(&mut self, degrees: f32) {
+        self.transform.rotation = degrees;
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    pub fn get_transform(&self) -> Transform {
+        self.transform
+    }
+
+    pub fn hide<'a>(&'a mut self) -> &'a mut Group {
+        self.visible = false;
+        self
+    }
+
+    pub fn show<'a>(&'a mut self) -> &'a mut Group {
+        self.visible = true;
+        self
+    }
+
+    pub fn get_visible(&self) -> bool {
+        self.visible
+    }
+}
+
+impl Displayable for Group {
+    fn update(&mut self) {
+        for child in &self.children {
+            child.borrow_mut().update();
+        }
+    }
+
+    fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        if self.visible {
+            let world = parent.then(&self.transform);
+            for child in &self.children {
+                child.borrow().paint_with(renderer, &world);
+            }
+        }
+    }
+
+    fn on_key_down(&mut self, event: &Event) {
+        for child in &self.children {
+            child.borrow_mut().on_key_down(event);
+        }
+    }
+}
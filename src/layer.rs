@@ -17,25 +17,34 @@ use sdl2::keyboard::Keycode;
 
 use sprite::Sprite;
 use display::Displayable;
+use transform::Transform;
+use scroll::{Scroller, to_texture};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum RollMode {
     None,
+    // The image stretched to the layer, repeating as it scrolls.
     Horizontal,
+    // The image at its own size, the layer showing a window into it.
     HorizontalEx,
+    // The image's height fitted to the layer, its width kept.
     HorizontalFixed,
     Vertical,
     VerticalEx,
+    // The image's width fitted to the layer, its height kept.
     VerticalFixed,
+    // The image stretched to the layer, repeating on both axes along `set_direction`.
+    Diagonal,
 }
+
 pub struct Layer {
     // visible: bool,
     scroll: RollMode,
-    scroll_step: i32,
-    scroll_x1: i32,
-    scroll_x2: i32,
-    scroll_w1: u32,
-    scroll_w2: u32,
+    // Pixels per frame, positive steps move the image left or up.
+    scroll_step: f32,
+    direction: (f32, f32),
+    scroller: Scroller,
+    parallax: f32,
     w: u32,
     h: u32,
     children: Vec<Rc<RefCell<Displayable>>>,
@@ -46,11 +55,10 @@ impl Layer {
     pub fn new(renderer: &Renderer, w: u32, h: u32, path: &str) -> Layer {
         Layer {
             scroll: RollMode::None,
-            scroll_step: 1,
-            scroll_x1: 0,
-            scroll_x2: w as i32,
-            scroll_w1: 0,
-            scroll_w2: 0,
+            scroll_step: 1.0,
+            direction: (1.0, 0.0),
+            scroller: Scroller::new(),
+            parallax: 1.0,
             w: w,
             h: h,
             children: Vec::new(),
@@ -60,19 +68,91 @@ impl Layer {
 
     pub fn set_scroll(&mut self, mode: RollMode) {
         self.scroll = mode;
-        // match mode {
-        //     RollMode::HorizontalFixed => {
-        //         let sz = self.node.get_size();
-        //         self.w = sz.0;
-        //         self.h = sz.1;
-        //     }
-        //     _ => {}
-        // }
+        self.scroller.offset = (0.0, 0.0);
     }
 
     pub fn get_scroll(&self) -> RollMode {
         self.scroll
     }
+
+    pub fn set_scroll_step(&mut self, step: f32) {
+        self.scroll_step = step;
+    }
+
+    pub fn get_scroll_step(&self) -> f32 {
+        self.scroll_step
+    }
+
+    // set_direction sets the scroll direction of RollMode::Diagonal,
+    // normalized so the step stays the speed.
+    pub fn set_direction(&mut self, dx: f32, dy: f32) {
+        let len = (dx * dx + dy * dy).sqrt();
+        if len > 0.0 {
+            self.direction = (dx / len, dy / len);
+        }
+    }
+
+    pub fn get_direction(&self) -> (f32, f32) {
+        self.direction
+    }
+
+    // set_offset sets the sub-pixel scroll position.
+    pub fn set_offset(&mut self, x: f32, y: f32) {
+        self.scroller.offset = (x, y);
+    }
+
+    pub fn get_offset(&self) -> (f32, f32) {
+        self.scroller.offset
+    }
+
+    // set_gap sets the empty space between repeats of the image.
+    pub fn set_gap(&mut self, x: u32, y: u32) {
+        self.scroller.gap = (x, y);
+    }
+
+    pub fn get_gap(&self) -> (u32, u32) {
+        self.scroller.gap
+    }
+
+    // set_parallax sets how much the layer follows a scene camera.
+    pub fn set_parallax(&mut self, factor: f32) {
+        self.parallax = factor;
+    }
+
+    // add_child attaches a child painted relative to the layer's transform.
+    pub fn add_child(&mut self, child: Rc<RefCell<Displayable>>) {
+        self.children.push(child);
+    }
+
+    // step is the per-frame movement of the mode.
+    fn step(&self) -> (f32, f32) {
+        let s = self.scroll_step;
+        match self.scroll {
+            RollMode::None => (0.0, 0.0),
+            RollMode::Horizontal |
+            RollMode::HorizontalEx |
+            RollMode::HorizontalFixed => (s, 0.0),
+            RollMode::Vertical |
+            RollMode::VerticalEx |
+            RollMode::VerticalFixed => (0.0, s),
+            RollMode::Diagonal => (s * self.direction.0, s * self.direction.1),
+        }
+    }
+
+    // tile is the size the image is drawn at and which axes repeat.
+    fn tile(&self) -> ((u32, u32), (bool, bool)) {
+        let sz = self.node.get_size();
+        match self.scroll {
+            RollMode::None => ((self.w, self.h), (false, false)),
+            RollMode::Horizontal => ((self.w, self.h), (true, false)),
+            RollMode::HorizontalEx => (sz, (true, false)),
+            RollMode::HorizontalFixed => ((sz.0, self.h), (true, false)),
+            RollMode::Vertical => ((self.w, self.h), (false, true)),
+            RollMode::VerticalEx => (sz, (false, true)),
+            RollMode::VerticalFixed => ((self.w, sz.1), (false, true)),
+            RollMode::Diagonal => ((self.w, self.h), (true, true)),
+        }
+    }
 }
 
 impl Displayable for Layer {
@@ -82,123 +162,62 @@ impl Displayable for Layer {
             child.borrow_mut().on_key_down(event);
         }
     }
-    fn update(&mut self) {
-        // if self.scroll {
-
-        // }
-        match self.scroll {
-            RollMode::None => {}
-            RollMode::Horizontal => {
-                self.scroll_x1 -= self.scroll_step;
-                self.scroll_x2 -= self.scroll_step;
-
-                if self.scroll_x1 < -1 * (self.w as i32 - self.scroll_step) {
-                    self.scroll_x1 = self.w as i32;
-                }
-                if self.scroll_x2 < -1 * (self.w as i32 - self.scroll_step) {
-                    self.scroll_x2 = self.scroll_x1 + self.w as i32;
-                }
-
-                // for child in &self.children {
-                //     child.borrow_mut().update();
-                // }
-            }
-            RollMode::HorizontalEx => {
-                let sz = self.get_size();
-                self.scroll_x1 += self.scroll_step as i32;
-                if self.scroll_x1 > sz.0 as i32 {
-                    self.scroll_x1 = 0;
-                }
-
-                if self.scroll_x1 > (sz.0 - self.w) as i32 {
-                    self.scroll_w1 = sz.0 - self.scroll_x1 as u32;
-                } else {
-                    self.scroll_w1 = self.w;
-                }
 
-                self.scroll_x2 += self.scroll_step;
-                if (self.scroll_x2 - self.w as i32) > sz.0 as i32 {
-                    self.scroll_x2 = self.w as i32;
-                }
+    fn get_parallax(&self) -> f32 {
+        self.parallax
+    }
 
-                self.scroll_w2 = self.w - self.scroll_w1;
-            }
-            RollMode::HorizontalFixed => {}
-            RollMode::Vertical => {}
-            RollMode::VerticalEx => {}
-            RollMode::VerticalFixed => {}
+    // A layer covers the screen, so it grows and shrinks with it.
+    fn on_resize(&mut self, w: u32, h: u32) {
+        self.w = w;
+        self.h = h;
+        for child in &self.children {
+            child.borrow_mut().on_resize(w, h);
         }
     }
 
+    fn update(&mut self) {
+        let (dx, dy) = self.step();
+        let (tile, _) = self.tile();
+        self.scroller.advance(dx, dy, tile);
+    }
+
     fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
         if self.get_visible() {
+            // The scrolling image only follows the layer's translation,
+            // children get the whole world transform.
+            let world = parent.then(&self.get_transform());
+            let (ox, oy) = (world.x.round() as i32, world.y.round() as i32);
 
             match self.scroll {
                 RollMode::None => {
                     // self.node.paint(renderer);
-                    let mut current_texture = self.get_texture();
-                    renderer.copy(&mut current_texture,
-                                  None,
-                                  Some(Rect::new(0, 0, self.w, self.h)))
-                            .expect("layer should have rendered.");
-                }
-                RollMode::Horizontal => {
-                    let mut current_texture = self.get_texture();
-                    renderer.copy(&mut current_texture,
-                                  None,
-                                  Some(Rect::new(self.scroll_x1, 0, self.w, self.h)))
-                            .expect("layer should have rendered.");
-
-
-                    renderer.copy(&mut current_texture,
-                                  None,
-                                  Some(Rect::new(self.scroll_x2, 0, self.w, self.h)))
-                            .expect("layer should have rendered.");
-                }
-                RollMode::HorizontalEx => {
-                    let mut current_texture = self.get_texture();
-                    renderer.copy(&mut current_texture,
-                                  Some(Rect::new(self.scroll_x1 as i32,
-                                                 0,
-                                                 self.scroll_w1,
-                                                 self.h)),
-                                  Some(Rect::new(0, 0, self.scroll_w1, self.h)))
-                            .expect("background should have rendered.");
-
-                    if self.scroll_w2 > 0 {
-                        renderer.copy(&mut current_texture,
-                                      Some(Rect::new(0, 0, self.scroll_w2, self.h)),
-                                      Some(Rect::new((self.w - self.scroll_w2) as i32,
-                                                     0,
-                                                     self.scroll_w2,
-                                                     self.h)))
-                                .expect("background should have rendered.");
-                    }
+                    self.node.paint_src(renderer, None, Rect::new(ox, oy, self.w, self.h));
                 }
-                RollMode::HorizontalFixed => {
-                    let mut current_texture = self.get_texture();
+                _ => {
+                    // Repeating axes turn the translation into more scrolling,
+                    // so a camera offset never opens a gap between tiles.
+                    let ((tw, th), wrap) = self.tile();
                     let sz = self.node.get_size();
-                    renderer.copy(&mut current_texture,
-                                  Some(Rect::new(self.scroll_x1 as i32, 0, self.scroll_w1, sz.1)),
-                                  Some(Rect::new(0, 0, self.scroll_w1, self.h)))
-                            .expect("background should have rendered.");
-
-                    if self.scroll_w2 > 0 {
-                        renderer.copy(&mut current_texture,
-                                      Some(Rect::new(0, 0, self.scroll_w2, sz.1)),
-                                      Some(Rect::new((self.w - self.scroll_w2) as i32,
-                                                     0,
-                                                     self.scroll_w2,
-                                                     self.h)))
-                                .expect("background should have rendered.");
+                    let (xs, ys) = self.scroller.spans((tw, th), wrap, (self.w, self.h), (ox, oy));
+
+                    for x in &xs {
+                        let (sx, sw) = to_texture(*x, tw, sz.0);
+                        for y in &ys {
+                            let (sy, sh) = to_texture(*y, th, sz.1);
+                            self.node.paint_src(renderer,
+                                                Some(Rect::new(sx, sy, sw, sh)),
+                                                Rect::new(x.1 as i32, y.1 as i32, x.2, y.2));
+                        }
                     }
                 }
-                RollMode::Vertical => {}
-                RollMode::VerticalEx => {}
-                RollMode::VerticalFixed => {}
             }
             for child in &self.children {
-                child.borrow_mut().paint(renderer);
+                child.borrow().paint_with(renderer, &world);
             }
 
         }
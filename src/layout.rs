@@ -0,0 +1,349 @@
+extern crate sdl2;
+
+use std::cell::RefCell;
+use std::mem;
+use std::rc::Rc;
+
+use sdl2::rect::Rect;
+
+use ui::{Widget, Align};
+
+// Anchor is one of nine points of a box: its corners, the middles of its
+// edges and its center.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    // get_factors is where the anchor sits across and down a box, from 0 to 1.
+    pub fn get_factors(&self) -> (f32, f32) {
+        match *self {
+            Anchor::TopLeft => (0.0, 0.0),
+            Anchor::Top => (0.5, 0.0),
+            Anchor::TopRight => (1.0, 0.0),
+            Anchor::Left => (0.0, 0.5),
+            Anchor::Center => (0.5, 0.5),
+            Anchor::Right => (1.0, 0.5),
+            Anchor::BottomLeft => (0.0, 1.0),
+            Anchor::Bottom => (0.5, 1.0),
+            Anchor::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+// Direction is the way a stack grows.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Direction {
+    Horizontal,
+    Vertical,
+}
+
+// place_axis places a `size` long span in an `area` long one at `factor`
+// along it. The margin pushes it in from the edge it is anchored to, and
+// is a plain offset when it is centered.
+fn place_axis(area: i32, factor: f32, margin: i32, size: u32) -> i32 {
+    let free = area - size as i32;
+    let at = (free as f32 * factor).round() as i32;
+    if factor > 0.5 { at - margin } else { at + margin }
+}
+
+// place is where the top left corner of a w by h box goes so that its
+// anchor point lands on the same anchor point of `area`, `margin` in from
+// the edges.
+pub fn place(area: Rect, anchor: Anchor, margin: (i32, i32), w: u32, h: u32) -> (i32, i32) {
+    let (fx, fy) = anchor.get_factors();
+    (area.x() + place_axis(area.width() as i32, fx, margin.0, w),
+     area.y() + place_axis(area.height() as i32, fy, margin.1, h))
+}
+
+// Node is something a layout places: a widget, or a stack of them.
+pub enum Node {
+    Widget(Rc<RefCell<Widget>>),
+    Stack(Stack),
+}
+
+impl Node {
+    fn get_size(&self) -> (u32, u32) {
+        match *self {
+            Node::Widget(ref w) => {
+                let r = w.borrow().get_rect();
+                (r.width(), r.height())
+            }
+            Node::Stack(ref s) => s.get_size(),
+        }
+    }
+
+    fn set_position(&mut self, x: i32, y: i32) {
+        match *self {
+            Node::Widget(ref w) => w.borrow_mut().set_position(x, y),
+            Node::Stack(ref mut s) => s.set_position(x, y),
+        }
+    }
+}
+
+// Stack lines its nodes up one after the other, `spacing` apart, and
+// aligns them across: Left is the top of a horizontal stack and Right its
+// bottom. Stacks nest.
+pub struct Stack {
+    direction: Direction,
+    spacing: u32,
+    align: Align,
+    nodes: Vec<Node>,
+}
+
+impl Stack {
+    pub fn new(direction: Direction, spacing: u32) -> Stack {
+        Stack {
+            direction: direction,
+            spacing: spacing,
+            align: Align::Center,
+            nodes: Vec::new(),
+        }
+    }
+
+    pub fn horizontal(spacing: u32) -> Stack {
+        Stack::new(Direction::Horizontal, spacing)
+    }
+
+    pub fn vertical(spacing: u32) -> Stack {
+        Stack::new(Direction::Vertical, spacing)
+    }
+
+    pub fn set_align(&mut self, align: Align) {
+        self.align = align;
+    }
+
+    pub fn add<'a, W: Widget + 'static>(&'a mut self, widget: Rc<RefCell<W>>) -> &'a mut Stack {
+        self.nodes.push(Node::Widget(widget));
+        self
+    }
+
+    pub fn add_stack<'a>(&'a mut self, stack: Stack) -> &'a mut Stack {
+        self.nodes.push(Node::Stack(stack));
+        self
+    }
+
+    // get_size is the box around the stacked nodes.
+    pub fn get_size(&self) -> (u32, u32) {
+        let (mut along, mut across) = (0, 0);
+        for (i, node) in self.nodes.iter().enumerate() {
+            let (a, b) = self.split(node.get_size());
+            along += a + if i > 0 { self.spacing } else { 0 };
+            across = across.max(b);
+        }
+        self.split((along, across))
+    }
+
+    // set_position moves the top left corner of the stack, laying out its nodes.
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        let (_, across) = self.split(self.get_size());
+        let (mut at, base) = self.split_point(x, y);
+        let factor = match self.align {
+            Align::Left => 0.0,
+            Align::Center => 0.5,
+            Align::Right => 1.0,
+        };
+        let direction = self.direction;
+        let spacing = self.spacing as i32;
+        for node in &mut self.nodes {
+            let (a, b) = match direction {
+                Direction::Horizontal => node.get_size(),
+                Direction::Vertical => {
+                    let (w, h) = node.get_size();
+                    (h, w)
+                }
+            };
+            let off = base + place_axis(across as i32, factor, 0, b);
+            match direction {
+                Direction::Horizontal => node.set_position(at, off),
+                Direction::Vertical => node.set_position(off, at),
+            }
+            at += a as i32 + spacing;
+        }
+    }
+
+    // split turns a size into its length along the stack and across it, and back.
+    fn split(&self, size: (u32, u32)) -> (u32, u32) {
+        match self.direction {
+            Direction::Horizontal => size,
+            Direction::Vertical => (size.1, size.0),
+        }
+    }
+
+    fn split_point(&self, x: i32, y: i32) -> (i32, i32) {
+        match self.direction {
+            Direction::Horizontal => (x, y),
+            Direction::Vertical => (y, x),
+        }
+    }
+}
+
+struct Entry {
+    node: Node,
+    anchor: Anchor,
+    margin: (i32, i32),
+}
+
+// Layout places widgets and stacks on the screen by their anchors, and
+// places them again whenever the screen changes size, so nothing needs to
+// work out positions from the screen size by hand.
+pub struct Layout {
+    width: u32,
+    height: u32,
+    entries: Vec<Entry>,
+}
+
+impl Layout {
+    pub fn new(w: u32, h: u32) -> Layout {
+        Layout {
+            width: w,
+            height: h,
+            entries: Vec::new(),
+        }
+    }
+
+    // anchor places a widget's anchor point on the screen's, `margin` in
+    // from the edges, e.g. a pause button at TopLeft or a copyright at Bottom.
+    pub fn anchor<W: Widget + 'static>(&mut self, widget: Rc<RefCell<W>>, anchor: Anchor, margin: (i32, i32)) {
+        self.add(Node::Widget(widget), anchor, margin);
+    }
+
+    pub fn anchor_stack(&mut self, stack: Stack, anchor: Anchor, margin: (i32, i32)) {
+        self.add(Node::Stack(stack), anchor, margin);
+    }
+
+    fn add(&mut self, node: Node, anchor: Anchor, margin: (i32, i32)) {
+        let mut entry = Entry {
+            node: node,
+            anchor: anchor,
+            margin: margin,
+        };
+        self.place(&mut entry);
+        self.entries.push(entry);
+    }
+
+    pub fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // resize lays everything out again for a new screen size.
+    pub fn resize(&mut self, w: u32, h: u32) {
+        self.width = w;
+        self.height = h;
+        self.apply();
+    }
+
+    // apply lays everything out again, after a widget changed size.
+    pub fn apply(&mut self) {
+        let mut entries = mem::replace(&mut self.entries, Vec::new());
+        for entry in &mut entries {
+            self.place(entry);
+        }
+        self.entries = entries;
+    }
+
+    // point is a point of the screen by anchor, for what is not a widget.
+    pub fn point(&self, anchor: Anchor, margin: (i32, i32)) -> (i32, i32) {
+        place(Rect::new(0, 0, self.width, self.height), anchor, margin, 0, 0)
+    }
+
+    fn place(&self, entry: &mut Entry) {
+        let (w, h) = entry.node.get_size();
+        let area = Rect::new(0, 0, self.width, self.height);
+        let (x, y) = place(area, entry.anchor, entry.margin, w, h);
+        entry.node.set_position(x, y);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use sdl2::render::Renderer;
+
+    use display::Displayable;
+
+    struct Block {
+        rect: Rect,
+    }
+
+    impl Displayable for Block {
+        fn update(&mut self) {}
+        fn paint(&self, _renderer: &mut Renderer) {}
+    }
+
+    impl Widget for Block {
+        fn get_rect(&self) -> Rect {
+            self.rect
+        }
+        fn set_position(&mut self, x: i32, y: i32) {
+            self.rect.set_x(x);
+            self.rect.set_y(y);
+        }
+    }
+
+    fn block(w: u32, h: u32) -> Rc<RefCell<Block>> {
+        Rc::new(RefCell::new(Block { rect: Rect::new(0, 0, w, h) }))
+    }
+
+    fn at(b: &Rc<RefCell<Block>>) -> (i32, i32) {
+        let r = b.borrow().rect;
+        (r.x(), r.y())
+    }
+
+    #[test]
+    fn test_place_corners() {
+        let area = Rect::new(0, 0, 800, 600);
+        assert_eq!(place(area, Anchor::TopLeft, (16, 16), 26, 28), (16, 16));
+        assert_eq!(place(area, Anchor::BottomRight, (16, 16), 26, 28), (758, 556));
+        assert_eq!(place(area, Anchor::Bottom, (0, 10), 100, 20), (350, 570));
+    }
+
+    #[test]
+    fn test_place_center_margin_is_offset() {
+        let area = Rect::new(0, 0, 800, 600);
+        assert_eq!(place(area, Anchor::Center, (0, -100), 0, 0), (400, 200));
+        assert_eq!(place(area, Anchor::Center, (0, 0), 200, 100), (300, 250));
+    }
+
+    #[test]
+    fn test_horizontal_stack() {
+        let (a, b) = (block(100, 40), block(50, 20));
+        let mut stack = Stack::horizontal(10);
+        stack.add(a.clone()).add(b.clone());
+        assert_eq!(stack.get_size(), (160, 40));
+        stack.set_position(5, 5);
+        assert_eq!(at(&a), (5, 5));
+        assert_eq!(at(&b), (115, 15));
+    }
+
+    #[test]
+    fn test_vertical_stack_aligned_left() {
+        let (a, b) = (block(100, 40), block(50, 20));
+        let mut stack = Stack::vertical(8);
+        stack.set_align(Align::Left);
+        stack.add(a.clone()).add(b.clone());
+        assert_eq!(stack.get_size(), (100, 68));
+        stack.set_position(0, 0);
+        assert_eq!(at(&b), (0, 48));
+    }
+
+    #[test]
+    fn test_layout_resize() {
+        let button = block(26, 28);
+        let mut layout = Layout::new(800, 600);
+        layout.anchor(button.clone(), Anchor::TopRight, (16, 16));
+        assert_eq!(at(&button), (758, 16));
+        layout.resize(1000, 600);
+        assert_eq!(at(&button), (958, 16));
+    }
+}
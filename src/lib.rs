@@ -3,6 +3,7 @@ extern crate sdl2;
 pub mod atlas;
 pub mod node;
 pub mod display;
+pub mod transform;
 use std::collections::HashMap;
 
 
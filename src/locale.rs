@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use serde_json::{self, Value};
+
+use text::DEFAULT_FONT;
+
+// Locale files are named by language, e.g. res/locale/zh.json.
+pub const LOCALE_DIR: &'static str = "res/locale";
+// Strings missing from a locale are taken from this one.
+pub const FALLBACK_LANGUAGE: &'static str = "en";
+
+// Message is a string of a locale, or its plural forms by category.
+enum Message {
+    Text(String),
+    Plural(HashMap<String, String>),
+}
+
+// StringTable is one locale file: the language's own name, the font able
+// to draw it, and its strings by key. A locale file looks like
+//
+//     {
+//         "name": "English",
+//         "font": "res/fonts/default.ttf",
+//         "strings": {
+//             "pause.resume": "Resume",
+//             "score.points": { "one": "{n} point", "other": "{n} points" }
+//         }
+//     }
+pub struct StringTable {
+    name: String,
+    font: Option<String>,
+    messages: HashMap<String, Message>,
+}
+
+impl StringTable {
+    pub fn parse(text: &str) -> Result<StringTable, String> {
+        let root: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let mut messages = HashMap::new();
+        if let Some(strings) = root["strings"].as_object() {
+            for (key, value) in strings {
+                let message = match *value {
+                    Value::String(ref s) => Message::Text(s.clone()),
+                    Value::Object(ref forms) => {
+                        Message::Plural(forms.iter()
+                                             .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                                             .collect())
+                    }
+                    _ => return Err(format!("{}: not a string or plural forms", key)),
+                };
+                messages.insert(key.clone(), message);
+            }
+        }
+        Ok(StringTable {
+            name: root["name"].as_str().unwrap_or("").to_string(),
+            font: root["font"].as_str().map(|s| s.to_string()),
+            messages: messages,
+        })
+    }
+
+    pub fn load(path: &str) -> Result<StringTable, String> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| format!("{}: {}", path, e))?;
+        StringTable::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+// plural_category is the CLDR plural category of `n` in a language. Chinese,
+// Japanese and Korean have a single form, the others here only tell one
+// from many.
+pub fn plural_category(language: &str, n: i64) -> &'static str {
+    match language {
+        "zh" | "ja" | "ko" => "other",
+        _ if n == 1 => "one",
+        _ => "other",
+    }
+}
+
+// substitute replaces every `{name}` in a template by its argument.
+fn substitute(template: &str, args: &[(&str, &str)]) -> String {
+    let mut s = template.to_string();
+    for &(name, value) in args {
+        s = s.replace(&format!("{{{}}}", name), value);
+    }
+    s
+}
+
+// system_language is the language the user's environment asks for, e.g.
+// "zh" for LANG=zh_CN.UTF-8.
+pub fn system_language() -> Option<String> {
+    let lang = env::var("LC_ALL").or_else(|_| env::var("LANG")).ok()?;
+    let code = lang.split(|c| c == '_' || c == '.' || c == '-').next().unwrap_or("");
+    if code.is_empty() || code == "C" || code == "POSIX" {
+        None
+    } else {
+        Some(code.to_lowercase())
+    }
+}
+
+// Strings holds the string tables of every locale and looks text up in the
+// current language, then in the fallback one, then gives the key itself so
+// a missing string shows where it is missing. Changing the language bumps
+// the revision, for whoever renders text to notice and render it again.
+pub struct Strings {
+    tables: HashMap<String, StringTable>,
+    language: String,
+    fallback: String,
+    revision: u32,
+}
+
+impl Strings {
+    pub fn new(fallback: &str) -> Strings {
+        Strings {
+            tables: HashMap::new(),
+            language: fallback.to_string(),
+            fallback: fallback.to_string(),
+            revision: 0,
+        }
+    }
+
+    // load_dir loads every .json file of a directory as the locale of the
+    // language it is named after.
+    pub fn load_dir(dir: &str, fallback: &str) -> Result<Strings, String> {
+        let mut strings = Strings::new(fallback);
+        let entries = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir, e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| format!("{}: {}", dir, e))?.path();
+            if path.extension().map_or(true, |e| e != "json") {
+                continue;
+            }
+            let language = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(s) => s.to_string(),
+                None => continue,
+            };
+            let table = StringTable::load(&path.to_string_lossy())?;
+            strings.add(&language, table);
+        }
+        if !strings.tables.contains_key(fallback) {
+            return Err(format!("{}: no locale for the fallback language {}", dir, fallback));
+        }
+        Ok(strings)
+    }
+
+    pub fn add(&mut self, language: &str, table: StringTable) {
+        self.tables.insert(language.to_string(), table);
+    }
+
+    // get_languages lists the loaded languages, sorted.
+    pub fn get_languages(&self) -> Vec<String> {
+        let mut languages: Vec<String> = self.tables.keys().cloned().collect();
+        languages.sort();
+        languages
+    }
+
+    pub fn get_language(&self) -> &str {
+        &self.language
+    }
+
+    // set_language switches to a loaded language, unless the font it needs
+    // to be drawn is missing.
+    pub fn set_language(&mut self, language: &str) -> Result<(), String> {
+        let font = match self.tables.get(language) {
+            Some(table) => table.font.as_ref(),
+            None => return Err(format!("no locale for {}", language)),
+        };
+        if let Some(font) = font {
+            if !Path::new(font).exists() {
+                return Err(format!("{}: missing font {}", language, font));
+            }
+        }
+        if self.language != language {
+            self.language = language.to_string();
+            self.revision += 1;
+        }
+        Ok(())
+    }
+
+    // next_language switches to the language after the current one, for a
+    // settings row cycling through them, skipping any it can't switch to.
+    pub fn next_language(&mut self) {
+        let languages = self.get_languages();
+        if let Some(i) = languages.iter().position(|l| *l == self.language) {
+            for step in 1..languages.len() {
+                if self.set_language(&languages[(i + step) % languages.len()]).is_ok() {
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn get_revision(&self) -> u32 {
+        self.revision
+    }
+
+    // get_name is the current language's name for itself, e.g. "中文".
+    pub fn get_name(&self) -> String {
+        self.tables.get(&self.language).map_or(self.language.clone(), |t| t.name.clone())
+    }
+
+    // get_font is the font of the current language, or the default font
+    // when it names none.
+    pub fn get_font(&self) -> &str {
+        match self.tables.get(&self.language).and_then(|t| t.font.as_ref()) {
+            Some(font) => font.as_str(),
+            None => DEFAULT_FONT,
+        }
+    }
+
+    fn lookup(&self, key: &str) -> Option<(&str, &Message)> {
+        for &language in &[&self.language, &self.fallback] {
+            if let Some(message) = self.tables.get(language).and_then(|t| t.messages.get(key)) {
+                return Some((language.as_str(), message));
+            }
+        }
+        None
+    }
+
+    pub fn get(&self, key: &str) -> String {
+        self.format(key, &[])
+    }
+
+    // format is the string of a key with its `{name}` arguments filled in,
+    // the "other" form of a plural.
+    pub fn format(&self, key: &str, args: &[(&str, &str)]) -> String {
+        match self.lookup(key) {
+            Some((_, &Message::Text(ref s))) => substitute(s, args),
+            Some((_, &Message::Plural(ref forms))) => {
+                forms.get("other").map_or(key.to_string(), |s| substitute(s, args))
+            }
+            None => key.to_string(),
+        }
+    }
+
+    // plural is the form of a key for a count `n`, with `{n}` and the other
+    // arguments filled in. A missing form falls back to "other".
+    pub fn plural(&self, key: &str, n: i64, args: &[(&str, &str)]) -> String {
+        let count = n.to_string();
+        let mut args = args.to_vec();
+        args.push(("n", count.as_str()));
+        match self.lookup(key) {
+            Some((_, &Message::Text(ref s))) => substitute(s, &args),
+            Some((language, &Message::Plural(ref forms))) => {
+                let form = forms.get(plural_category(language, n)).or_else(|| forms.get("other"));
+                form.map_or(key.to_string(), |s| substitute(s, &args))
+            }
+            None => key.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings() -> Strings {
+        let en = r#"{ "name": "English", "strings": {
+            "pause.resume": "Resume",
+            "pause.quit": "Quit to title",
+            "settings.language": "Language: {language}",
+            "score.points": { "one": "{n} point", "other": "{n} points" }
+        } }"#;
+        let zh = r#"{ "name": "中文", "strings": {
+            "pause.resume": "继续",
+            "score.points": { "other": "{n} 分" }
+        } }"#;
+        let mut strings = Strings::new("en");
+        strings.add("en", StringTable::parse(en).unwrap());
+        strings.add("zh", StringTable::parse(zh).unwrap());
+        strings
+    }
+
+    #[test]
+    fn test_fallback() {
+        let mut strings = strings();
+        strings.set_language("zh").unwrap();
+        assert_eq!(strings.get("pause.resume"), "继续");
+        assert_eq!(strings.get("pause.quit"), "Quit to title");
+        assert_eq!(strings.get("pause.missing"), "pause.missing");
+    }
+
+    #[test]
+    fn test_format() {
+        let strings = strings();
+        assert_eq!(strings.format("settings.language", &[("language", "English")]),
+                   "Language: English");
+    }
+
+    #[test]
+    fn test_plural() {
+        let mut strings = strings();
+        assert_eq!(strings.plural("score.points", 1, &[]), "1 point");
+        assert_eq!(strings.plural("score.points", 3, &[]), "3 points");
+        strings.set_language("zh").unwrap();
+        assert_eq!(strings.plural("score.points", 1, &[]), "1 分");
+    }
+
+    #[test]
+    fn test_switch_language() {
+        let mut strings = strings();
+        assert!(strings.set_language("fr").is_err());
+        assert_eq!(strings.get_revision(), 0);
+        strings.next_language();
+        assert_eq!(strings.get_language(), "zh");
+        assert_eq!(strings.get_name(), "中文");
+        assert_eq!(strings.get_revision(), 1);
+        strings.next_language();
+        assert_eq!(strings.get_language(), "en");
+    }
+
+    #[test]
+    fn test_missing_font() {
+        let mut strings = strings();
+        let ja = r#"{ "name": "日本語", "font": "res/fonts/missing.ttf", "strings": {} }"#;
+        strings.add("ja", StringTable::parse(ja).unwrap());
+        assert!(strings.set_language("ja").is_err());
+        assert_eq!(strings.get_language(), "en");
+        strings.next_language();
+        assert_eq!(strings.get_language(), "zh");
+        strings.next_language();
+        assert_eq!(strings.get_language(), "en");
+    }
+
+    #[test]
+    fn test_shipped_locales() {
+        let mut strings = Strings::load_dir(LOCALE_DIR, FALLBACK_LANGUAGE).unwrap();
+        for language in strings.get_languages() {
+            strings.set_language(&language).unwrap();
+            assert!(Path::new(strings.get_font()).exists());
+        }
+    }
+}
@@ -1,5 +1,8 @@
 extern crate sdl2;
 extern crate rand;
+extern crate xml;
+extern crate serde_json;
+extern crate plist;
 
 pub mod node;
 pub mod layer;
@@ -10,33 +13,64 @@ pub mod display;
 pub mod flappy;
 pub mod scheduler;
 pub mod atlas;
+pub mod replay;
+pub mod transform;
+pub mod group;
+pub mod camera;
+pub mod viewport;
+pub mod parallax;
+pub mod scroll;
+pub mod tilemap;
+pub mod particles;
+pub mod actions;
+pub mod easing;
+pub mod script;
+pub mod gameover;
+pub mod pause;
+pub mod ui;
+pub mod layout;
+pub mod text;
+pub mod bmfont;
+pub mod locale;
 
 #[cfg(target_os = "emscripten")]
 pub mod emscripten;
 
 use std::process;
 use std::path::Path;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::thread;
 use std::rc::Rc;
+use std::cell::RefCell;
 
 use sdl2::pixels::Color;
 use sdl2::image::{INIT_PNG, INIT_JPG};
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
 use sdl2::rect::Rect;
 use sdl2::render::Renderer;
 use sdl2::mixer::{INIT_OGG, AUDIO_S16LSB};
 
 // use scene::Scene;
-use flappy::{Bird, FlappyScene};
-use display::Displayable;
+use flappy::{Bird, FlappyScene, StartScene};
+use gameover::Choice;
+use display::{Displayable, Float};
+use scheduler::Scheduler;
+use viewport::{Viewport, ScaleMode};
+use text::TextSystem;
+use locale::{Strings, LOCALE_DIR, FALLBACK_LANGUAGE};
 
-macro_rules! rect(
-    ($x:expr, $y:expr, $w:expr, $h:expr) => (
-        Rect::new($x as i32, $y as i32, $w as u32, $h as u32)
-    )
-);
+// The game is laid out for this size and scaled to fit the window.
+const DESIGN_W: u32 = 800;
+const DESIGN_H: u32 = 600;
+
+// Scheduler targets of the game loop.
+const GAME_OVER: i32 = 1;
+// Seconds between the crash and the game over screen.
+const GAME_OVER_DELAY: f32 = 1.0;
+// Longer frames, e.g. while the window is dragged, count as this many
+// seconds so timers don't all fire at once afterwards.
+const MAX_FRAME_TIME: f32 = 0.25;
 
 pub fn main() {
     let sdl_context = sdl2::init().unwrap();
@@ -44,6 +78,16 @@ pub fn main() {
     // let _mixer_context = sdl2::mixer::init(INIT_OGG).unwrap();
     // let music = sdl2::mixer::Music::from_file(Path::new("res/audio/soundtrack.ogg")).unwrap();
     let video_subsystem = sdl_context.video().unwrap();
+    // Gamepads plugged in at start move through the menus like the arrow keys.
+    let controller_subsystem = sdl_context.game_controller().unwrap();
+    let mut _controllers = Vec::new();
+    for id in 0..controller_subsystem.num_joysticks().unwrap_or(0) {
+        if controller_subsystem.is_game_controller(id) {
+            if let Ok(controller) = controller_subsystem.open(id) {
+                _controllers.push(controller);
+            }
+        }
+    }
 
     let frequency = 44100;
     let format = AUDIO_S16LSB; // signed 16 bit samples, in little-endian byte order
@@ -52,20 +96,43 @@ pub fn main() {
     // let _ = sdl2::mixer::open_audio(frequency, format, channels, chunk_size).unwrap();
     // sdl2::mixer::allocate_channels(0);
 
-    let window = video_subsystem.window("Chinese chess Rust", 800, 600)
+    // The game speaks the user's language when it has a locale for it.
+    let mut strings = match Strings::load_dir(LOCALE_DIR, FALLBACK_LANGUAGE) {
+        Ok(strings) => strings,
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    };
+    if let Some(language) = locale::system_language() {
+        if strings.get_languages().contains(&language) {
+            if let Err(e) = strings.set_language(&language) {
+                println!("{}", e);
+            }
+        }
+    }
+    let strings = Rc::new(RefCell::new(strings));
+    let mut revision = strings.borrow().get_revision();
+
+    let window = video_subsystem.window(&strings.borrow().get("window.title"), DESIGN_W, DESIGN_H)
                                 .position_centered()
+                                .resizable()
                                 .opengl()
                                 .build()
                                 .unwrap();
 
     let _image_context = sdl2::image::init(INIT_PNG | INIT_JPG).unwrap();
 
-    let mut renderer = window.renderer().build().unwrap();
+    // Presenting in step with the display keeps updates about a frame apart.
+    let mut renderer = window.renderer().present_vsync().build().unwrap();
 
     renderer.set_draw_color(Color::RGB(255, 255, 255));
     renderer.clear();
     renderer.present();
     let mut event_pump = sdl_context.event_pump().unwrap();
+    let mut viewport = Viewport::new(DESIGN_W, DESIGN_H, ScaleMode::Letterbox);
+    let (window_w, window_h) = renderer.output_size().unwrap();
+    viewport.resize(window_w, window_h);
 
     // draw_title: Flappy Rust
     // draw_title("Chinese chess", &mut renderer);
@@ -76,13 +143,49 @@ pub fn main() {
 
     // Testing a bird
     // let mut scene = Scene::new(&mut renderer, "res/imgs/background.png");
-    let mut scene = FlappyScene::new(&mut renderer, 800, 600);
+    let ttf_context = sdl2::ttf::init().unwrap();
+    let mut text = TextSystem::new(&ttf_context);
+    let mut scene = FlappyScene::new(&mut renderer, &mut text, strings.clone(), DESIGN_W, DESIGN_H);
     // let mut bird = Bird::new(&mut renderer);
     // scene.add_child(Rc::new(bird));
+    // A Tiled map given on the command line replaces the random pipes.
+    if let Some(course) = std::env::args().nth(1) {
+        if let Err(e) = scene.load_course(&renderer, &course) {
+            println!("{}", e);
+        }
+    }
     scene.start();
-    scene.paint(&mut renderer);
+    // The game opens on the title screen.
+    let mut title = match StartScene::new(&renderer, &mut text, strings.clone(), &scene, DESIGN_W, DESIGN_H) {
+        Ok(title) => title,
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    };
+    title.set_best_score(scene.get_best_score());
+    title.start();
+    let mut in_title = true;
+    let mut scheduler: Scheduler<FlappyScene> = Scheduler::new();
+    let mut paused = false;
+    let mut last_frame = Instant::now();
+    viewport.apply(&mut renderer);
+    title.paint(&mut renderer);
     let mut main_loop = || {
         for event in event_pump.poll_iter() {
+            // Leaving the window pauses the game.
+            if let Event::Window { win_event: WindowEvent::FocusLost, .. } = event {
+                if !in_title {
+                    scene.pause();
+                }
+            }
+            // Both scenes lay themselves out again when the logical size changes.
+            if viewport.on_event(&event) {
+                let (w, h) = viewport.get_logical_size();
+                title.on_resize(w, h);
+                scene.on_resize(w, h);
+            }
+            let current: &mut Displayable = if in_title { &mut title } else { &mut scene };
             match event {
                 Event::Quit { .. } => {
                     process::exit(0);
@@ -91,32 +194,90 @@ pub fn main() {
                     process::exit(0);
                 }
                 Event::KeyDown { .. } => {
-                    scene.on_key_down(&event);
+                    current.on_key_down(&event);
                 }
                 Event::KeyUp { .. } => {
-                    scene.on_key_up(&event);
+                    current.on_key_up(&event);
+                }
+                Event::ControllerButtonDown { .. } => {
+                    current.on_key_down(&event);
+                }
+                Event::MouseButtonDown { .. } => {
+                    current.on_mouse_down(&viewport.map_event(&event));
+                }
+                Event::MouseButtonUp { .. } => {
+                    current.on_mouse_up(&viewport.map_event(&event));
+                }
+                Event::MouseMotion { .. } => {
+                    current.on_mouse_move(&viewport.map_event(&event));
                 }
                 _ => {}
             }
         }
 
+        // A new language renders every menu and the window title again.
+        if strings.borrow().get_revision() != revision {
+            revision = strings.borrow().get_revision();
+            text.clear_strings();
+            if let Err(e) = title.relabel(&renderer, &mut text).and_then(|_| scene.relabel(&renderer, &mut text)) {
+                println!("{}", e);
+            }
+            if let Some(window) = renderer.window_mut() {
+                let _ = window.set_title(&strings.borrow().get("window.title"));
+            }
+        }
+
         // The rest of the game loop goes here...
         thread::sleep(Duration::from_millis(10));
-        renderer.clear();
+        // The scheduler runs on the time that really passed since last frame.
+        let now = Instant::now();
+        let dt = now.duration_since(last_frame).to_f32().min(MAX_FRAME_TIME);
+        last_frame = now;
+        if in_title && title.take_play() {
+            title.stop();
+            in_title = false;
+        }
+
+        // Timers and music stop along with the game.
+        if scene.is_paused() != paused {
+            paused = scene.is_paused();
+            if paused {
+                sdl2::mixer::Music::pause();
+            } else {
+                sdl2::mixer::Music::resume();
+            }
+        }
+        if !paused && !in_title {
+            scheduler.update(dt, &mut scene);
+        }
 
-        // Update and paint scene
-        scene.update();
-        scene.paint(&mut renderer);
+        if scene.game_over() && !scene.is_game_over_shown() && !scheduler.has_target(GAME_OVER) {
+            scheduler.schedule_once(GAME_OVER, GAME_OVER_DELAY, |scene| scene.show_game_over());
+        }
+        match scene.take_choice() {
+            Some(Choice::Restart) => scene.restart(),
+            Some(Choice::Menu) => {
+                // The run ends here, the next one waits behind the title.
+                scene.restart();
+                title.set_best_score(scene.get_best_score());
+                title.start();
+                in_title = true;
+            }
+            None => {}
+        }
 
+        renderer.clear();
+        viewport.apply(&mut renderer);
 
-        if scene.game_over() {
-            draw_title("Game Over", &mut renderer);
-            // TODO: do this based on time elapsed rather than sleep.
-            thread::sleep(Duration::from_millis(3000));
-            scene.restart();
-            // flappy.restart();
-            // pipes = Pipes::new(&mut renderer);
+        // Update and paint the current scene. The game's actions and scripts
+        // run on the scheduler's clock, so waits and timers end together.
+        if in_title {
+            title.update();
+        } else {
+            scene.update_with(dt, &scheduler.get_scheduled());
         }
+        let current: &Displayable = if in_title { &title } else { &scene };
+        current.paint(&mut renderer);
 
         renderer.present();
     };
@@ -132,27 +293,3 @@ pub fn main() {
         main_loop();
     }
 }
-
-fn draw_title(title: &str, renderer: &mut Renderer) {
-    renderer.clear();
-
-    // Load a font
-    let font_path = Path::new("res/fonts/default.ttf");
-    let ttf_context = sdl2::ttf::init().unwrap();
-    let mut font = ttf_context.load_font(font_path, 50).unwrap();
-    font.set_style(sdl2::ttf::STYLE_BOLD);
-
-    // Render the surface
-    let surface = font.render(title)
-                      .blended(Color::RGBA(255, 87, 0, 255))
-                      .unwrap();
-    let mut texture = renderer.create_texture_from_surface(&surface).unwrap();
-
-    renderer.set_draw_color(Color::RGBA(0, 217, 255, 255));
-    renderer.clear();
-
-    renderer.copy(&mut texture, None, Some(rect!(10, 10, 790, 590)))
-            .unwrap();
-
-    renderer.present();
-}
@@ -16,9 +16,10 @@ use sdl2::image::LoadTexture;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 
+use transform::Transform;
+
 pub struct Node {
-    x: i32,
-    y: i32,
+    transform: Transform,
     w: u32,
     h: u32,
     interval: f32,
@@ -46,8 +47,7 @@ impl Node {
         let tquery = rc_textures[0].query();
 
         Node {
-            x: 0,
-            y: 0,
+            transform: Transform::identity(),
             w: tquery.width,
             h: tquery.height,
             interval: 0.0,
@@ -63,8 +63,7 @@ impl Node {
         rc_textures.push(texture);
         let tquery = rc_textures[0].query();
         Node {
-            x: 0,
-            y: 0,
+            transform: Transform::identity(),
             w: tquery.width,
             h: tquery.height,
             interval: 0.0,
@@ -134,12 +133,34 @@ impl Node {
     }
 
     pub fn set_position(&mut self, x: i32, y: i32) {
-        self.x = x;
-        self.y = y;
+        self.transform.x = x as f32;
+        self.transform.y = y as f32;
     }
 
     pub fn get_position(&self) -> (i32, i32) {
-        (self.x, self.y)
+        (self.transform.x as i32, self.transform.y as i32)
+    }
+
+    pub fn set_scale(&mut self, sx: f32, sy: f32) {
+        self.transform.scale_x = sx;
+        self.transform.scale_y = sy;
+    }
+
+    pub fn set_rotation(&mut self, degrees: f32) {
+        self.transform.rotation = degrees;
+    }
+
+    pub fn set_anchor(&mut self, ax: f32, ay: f32) {
+        self.transform.anchor_x = ax;
+        self.transform.anchor_y = ay;
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    pub fn get_transform(&self) -> Transform {
+        self.transform
     }
 
     pub fn set_size(&mut self, w: u32, h: u32) {
@@ -159,15 +180,20 @@ impl Node {
     }
 
     pub fn paint(&self, renderer: &mut Renderer) {
-        let rect = Rect::new(self.x, self.y, self.w, self.h);
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    pub fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        let world = parent.then(&self.transform);
+        let (rect, pivot) = world.dest_rect(self.w, self.h);
         let idx = self.cursor as usize % self.textures.len();
         renderer.copy_ex(&self.textures[idx],
                          None,
                          Some(rect),
-                         0.0,
-                         None,
-                         false,
-                         false)
+                         world.rotation as f64,
+                         Some(pivot),
+                         world.scale_x < 0.0,
+                         world.scale_y < 0.0)
                 .expect("Single star particle should have rendered.");
     }
 
@@ -181,6 +207,10 @@ impl Node {
         self
     }
 
+    pub fn get_visible(&self) -> bool {
+        self.visible
+    }
+
     pub fn blend_mode_none(&mut self) {
         for child in &mut self.textures {
             // child.borrow_mut().update();
@@ -0,0 +1,153 @@
+extern crate sdl2;
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::rc::Rc;
+
+use sdl2::render::Renderer;
+
+use layer::{Layer, RollMode};
+use scene::Scene;
+
+#[derive(Clone)]
+pub struct ParallaxLayer {
+    factor: f32,
+    layer: Rc<RefCell<Layer>>,
+}
+
+// ParallaxBackground drives a stack of scrolling layers from one world
+// scroll speed. Each layer moves at its own fraction of that speed and
+// follows the camera by the same fraction, so 0.0 is the sky and 1.0 the
+// ground the pipes stand on. The layers are painted by the scene they are
+// attached to; the background only sets their speed every frame.
+pub struct ParallaxBackground {
+    w: u32,
+    h: u32,
+    speed: f32,
+    paused: bool,
+    layers: Vec<ParallaxLayer>,
+}
+
+impl ParallaxBackground {
+    pub fn new(w: u32, h: u32) -> ParallaxBackground {
+        ParallaxBackground {
+            w: w,
+            h: h,
+            speed: 0.0,
+            paused: false,
+            layers: Vec::new(),
+        }
+    }
+
+    // load reads one layer per line, back to front:
+    //   <image path> <speed factor> <vertical offset> <repeat gap>
+    // Blank lines and lines starting with '#' are skipped.
+    pub fn load(renderer: &Renderer, w: u32, h: u32, path: &str) -> Result<ParallaxBackground, String> {
+        let file = File::open(Path::new(path)).map_err(|e| e.to_string())?;
+        let mut bg = ParallaxBackground::new(w, h);
+
+        for line in BufReader::new(file).lines() {
+            let l = line.map_err(|e| e.to_string())?;
+            let l = l.trim();
+            if l.is_empty() || l.starts_with('#') {
+                continue;
+            }
+            let items: Vec<&str> = l.split_whitespace().collect();
+            if items.len() < 2 {
+                return Err(format!("bad parallax layer: {}", l));
+            }
+            let factor = items[1].parse::<f32>().map_err(|e| e.to_string())?;
+            let offset_y = match items.get(2) {
+                Some(v) => v.parse::<i32>().map_err(|e| e.to_string())?,
+                None => 0,
+            };
+            let gap = match items.get(3) {
+                Some(v) => v.parse::<u32>().map_err(|e| e.to_string())?,
+                None => 0,
+            };
+            bg.add_layer(renderer, items[0], factor, offset_y, gap);
+        }
+        Ok(bg)
+    }
+
+    pub fn add_layer(&mut self,
+                     renderer: &Renderer,
+                     path: &str,
+                     factor: f32,
+                     offset_y: i32,
+                     gap: u32)
+                     -> Rc<RefCell<Layer>> {
+        let layer = Rc::new(RefCell::new(Layer::new(renderer, self.w, self.h, path)));
+        {
+            let mut l = layer.borrow_mut();
+            l.set_scroll(RollMode::Horizontal);
+            l.set_scroll_step(0.0);
+            l.set_gap(gap, 0);
+            l.set_parallax(factor);
+            l.set_position(0, offset_y);
+        }
+        self.layers.push(ParallaxLayer {
+            factor: factor,
+            layer: layer.clone(),
+        });
+        layer
+    }
+
+    // share is another background over the same layers, for a second scene
+    // to drive at its own speed. The layers scroll on from where they were.
+    pub fn share(&self) -> ParallaxBackground {
+        ParallaxBackground {
+            w: self.w,
+            h: self.h,
+            speed: self.speed,
+            paused: self.paused,
+            layers: self.layers.clone(),
+        }
+    }
+
+    // attach adds the layers to a scene, back to front from z upwards.
+    pub fn attach(&self, scene: &mut Scene, name: &str, z: i32) {
+        for (i, l) in self.layers.iter().enumerate() {
+            scene.add_child_z(&format!("{}_{}", name, i), z + i as i32, l.layer.clone());
+        }
+    }
+
+    pub fn get_layer(&self, idx: usize) -> Option<Rc<RefCell<Layer>>> {
+        self.layers.get(idx).map(|l| l.layer.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    // set_speed is the world scroll speed in pixels per frame.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // update hands every layer its share of the world speed for this frame.
+    pub fn update(&mut self) {
+        let speed = if self.paused { 0.0 } else { self.speed };
+        for l in &self.layers {
+            l.layer.borrow_mut().set_scroll_step(speed * l.factor);
+        }
+    }
+}
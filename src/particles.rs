@@ -0,0 +1,751 @@
+extern crate sdl2;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::rc::Rc;
+
+use plist::Value as Plist;
+use rand::{thread_rng, Rng};
+use sdl2::rect::Rect;
+use sdl2::render::{Renderer, BlendMode};
+use serde_json::{self, Value};
+
+use atlas::{TexElement, Tint};
+use display::{Displayable, FRAME_TIME};
+use transform::Transform;
+
+// Curve is a value over a particle's life, linear between keys at
+// normalized times from 0.0 (birth) to 1.0 (death).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    keys: Vec<(f32, f32)>,
+}
+
+impl Curve {
+    pub fn constant(v: f32) -> Curve {
+        Curve { keys: vec![(0.0, v)] }
+    }
+
+    pub fn linear(from: f32, to: f32) -> Curve {
+        Curve { keys: vec![(0.0, from), (1.0, to)] }
+    }
+
+    // new takes (time, value) keys, sorting them by time.
+    pub fn new(mut keys: Vec<(f32, f32)>) -> Result<Curve, String> {
+        check_times(&keys)?;
+        keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        Ok(Curve { keys: keys })
+    }
+
+    pub fn eval(&self, t: f32) -> f32 {
+        match lerp_keys(&self.keys, t) {
+            Some((a, b, f)) => self.keys[a].1 + (self.keys[b].1 - self.keys[a].1) * f,
+            None => 0.0,
+        }
+    }
+}
+
+// Gradient is a color and alpha over a particle's life, like Curve.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    keys: Vec<(f32, Tint)>,
+}
+
+impl Gradient {
+    pub fn constant(tint: Tint) -> Gradient {
+        Gradient { keys: vec![(0.0, tint)] }
+    }
+
+    pub fn linear(from: Tint, to: Tint) -> Gradient {
+        Gradient { keys: vec![(0.0, from), (1.0, to)] }
+    }
+
+    pub fn new(mut keys: Vec<(f32, Tint)>) -> Result<Gradient, String> {
+        check_times(&keys)?;
+        keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        Ok(Gradient { keys: keys })
+    }
+
+    pub fn eval(&self, t: f32) -> Tint {
+        match lerp_keys(&self.keys, t) {
+            Some((a, b, f)) => {
+                let (a, b) = (self.keys[a].1, self.keys[b].1);
+                let mix = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * f).round() as u8;
+                Tint::rgba(mix(a.r, b.r), mix(a.g, b.g), mix(a.b, b.b), mix(a.a, b.a))
+            }
+            None => Tint::white(),
+        }
+    }
+}
+
+// check_times rejects keys that can't be sorted by time.
+fn check_times<T>(keys: &[(f32, T)]) -> Result<(), String> {
+    if keys.iter().any(|k| k.0.is_nan()) {
+        return Err("key time is not a number".to_string());
+    }
+    Ok(())
+}
+
+// lerp_keys finds the two keys around `t` and how far it is between them.
+fn lerp_keys<T>(keys: &[(f32, T)], t: f32) -> Option<(usize, usize, f32)> {
+    if keys.is_empty() {
+        return None;
+    }
+    let last = keys.len() - 1;
+    if t <= keys[0].0 {
+        return Some((0, 0, 0.0));
+    }
+    if t >= keys[last].0 {
+        return Some((last, last, 0.0));
+    }
+    let b = keys.iter().position(|k| k.0 > t).unwrap_or(last);
+    let a = b - 1;
+    let span = keys[b].0 - keys[a].0;
+    Some((a, b, if span > 0.0 { (t - keys[a].0) / span } else { 0.0 }))
+}
+
+// EmitterConfig describes an effect. Times are in seconds, distances in
+// pixels and angles in degrees clockwise from the positive x axis; the
+// `_var` fields are the +/- random spread around a value.
+#[derive(Clone)]
+pub struct EmitterConfig {
+    pub max_particles: usize,
+    // Particles per second while running, 0.0 for burst only effects.
+    pub rate: f32,
+    // How long the emitter runs after start, negative for forever.
+    pub duration: f32,
+    pub life: f32,
+    pub life_var: f32,
+    pub speed: f32,
+    pub speed_var: f32,
+    pub angle: f32,
+    pub angle_var: f32,
+    pub gravity: (f32, f32),
+    // Velocity lost per second, 0.0 keeps it and 1.0 stops it within a second.
+    pub drag: f32,
+    // Spawn area around the emitter position.
+    pub position_var: (f32, f32),
+    pub rotation: f32,
+    pub rotation_var: f32,
+    // Rotation speed in degrees per second.
+    pub spin: f32,
+    pub spin_var: f32,
+    // Size in pixels of the frame's larger side over life, spread by size_var as a fraction.
+    pub size: Curve,
+    pub size_var: f32,
+    pub color: Gradient,
+    pub blend: BlendMode,
+    // One frame is picked at random for each particle.
+    pub frames: Vec<Rc<RefCell<TexElement>>>,
+}
+
+impl EmitterConfig {
+    pub fn new(frames: Vec<Rc<RefCell<TexElement>>>) -> EmitterConfig {
+        EmitterConfig {
+            max_particles: 64,
+            rate: 10.0,
+            duration: -1.0,
+            life: 1.0,
+            life_var: 0.0,
+            speed: 50.0,
+            speed_var: 0.0,
+            angle: -90.0,
+            angle_var: 180.0,
+            gravity: (0.0, 0.0),
+            drag: 0.0,
+            position_var: (0.0, 0.0),
+            rotation: 0.0,
+            rotation_var: 0.0,
+            spin: 0.0,
+            spin_var: 0.0,
+            size: Curve::constant(16.0),
+            size_var: 0.0,
+            color: Gradient::linear(Tint::white(), Tint::rgba(255, 255, 255, 0)),
+            blend: BlendMode::Blend,
+            frames: frames,
+        }
+    }
+
+    // load reads an effect preset, a Cocos2d / Particle Designer `.plist` or
+    // a `.json` file with the same keys. Textures are looked up in the atlas
+    // by name, with or without extension, before loading them from disk next
+    // to the preset.
+    pub fn load(renderer: &Renderer,
+                path: &str,
+                atlas: &HashMap<String, Rc<RefCell<TexElement>>>)
+                -> Result<EmitterConfig, String> {
+        let mut bytes = Vec::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_end(&mut bytes))
+            .map_err(|e| format!("{}: {}", path, e))?;
+        let preset = if path.ends_with(".json") {
+            serde_json::from_slice(&bytes).map_err(|e| format!("{}: {}", path, e))?
+        } else {
+            let plist = Plist::from_reader(Cursor::new(bytes)).map_err(|e| format!("{}: {:?}", path, e))?;
+            plist_to_json(plist)
+        };
+
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        let mut cache: HashMap<String, Rc<RefCell<TexElement>>> = HashMap::new();
+        let mut frame = |name: &str| -> Result<Rc<RefCell<TexElement>>, String> {
+            let stem = Path::new(name).file_stem().and_then(|s| s.to_str()).unwrap_or(name);
+            if let Some(tex) = atlas.get(name).or(atlas.get(stem)).or(cache.get(name)) {
+                return Ok(tex.clone());
+            }
+            let file = dir.join(name);
+            let texture = TexElement::load_texture(renderer, &file.to_string_lossy())
+                .map_err(|e| format!("{}: {}", path, e))?;
+            let query = texture.query();
+            let tex = TexElement::new_from_texture(Rc::new(RefCell::new(texture)),
+                                                   Rect::new(0, 0, query.width, query.height));
+            let tex = Rc::new(RefCell::new(tex));
+            cache.insert(name.to_string(), tex.clone());
+            Ok(tex)
+        };
+
+        let mut frames = Vec::new();
+        match preset["frames"] {
+            Value::Array(ref names) => {
+                for name in names.iter().filter_map(|n| n.as_str()) {
+                    frames.push(frame(name)?);
+                }
+            }
+            _ => {
+                if preset["textureImageData"].is_string() && !preset["textureFileName"].is_string() {
+                    return Err(format!("{}: embedded textureImageData is not supported", path));
+                }
+                frames.push(frame(preset["textureFileName"].as_str().unwrap_or("star.png"))?);
+            }
+        }
+        EmitterConfig::from_preset(&preset, frames)
+    }
+
+    // from_preset builds a config out of Cocos2d particle keys. Cocos angles
+    // and gravity point up, ours down, and only the gravity emitter type is
+    // supported. Extra keys: `frames` (texture names), `emissionRate`,
+    // `drag`, `sizeCurve` ([[t, size], ...]) and `colorGradient`
+    // ([[t, r, g, b, a], ...] in 0.0 - 1.0).
+    pub fn from_preset(preset: &Value,
+                       frames: Vec<Rc<RefCell<TexElement>>>)
+                       -> Result<EmitterConfig, String> {
+        let num = |key: &str, default: f32| preset[key].as_f64().map_or(default, |v| v as f32);
+        if num("emitterType", 0.0) != 0.0 {
+            return Err("only gravity emitters (emitterType 0) are supported".to_string());
+        }
+
+        let mut c = EmitterConfig::new(frames);
+        c.max_particles = num("maxParticles", c.max_particles as f32).max(0.0) as usize;
+        c.duration = num("duration", c.duration);
+        c.life = num("particleLifespan", c.life);
+        c.life_var = num("particleLifespanVariance", 0.0);
+        // Cocos fills the pool over one lifespan.
+        c.rate = num("emissionRate", c.max_particles as f32 / c.life.max(FRAME_TIME));
+        c.speed = num("speed", c.speed);
+        c.speed_var = num("speedVariance", 0.0);
+        c.angle = -num("angle", -c.angle);
+        c.angle_var = num("angleVariance", c.angle_var);
+        c.gravity = (num("gravityx", 0.0), -num("gravityy", 0.0));
+        c.drag = num("drag", 0.0);
+        c.position_var = (num("sourcePositionVariancex", 0.0), num("sourcePositionVariancey", 0.0));
+
+        let rotation = num("rotationStart", 0.0);
+        c.rotation = rotation;
+        c.rotation_var = num("rotationStartVariance", 0.0);
+        c.spin = (num("rotationEnd", rotation) - rotation) / c.life.max(FRAME_TIME);
+        c.spin_var = num("rotationEndVariance", 0.0) / c.life.max(FRAME_TIME);
+
+        let start = num("startParticleSize", 16.0);
+        // Cocos uses -1 for "same as the start size".
+        let finish = match num("finishParticleSize", start) {
+            v if v < 0.0 => start,
+            v => v,
+        };
+        c.size = match preset["sizeCurve"] {
+            Value::Array(ref keys) => {
+                Curve::new(keys.iter().filter_map(|k| pair(k)).collect()).map_err(|e| format!("sizeCurve: {}", e))?
+            }
+            _ => Curve::linear(start, finish),
+        };
+        c.size_var = if start > 0.0 { num("startParticleSizeVariance", 0.0) / start } else { 0.0 };
+
+        let color = |prefix: &str, default: Tint| {
+            let part = |name: &str, v: u8| {
+                preset[&format!("{}{}", prefix, name)[..]].as_f64().map_or(v, |f| unit(f as f32))
+            };
+            Tint::rgba(part("Red", default.r),
+                       part("Green", default.g),
+                       part("Blue", default.b),
+                       part("Alpha", default.a))
+        };
+        c.color = match preset["colorGradient"] {
+            Value::Array(ref keys) => {
+                Gradient::new(keys.iter().filter_map(|k| color_key(k)).collect())
+                    .map_err(|e| format!("colorGradient: {}", e))?
+            }
+            _ => {
+                let start = color("startColor", Tint::white());
+                Gradient::linear(start, color("finishColor", Tint::rgba(start.r, start.g, start.b, 0)))
+            }
+        };
+
+        // GL_ONE as destination factor is additive, anything else alpha blending.
+        c.blend = match preset["blendFuncDestination"].as_f64() {
+            Some(v) if v == 1.0 => BlendMode::Add,
+            _ => BlendMode::Blend,
+        };
+        Ok(c)
+    }
+}
+
+fn unit(v: f32) -> u8 {
+    (v.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+fn pair(key: &Value) -> Option<(f32, f32)> {
+    match (key[0].as_f64(), key[1].as_f64()) {
+        (Some(t), Some(v)) => Some((t as f32, v as f32)),
+        _ => None,
+    }
+}
+
+fn color_key(key: &Value) -> Option<(f32, Tint)> {
+    let v: Vec<f32> = match key.as_array() {
+        Some(v) => v.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect(),
+        None => return None,
+    };
+    if v.len() < 4 {
+        return None;
+    }
+    Some((v[0], Tint::rgba(unit(v[1]), unit(v[2]), unit(v[3]), v.get(4).map_or(255, |a| unit(*a)))))
+}
+
+// plist_to_json turns a property list into the JSON value presets are read from.
+fn plist_to_json(plist: Plist) -> Value {
+    match plist {
+        Plist::Array(items) => Value::Array(items.into_iter().map(plist_to_json).collect()),
+        Plist::Dictionary(dict) => {
+            Value::Object(dict.into_iter().map(|(k, v)| (k, plist_to_json(v))).collect())
+        }
+        Plist::Boolean(b) => Value::Bool(b),
+        Plist::Real(f) => Value::from(f),
+        Plist::Integer(i) => Value::from(i),
+        Plist::String(s) => Value::String(s),
+        _ => Value::Null,
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Particle {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+    rotation: f32,
+    spin: f32,
+    size: f32,
+    age: f32,
+    life: f32,
+    frame: usize,
+}
+
+impl Particle {
+    fn dead() -> Particle {
+        Particle {
+            x: 0.0,
+            y: 0.0,
+            vx: 0.0,
+            vy: 0.0,
+            rotation: 0.0,
+            spin: 0.0,
+            size: 0.0,
+            age: 0.0,
+            life: 0.0,
+            frame: 0,
+        }
+    }
+}
+
+// Emitter is a particle effect. Particles live in the emitter's parent space,
+// so moving the emitter leaves a trail behind. The pool is allocated once:
+// live particles are kept at the front and dead ones swapped to the back.
+pub struct Emitter {
+    config: EmitterConfig,
+    particles: Vec<Particle>,
+    count: usize,
+    x: f32,
+    y: f32,
+    running: bool,
+    elapsed: f32,
+    pending: f32,
+    visible: bool,
+}
+
+impl Emitter {
+    pub fn new(config: EmitterConfig) -> Emitter {
+        Emitter {
+            particles: vec![Particle::dead(); config.max_particles],
+            config: config,
+            count: 0,
+            x: 0.0,
+            y: 0.0,
+            running: false,
+            elapsed: 0.0,
+            pending: 0.0,
+            visible: true,
+        }
+    }
+
+    pub fn get_config(&self) -> &EmitterConfig {
+        &self.config
+    }
+
+    // set_config swaps the effect, keeping the live particles that still fit.
+    pub fn set_config(&mut self, config: EmitterConfig) {
+        let max = config.max_particles;
+        let frames = config.frames.len();
+        self.config = config;
+        self.particles.resize(max, Particle::dead());
+        self.count = self.count.min(max);
+        for p in &mut self.particles[..] {
+            p.frame = if frames > 0 { p.frame % frames } else { 0 };
+        }
+    }
+
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        self.x = x as f32;
+        self.y = y as f32;
+    }
+
+    pub fn get_position(&self) -> (i32, i32) {
+        (self.x as i32, self.y as i32)
+    }
+
+    // start emits at the configured rate until the duration runs out.
+    pub fn start(&mut self) {
+        self.running = true;
+        self.elapsed = 0.0;
+        self.pending = 0.0;
+    }
+
+    // stop ends emission, the live particles play out.
+    pub fn stop(&mut self) {
+        self.running = false;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    // is_active tells whether the emitter still has anything to show.
+    pub fn is_active(&self) -> bool {
+        self.running || self.count > 0
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.count
+    }
+
+    // clear kills every particle at once.
+    pub fn clear(&mut self) {
+        self.count = 0;
+    }
+
+    // burst emits `n` particles at once, as many as the pool has room for.
+    pub fn burst(&mut self, n: usize) {
+        let mut rng = thread_rng();
+        for _ in 0..n {
+            if !self.spawn(&mut rng) {
+                break;
+            }
+        }
+    }
+
+    // burst_at moves the emitter and bursts there.
+    pub fn burst_at(&mut self, x: i32, y: i32, n: usize) {
+        self.set_position(x, y);
+        self.burst(n);
+    }
+
+    pub fn hide<'a>(&'a mut self) -> &'a mut Emitter {
+        self.visible = false;
+        self
+    }
+
+    pub fn show<'a>(&'a mut self) -> &'a mut Emitter {
+        self.visible = true;
+        self
+    }
+
+    pub fn get_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn spawn<R: Rng>(&mut self, rng: &mut R) -> bool {
+        if self.count >= self.particles.len() {
+            return false;
+        }
+        let c = &self.config;
+        let frame = if c.frames.len() > 1 {
+            rng.gen_range(0, c.frames.len())
+        } else {
+            0
+        };
+        let mut spread = |v: f32| if v > 0.0 { rng.gen_range(-v, v) } else { 0.0 };
+        let angle = (c.angle + spread(c.angle_var)).to_radians();
+        let speed = c.speed + spread(c.speed_var);
+        self.particles[self.count] = Particle {
+            x: self.x + spread(c.position_var.0),
+            y: self.y + spread(c.position_var.1),
+            vx: angle.cos() * speed,
+            vy: angle.sin() * speed,
+            rotation: c.rotation + spread(c.rotation_var),
+            spin: c.spin + spread(c.spin_var),
+            size: 1.0 + spread(c.size_var),
+            age: 0.0,
+            life: (c.life + spread(c.life_var)).max(FRAME_TIME),
+            frame: frame,
+        };
+        self.count += 1;
+        true
+    }
+}
+
+impl Displayable for Emitter {
+    fn update(&mut self) {
+        let dt = FRAME_TIME;
+        if self.running {
+            let mut rng = thread_rng();
+            self.pending += self.config.rate * dt;
+            while self.pending >= 1.0 {
+                self.pending -= 1.0;
+                self.spawn(&mut rng);
+            }
+            self.elapsed += dt;
+            if self.config.duration >= 0.0 && self.elapsed >= self.config.duration {
+                self.running = false;
+            }
+        }
+
+        let (gx, gy) = self.config.gravity;
+        let keep = (1.0 - self.config.drag * dt).max(0.0);
+        let mut i = 0;
+        while i < self.count {
+            let dead = {
+                let p = &mut self.particles[i];
+                p.age += dt;
+                p.vx = (p.vx + gx * dt) * keep;
+                p.vy = (p.vy + gy * dt) * keep;
+                p.x += p.vx * dt;
+                p.y += p.vy * dt;
+                p.rotation += p.spin * dt;
+                p.age >= p.life
+            };
+            if dead {
+                self.count -= 1;
+                self.particles.swap(i, self.count);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        if !self.visible || self.config.frames.is_empty() {
+            return;
+        }
+        for p in &self.particles[..self.count] {
+            let t = p.age / p.life;
+            let tex = self.config.frames[p.frame].borrow();
+            let (w, h) = tex.get_size();
+            let scale = self.config.size.eval(t) * p.size / w.max(h).max(1) as f32;
+
+            let mut local = Transform::identity();
+            local.x = p.x;
+            local.y = p.y;
+            local.rotation = p.rotation;
+            local.scale_x = scale;
+            local.scale_y = scale;
+            local.anchor_x = 0.5;
+            local.anchor_y = 0.5;
+            tex.paint_blended(renderer,
+                              &parent.then(&local),
+                              &self.config.color.eval(t),
+                              self.config.blend);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PRESET: &'static str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>angle</key><real>90.0</real>
+	<key>blendFuncDestination</key><integer>1</integer>
+	<key>finishParticleSize</key><real>-1.0</real>
+	<key>gravityy</key><real>-200.0</real>
+	<key>maxParticles</key><integer>40</integer>
+	<key>particleLifespan</key><real>2.0</real>
+	<key>rotationStart</key><real>30.0</real>
+	<key>rotationEnd</key><real>90.0</real>
+	<key>rotationEndVariance</key><real>20.0</real>
+	<key>startColorRed</key><real>1.0</real>
+	<key>startColorGreen</key><real>0.5</real>
+	<key>startParticleSize</key><real>8.0</real>
+	<key>textureFileName</key><string>star.png</string>
+</dict>
+</plist>"#;
+
+    fn preset(text: &str) -> Value {
+        plist_to_json(Plist::from_reader(Cursor::new(text.as_bytes())).unwrap())
+    }
+
+    #[test]
+    fn test_plist_to_json() {
+        let v = preset(PRESET);
+        assert_eq!(v["maxParticles"].as_i64(), Some(40));
+        assert_eq!(v["angle"].as_f64(), Some(90.0));
+        assert_eq!(v["textureFileName"].as_str(), Some("star.png"));
+    }
+
+    #[test]
+    fn test_from_preset() {
+        let c = EmitterConfig::from_preset(&preset(PRESET), Vec::new()).unwrap();
+        // Without an emissionRate the pool fills over one lifespan.
+        assert_eq!(c.rate, 20.0);
+        assert_eq!(c.rotation, 30.0);
+        assert_eq!(c.spin, 30.0);
+        assert_eq!(c.spin_var, 10.0);
+        // Cocos points up, we point down.
+        assert_eq!(c.angle, -90.0);
+        assert_eq!(c.gravity, (0.0, 200.0));
+        assert_eq!(c.size.eval(1.0), 8.0);
+        assert_eq!(c.color.eval(0.0), Tint::rgba(255, 128, 255, 255));
+        assert_eq!(c.blend, BlendMode::Add);
+    }
+
+    #[test]
+    fn test_from_preset_rejects_radial() {
+        let v = preset(&PRESET.replace("<key>angle</key>", "<key>emitterType</key><real>1</real><key>angle</key>"));
+        assert!(EmitterConfig::from_preset(&v, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_shipped_presets() {
+        let sparkles = preset(include_str!("../res/particles/sparkles.plist"));
+        let c = EmitterConfig::from_preset(&sparkles, Vec::new()).unwrap();
+        assert_eq!(c.max_particles, 48);
+        assert_eq!(c.blend, BlendMode::Add);
+        for json in &[include_str!("../res/particles/feathers.json"), include_str!("../res/particles/dust.json")] {
+            let v: Value = serde_json::from_str(json).unwrap();
+            assert!(EmitterConfig::from_preset(&v, Vec::new()).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_curve_keys() {
+        let curve = Curve::new(vec![(1.0, 8.0), (0.0, 4.0)]).unwrap();
+        assert_eq!(curve.eval(0.5), 6.0);
+        assert!(Curve::new(vec![(0.0, 1.0), (::std::f32::NAN, 2.0)]).is_err());
+        assert!(Gradient::new(vec![(::std::f32::NAN, Tint::white())]).is_err());
+    }
+
+    // config is an effect without textures, nothing random but the angle.
+    fn config(max: usize, rate: f32, life: f32) -> EmitterConfig {
+        let mut c = EmitterConfig::new(Vec::new());
+        c.max_particles = max;
+        c.rate = rate;
+        c.life = life;
+        c
+    }
+
+    fn step(emitter: &mut Emitter, frames: usize) {
+        for _ in 0..frames {
+            emitter.update();
+        }
+    }
+
+    #[test]
+    fn test_burst_fills_pool_at_most() {
+        let mut emitter = Emitter::new(config(8, 0.0, 1.0));
+        emitter.burst_at(10, 20, 5);
+        assert_eq!(emitter.get_count(), 5);
+        assert_eq!(emitter.get_position(), (10, 20));
+        emitter.burst(10);
+        assert_eq!(emitter.get_count(), 8);
+        emitter.burst(1);
+        assert_eq!(emitter.get_count(), 8);
+        emitter.clear();
+        assert_eq!(emitter.get_count(), 0);
+    }
+
+    #[test]
+    fn test_particles_expire() {
+        let mut emitter = Emitter::new(config(8, 0.0, 0.5));
+        emitter.burst(4);
+        step(&mut emitter, 29);
+        assert_eq!(emitter.get_count(), 4);
+        assert!(emitter.is_active());
+        step(&mut emitter, 2);
+        assert_eq!(emitter.get_count(), 0);
+        assert!(!emitter.is_active());
+    }
+
+    #[test]
+    fn test_dead_particles_reused() {
+        let mut emitter = Emitter::new(config(8, 0.0, 0.5));
+        emitter.burst(4);
+        step(&mut emitter, 15);
+        emitter.burst(10);
+        assert_eq!(emitter.get_count(), 8);
+        // The first four die, making room for four more.
+        step(&mut emitter, 16);
+        assert_eq!(emitter.get_count(), 4);
+        emitter.burst(10);
+        assert_eq!(emitter.get_count(), 8);
+        step(&mut emitter, 31);
+        assert_eq!(emitter.get_count(), 0);
+    }
+
+    #[test]
+    fn test_rate() {
+        // Half a particle a frame.
+        let mut emitter = Emitter::new(config(100, 30.0, 10.0));
+        step(&mut emitter, 10);
+        assert_eq!(emitter.get_count(), 0);
+        emitter.start();
+        step(&mut emitter, 1);
+        assert_eq!(emitter.get_count(), 0);
+        step(&mut emitter, 2);
+        assert_eq!(emitter.get_count(), 1);
+        step(&mut emitter, 58);
+        assert_eq!(emitter.get_count(), 30);
+        emitter.stop();
+        step(&mut emitter, 10);
+        assert_eq!(emitter.get_count(), 30);
+    }
+
+    #[test]
+    fn test_rate_limited_by_pool_and_duration() {
+        let mut c = config(10, 60.0, 10.0);
+        c.duration = 0.5;
+        let mut emitter = Emitter::new(c);
+        emitter.start();
+        step(&mut emitter, 20);
+        assert_eq!(emitter.get_count(), 10);
+        assert!(emitter.is_running());
+        step(&mut emitter, 11);
+        assert!(!emitter.is_running());
+        assert!(emitter.is_active());
+    }
+}
@@ -0,0 +1,324 @@
+extern crate sdl2;
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::render::Renderer;
+
+use display::Displayable;
+use sprite::Sprite;
+use atlas::{TexElement, Tint};
+use transform::Transform;
+use ui::{Ui, Button, Toggle};
+use text::{TextSystem, FontKey};
+use layout::{Layout, Stack, Anchor};
+use locale::Strings;
+
+const FONT_SIZE: u16 = 32;
+// How dark the game gets behind the menu.
+const DIM_ALPHA: u8 = 160;
+// Gap between the menu rows.
+const ROW_SPACING: u32 = 12;
+// The pause button sits in the top left corner, this far from the edges.
+const BUTTON_MARGIN: i32 = 16;
+
+// Settings are shared by every menu that can change them.
+pub struct Settings {
+    // Whether the best run's ghost races along.
+    pub ghost: bool,
+}
+
+impl Settings {
+    pub fn new() -> Settings {
+        Settings { ghost: true }
+    }
+}
+
+// PauseChoice is what the player asked of the pause menu.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PauseChoice {
+    Pause,
+    Resume,
+    Restart,
+    Quit,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Entry {
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+    Language,
+    Back,
+}
+
+// render_label renders the string of a key in the current language's font.
+// The language row names the current language.
+fn render_label(renderer: &Renderer,
+                text: &mut TextSystem,
+                strings: &Strings,
+                key: &str)
+                -> Result<Rc<RefCell<TexElement>>, String> {
+    let font = FontKey::new(strings.get_font(), FONT_SIZE);
+    let s = strings.format(key, &[("language", &strings.get_name())]);
+    text.render(renderer, &font, &s, Tint::white(), 0)
+}
+
+// PauseMenu is the pause button in the corner of the screen and, while the
+// game is paused, the menu dimming the game behind it: resume, restart,
+// settings and quit to the title. Its owner hands it the events and takes
+// the player's choice, as with the game over screen.
+pub struct PauseMenu {
+    visible: bool,
+    dim: Sprite,
+    pause: Rc<RefCell<Button>>,
+    resume: Rc<RefCell<Button>>,
+    // The main page first, then the settings.
+    pages: Vec<Ui>,
+    page: usize,
+    ghost: Rc<RefCell<Toggle>>,
+    // The text rows by the key of their string, rendered again when the
+    // language changes.
+    labels: Vec<(Rc<RefCell<Button>>, &'static str)>,
+    strings: Rc<RefCell<Strings>>,
+    layout: Layout,
+    // Set by the rows when clicked.
+    entry: Rc<Cell<Option<Entry>>>,
+    // Opened straight on the settings, which close it again.
+    settings_only: bool,
+    settings: Rc<RefCell<Settings>>,
+    choice: Rc<Cell<Option<PauseChoice>>>,
+}
+
+impl PauseMenu {
+    pub fn new(renderer: &Renderer,
+               text: &mut TextSystem,
+               strings: Rc<RefCell<Strings>>,
+               atlas: &HashMap<String, Rc<RefCell<TexElement>>>,
+               settings: Rc<RefCell<Settings>>,
+               w: u32,
+               h: u32)
+               -> Result<PauseMenu, String> {
+        let mut label = |key: &str| render_label(renderer, text, &strings.borrow(), key);
+
+        let entry = Rc::new(Cell::new(None));
+        let mut labels = Vec::new();
+        let mut row = |page: &mut Ui, rows: &mut Stack, e: Entry, key: &'static str, tex| {
+            let mut button = Button::new(tex);
+            let entry = entry.clone();
+            button.on_click(move || entry.set(Some(e)));
+            let button = page.add(button);
+            rows.add(button.clone());
+            labels.push((button, key));
+        };
+        let mut main = Ui::new();
+        let mut main_rows = Stack::vertical(ROW_SPACING);
+        for &(e, key) in &[(Entry::Resume, "pause.resume"),
+                           (Entry::Restart, "pause.restart"),
+                           (Entry::Settings, "pause.settings"),
+                           (Entry::Quit, "pause.quit")] {
+            row(&mut main, &mut main_rows, e, key, label(key)?);
+        }
+
+        let mut page = Ui::new();
+        let mut page_rows = Stack::vertical(ROW_SPACING);
+        let mut toggle = Toggle::new(label("settings.ghost.on")?,
+                                     label("settings.ghost.off")?,
+                                     settings.borrow().ghost);
+        let shared = settings.clone();
+        toggle.on_change(move |on| shared.borrow_mut().ghost = on);
+        let ghost = page.add(toggle);
+        page_rows.add(ghost.clone());
+        for &(e, key) in &[(Entry::Language, "settings.language"), (Entry::Back, "settings.back")] {
+            row(&mut page, &mut page_rows, e, key, label(key)?);
+        }
+
+        let choice = Rc::new(Cell::new(None));
+        let button = |name: &str, pick: PauseChoice| {
+            let mut button = Button::new(atlas[name].clone());
+            let choice = choice.clone();
+            button.on_click(move || choice.set(Some(pick)));
+            Rc::new(RefCell::new(button))
+        };
+        let pause = button("button_pause", PauseChoice::Pause);
+        let resume = button("button_resume", PauseChoice::Resume);
+        resume.borrow_mut().hide();
+
+        let mut layout = Layout::new(w, h);
+        let margin = (BUTTON_MARGIN, BUTTON_MARGIN);
+        layout.anchor(pause.clone(), Anchor::TopLeft, margin);
+        layout.anchor(resume.clone(), Anchor::TopLeft, margin);
+        layout.anchor_stack(main_rows, Anchor::Center, (0, 0));
+        layout.anchor_stack(page_rows, Anchor::Center, (0, 0));
+
+        let mut menu = PauseMenu {
+            visible: false,
+            dim: Sprite::new_from_tex(atlas["black"].clone()),
+            pause: pause,
+            resume: resume,
+            pages: vec![main, page],
+            page: 0,
+            ghost: ghost,
+            labels: labels,
+            strings: strings,
+            layout: layout,
+            entry: entry,
+            settings_only: false,
+            settings: settings,
+            choice: choice,
+        };
+        menu.dim.set_alpha(DIM_ALPHA);
+        menu.fit_dim(w, h);
+        Ok(menu)
+    }
+
+    // relabel renders the text rows again in the current language, and lays
+    // them out again as their sizes changed.
+    pub fn relabel(&mut self, renderer: &Renderer, text: &mut TextSystem) -> Result<(), String> {
+        let strings = self.strings.borrow();
+        for &(ref button, key) in &self.labels {
+            button.borrow_mut().set_image(render_label(renderer, text, &strings, key)?);
+        }
+        self.ghost.borrow_mut().set_images(render_label(renderer, text, &strings, "settings.ghost.on")?,
+                                           render_label(renderer, text, &strings, "settings.ghost.off")?);
+        self.layout.apply();
+        Ok(())
+    }
+
+    // fit_dim stretches the black tile over the whole screen.
+    fn fit_dim(&mut self, w: u32, h: u32) {
+        let (tw, th) = self.dim.get_size();
+        self.dim.set_scale(w as f32 / tw as f32, h as f32 / th as f32);
+    }
+
+    // show opens the menu on its main page.
+    pub fn show(&mut self) {
+        self.visible = true;
+        self.settings_only = false;
+        self.choice.set(None);
+        self.pause.borrow_mut().hide();
+        self.resume.borrow_mut().show();
+        self.open(0);
+    }
+
+    // show_settings opens the menu on the settings, for the title screen.
+    // Going back from them resumes.
+    pub fn show_settings(&mut self) {
+        self.show();
+        self.settings_only = true;
+        self.open(1);
+    }
+
+    pub fn hide(&mut self) {
+        self.visible = false;
+        self.resume.borrow_mut().hide();
+        self.pause.borrow_mut().show();
+    }
+
+    pub fn get_visible(&self) -> bool {
+        self.visible
+    }
+
+    // take_choice hands out the player's choice once, if any was made.
+    pub fn take_choice(&mut self) -> Option<PauseChoice> {
+        self.choice.take()
+    }
+
+    fn open(&mut self, page: usize) {
+        self.page = page;
+        // Another menu sharing the settings may have changed them.
+        self.ghost.borrow_mut().set_value(self.settings.borrow().ghost);
+        self.pages[page].focus_first();
+    }
+
+    // activate acts on the row the player pressed, if any.
+    fn activate(&mut self) {
+        match self.entry.take() {
+            Some(Entry::Resume) => self.choice.set(Some(PauseChoice::Resume)),
+            Some(Entry::Restart) => self.choice.set(Some(PauseChoice::Restart)),
+            Some(Entry::Quit) => self.choice.set(Some(PauseChoice::Quit)),
+            Some(Entry::Settings) => self.open(1),
+            // The owner notices the new language and relabels every menu.
+            Some(Entry::Language) => self.strings.borrow_mut().next_language(),
+            Some(Entry::Back) => self.back(),
+            None => {}
+        }
+    }
+
+    fn back(&mut self) {
+        if self.settings_only {
+            self.choice.set(Some(PauseChoice::Resume));
+        } else {
+            self.open(0);
+        }
+    }
+}
+
+impl Displayable for PauseMenu {
+    fn update(&mut self) {
+        self.pages[self.page].update();
+    }
+
+    fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        if !self.visible {
+            self.pause.borrow().paint_with(renderer, parent);
+            return;
+        }
+        self.dim.paint_with(renderer, parent);
+        self.resume.borrow().paint_with(renderer, parent);
+        self.pages[self.page].paint_with(renderer, parent);
+    }
+
+    fn get_parallax(&self) -> f32 {
+        0.0
+    }
+
+    fn on_resize(&mut self, w: u32, h: u32) {
+        self.fit_dim(w, h);
+        self.layout.resize(w, h);
+    }
+
+    fn on_key_down(&mut self, event: &Event) {
+        if !self.visible {
+            return;
+        }
+        match event {
+            &Event::KeyDown { keycode: Some(Keycode::Backspace), .. } if self.page > 0 => {
+                self.back();
+            }
+            _ => {
+                self.pages[self.page].on_key_down(event);
+                self.activate();
+            }
+        }
+    }
+
+    fn on_mouse_down(&mut self, event: &Event) {
+        self.pause.borrow_mut().on_mouse_down(event);
+        self.resume.borrow_mut().on_mouse_down(event);
+        if self.visible {
+            self.pages[self.page].on_mouse_down(event);
+        }
+    }
+
+    fn on_mouse_up(&mut self, event: &Event) {
+        self.pause.borrow_mut().on_mouse_up(event);
+        self.resume.borrow_mut().on_mouse_up(event);
+        self.pages[self.page].on_mouse_up(event);
+        self.activate();
+    }
+
+    fn on_mouse_move(&mut self, event: &Event) {
+        if self.visible {
+            self.pages[self.page].on_mouse_move(event);
+        }
+    }
+}
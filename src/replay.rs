@@ -0,0 +1,157 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+// Replay is a recorded run: the seed the pipes were generated from, how many
+// frames the bird survived and the frames on which the player flapped.
+// Physics is stepped once per frame so replaying the flaps on the same seed
+// reproduces the run exactly.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    seed: usize,
+    frames: u32,
+    flaps: Vec<u32>,
+}
+
+impl Replay {
+    pub fn new(seed: usize) -> Replay {
+        Replay {
+            seed: seed,
+            frames: 0,
+            flaps: Vec::new(),
+        }
+    }
+
+    // load reads a replay saved by `save`.
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let mut text = String::new();
+        File::open(Path::new(path))
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| e.to_string())?;
+        Replay::parse(&text)
+    }
+
+    // parse reads a replay from text. The first line holds
+    // "<seed> <frames>", the second the flap frames.
+    pub fn parse(text: &str) -> Result<Replay, String> {
+        let mut lines = text.lines();
+        let header = lines.next().ok_or_else(|| "replay file is empty".to_string())?;
+        let mut items = header.split_whitespace();
+        let seed = items.next()
+                        .ok_or_else(|| "replay seed not found".to_string())?
+                        .parse::<usize>()
+                        .map_err(|e| e.to_string())?;
+        let frames = items.next()
+                          .ok_or_else(|| "replay length not found".to_string())?
+                          .parse::<u32>()
+                          .map_err(|e| e.to_string())?;
+
+        let mut flaps = Vec::new();
+        if let Some(line) = lines.next() {
+            for item in line.split_whitespace() {
+                flaps.push(item.parse::<u32>().map_err(|e| e.to_string())?);
+            }
+        }
+
+        Ok(Replay {
+            seed: seed,
+            frames: frames,
+            flaps: flaps,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut file = File::create(Path::new(path)).map_err(|e| e.to_string())?;
+        file.write_all(self.to_text().as_bytes()).map_err(|e| e.to_string())
+    }
+
+    // to_text is the replay as `parse` reads it.
+    pub fn to_text(&self) -> String {
+        let flaps: Vec<String> = self.flaps.iter().map(|f| f.to_string()).collect();
+        format!("{} {}\n{}\n", self.seed, self.frames, flaps.join(" "))
+    }
+
+    pub fn record_flap(&mut self, frame: u32) {
+        self.flaps.push(frame);
+    }
+
+    pub fn finish(&mut self, frames: u32) {
+        self.frames = frames;
+    }
+
+    pub fn get_seed(&self) -> usize {
+        self.seed
+    }
+
+    pub fn get_frames(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn get_flaps(&self) -> &[u32] {
+        &self.flaps
+    }
+
+    // is_better_than tells whether this run survived longer than `other`.
+    pub fn is_better_than(&self, other: &Replay) -> bool {
+        self.frames > other.frames
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+
+    fn run(seed: usize, frames: u32, flaps: &[u32]) -> Replay {
+        let mut replay = Replay::new(seed);
+        for &f in flaps {
+            replay.record_flap(f);
+        }
+        replay.finish(frames);
+        replay
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let replay = run(42, 900, &[3, 40, 41, 120]);
+        let back = Replay::parse(&replay.to_text()).unwrap();
+        assert_eq!(back.get_seed(), 42);
+        assert_eq!(back.get_frames(), 900);
+        assert_eq!(back.get_flaps(), &[3, 40, 41, 120]);
+    }
+
+    #[test]
+    fn test_no_flaps() {
+        let back = Replay::parse(&run(7, 60, &[]).to_text()).unwrap();
+        assert_eq!(back.get_frames(), 60);
+        assert!(back.get_flaps().is_empty());
+        assert_eq!(Replay::parse("7 60").unwrap().get_frames(), 60);
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let path = env::temp_dir().join("game_test.replay");
+        let path = path.to_str().unwrap();
+        run(1, 2, &[1]).save(path).unwrap();
+        assert_eq!(Replay::load(path).unwrap().get_flaps(), &[1]);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(Replay::parse("").is_err());
+        assert!(Replay::parse("42").is_err());
+        assert!(Replay::parse("42 x").is_err());
+        assert!(Replay::parse("-1 60").is_err());
+        assert!(Replay::parse("42 60\n1 two 3").is_err());
+        assert!(Replay::load("no/such/best.replay").is_err());
+    }
+
+    #[test]
+    fn test_is_better_than() {
+        assert!(run(1, 100, &[]).is_better_than(&run(2, 99, &[])));
+        assert!(!run(1, 100, &[]).is_better_than(&run(2, 100, &[])));
+    }
+}
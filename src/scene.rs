@@ -1,7 +1,6 @@
 extern crate sdl2;
 
 use std::ops::{Deref, DerefMut};
-use std::collections::HashMap;
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::path::Path;
@@ -13,13 +12,52 @@ use sdl2::image::LoadTexture;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 
-use display::Displayable;
+use display::{Displayable, FRAME_TIME};
+use actions::{Action, ActionId, ActionManager, Target};
+use script::{Script, ScriptId, ScriptRunner};
+use scheduler::JobId;
 use sprite::Sprite;
+use transform::Transform;
+use camera::Camera;
+
+// A child of the scene, kept sorted by (z, order) so painting is back to front
+// and children sharing a z keep the order they were added in.
+struct SceneChild {
+    name: String,
+    tag: String,
+    z: i32,
+    order: u64,
+    node: Rc<RefCell<Displayable>>,
+}
+
+enum SceneCommand {
+    Add(String, i32, Rc<RefCell<Displayable>>),
+    Remove(String),
+}
+
+// SceneQueue lets children add or remove scene children while the scene is
+// iterating over them; the commands are applied around the next update.
+#[derive(Clone)]
+pub struct SceneQueue {
+    commands: Rc<RefCell<Vec<SceneCommand>>>,
+}
+
+impl SceneQueue {
+    pub fn add_child(&self, name: &str, z: i32, child: Rc<RefCell<Displayable>>) {
+        self.commands.borrow_mut().push(SceneCommand::Add(name.to_string(), z, child));
+    }
+
+    pub fn remove_child(&self, name: &str) {
+        self.commands.borrow_mut().push(SceneCommand::Remove(name.to_string()));
+    }
+}
 
 pub struct Scene {
     // Internal state.
     paused: bool,
     game_over: bool,
+    transform: Transform,
+    camera: Option<Rc<RefCell<Camera>>>,
 
     // Objects.
     // https://www.reddit.com/r/rust/comments/4ij34q/how_to_use_rcrefcellt_properly/
@@ -29,11 +67,13 @@ pub struct Scene {
     // pipes: Rc<RefCell<Pipes>>,
     //
     // Generic.
-    children_index: Vec<String>,
-    children: HashMap<String, Rc<RefCell<Displayable>>>, // background: Node,
+    next_order: u64,
+    children: Vec<SceneChild>, // background: Node,
+    queue: SceneQueue,
+    actions: ActionManager,
+    scripts: ScriptRunner,
 }
 
-// TODO: refactor this code since it's all copy pasta...but scrolling now works!
 impl Scene {
     pub fn new(renderer: &Renderer) -> Scene {
         // let flappy = Rc::new(RefCell::new(Bird::new(renderer)));
@@ -43,33 +83,122 @@ impl Scene {
         // children.push(flappy.clone());
         // children.push(pipes.clone());
 
+        Scene::empty()
+    }
+
+    fn empty() -> Scene {
         Scene {
             // flappy: flappy.clone(),
             // pipes: pipes.clone(),
             paused: false,
             game_over: false,
-            children_index: Vec::new(),
-            children: HashMap::new(), // background: Node::new(renderer, &[path]),
+            transform: Transform::identity(),
+            camera: None,
+            next_order: 0,
+            children: Vec::new(), // background: Node::new(renderer, &[path]),
+            queue: SceneQueue { commands: Rc::new(RefCell::new(Vec::new())) },
+            actions: ActionManager::new(),
+            scripts: ScriptRunner::new(),
         }
     }
 
+    // add_child adds a child at z 0, replacing any child with the same name.
     pub fn add_child(&mut self, name: &str, child: Rc<RefCell<Displayable>>) {
-        self.children_index.push(name.to_string());
-        self.children.insert(name.to_string(), child);
+        self.add_child_z(name, 0, child);
+    }
+
+    // add_child_z adds a child painted above every child with a lower z.
+    pub fn add_child_z(&mut self, name: &str, z: i32, child: Rc<RefCell<Displayable>>) {
+        self.remove_child(name);
+        let order = self.next_order;
+        self.next_order += 1;
+        self.children.push(SceneChild {
+            name: name.to_string(),
+            tag: String::new(),
+            z: z,
+            order: order,
+            node: child,
+        });
+        self.sort_children();
+    }
+
+    pub fn remove_child(&mut self, name: &str) -> Option<Rc<RefCell<Displayable>>> {
+        match self.children.iter().position(|c| c.name == name) {
+            Some(idx) => Some(self.children.remove(idx).node),
+            None => None,
+        }
+    }
+
+    // reorder moves a child to `z`, on top of the children already there.
+    pub fn reorder(&mut self, name: &str, z: i32) -> Result<(), &str> {
+        let order = self.next_order;
+        match self.children.iter_mut().find(|c| c.name == name) {
+            Some(child) => {
+                child.z = z;
+                child.order = order;
+            }
+            None => return Err("the element not found"),
+        }
+        self.next_order += 1;
+        self.sort_children();
+        Ok(())
+    }
+
+    pub fn get_z(&self, name: &str) -> Result<i32, &str> {
+        match self.children.iter().find(|c| c.name == name) {
+            Some(child) => Ok(child.z),
+            None => Err("the element not found"),
+        }
     }
 
     pub fn get_child(&self, name: &str) -> Result<&Rc<RefCell<Displayable>>, &str> {
-        // if (idx as usize) < self.children.len() {
-        //     Ok(&self.children[idx as usize])
-        // } else {
-        //     Err("array out of bounds")
-        // }
-        match self.children.get(&name.to_string()) {
-            Some(dp) => Ok(dp),
+        match self.children.iter().find(|c| c.name == name) {
+            Some(child) => Ok(&child.node),
+            None => Err("the element not found"),
+        }
+    }
+
+    pub fn set_tag(&mut self, name: &str, tag: &str) -> Result<(), &str> {
+        match self.children.iter_mut().find(|c| c.name == name) {
+            Some(child) => {
+                child.tag = tag.to_string();
+                Ok(())
+            }
             None => Err("the element not found"),
         }
     }
 
+    // get_children_by_tag returns the tagged children in paint order.
+    pub fn get_children_by_tag(&self, tag: &str) -> Vec<Rc<RefCell<Displayable>>> {
+        self.children
+            .iter()
+            .filter(|c| c.tag == tag)
+            .map(|c| c.node.clone())
+            .collect()
+    }
+
+    // get_queue hands out a queue children can keep to add or remove
+    // scene children from inside update or event handlers.
+    pub fn get_queue(&self) -> SceneQueue {
+        self.queue.clone()
+    }
+
+    fn sort_children(&mut self) {
+        self.children.sort_by(|a, b| (a.z, a.order).cmp(&(b.z, b.order)));
+    }
+
+    fn flush_queue(&mut self) {
+        let commands: Vec<SceneCommand> = self.queue.commands.borrow_mut().drain(..).collect();
+        for command in commands {
+            match command {
+                SceneCommand::Add(name, z, child) => self.add_child_z(&name, z, child),
+                SceneCommand::Remove(name) => {
+                    self.remove_child(&name);
+                }
+            }
+        }
+    }
+
     // pub fn paint_child(&self, renderer: &mut Renderer) {
     //     for child in &self.children {
     //         child.borrow_mut().paint(renderer);
@@ -83,37 +212,138 @@ impl Scene {
 
 
         // Finally reset the state of the scene.
+        self.actions.stop_all();
+        self.scripts.stop_all();
         self.game_over = false;
     }
 
+    // run_action animates `target`, stepping with the scene and freezing
+    // while it is paused.
+    pub fn run_action(&mut self, target: Target, action: Box<Action>) -> ActionId {
+        self.actions.run(target, action)
+    }
+
+    pub fn get_actions(&mut self) -> &mut ActionManager {
+        &mut self.actions
+    }
+
+    // run_script runs `script` with the scene, like its actions.
+    pub fn run_script(&mut self, script: Script) -> ScriptId {
+        self.scripts.run(script)
+    }
+
+    // signal raises an event the scene's scripts can wait on.
+    pub fn signal(&mut self, name: &str) {
+        self.scripts.signal(name);
+    }
+
+    pub fn get_scripts(&mut self) -> &mut ScriptRunner {
+        &mut self.scripts
+    }
+
+    // pause freezes the children, actions and scripts until resumed, e.g.
+    // under a pause menu.
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.actions.pause();
+        self.scripts.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.actions.resume();
+        self.scripts.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
     pub fn game_over(&self) -> bool {
         self.game_over
     }
-}
 
-impl Displayable for Scene {
-    fn update(&mut self) {
+    pub fn set_game_over(&mut self) {
+        self.game_over = true;
+    }
+
+    // Moving the scene moves every child with it.
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        self.transform.x = x as f32;
+        self.transform.y = y as f32;
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    pub fn get_transform(&self) -> Transform {
+        self.transform
+    }
+
+    // set_camera paints the children through the camera, each following it
+    // by its own parallax factor.
+    pub fn set_camera(&mut self, camera: Option<Rc<RefCell<Camera>>>) {
+        self.camera = camera;
+    }
+
+    pub fn get_camera(&self) -> Option<Rc<RefCell<Camera>>> {
+        self.camera.clone()
+    }
+
+    // update_with updates the scene stepping actions and scripts by `dt`,
+    // with `jobs` the scheduler's pending jobs, so a game loop driving a
+    // scheduler keeps both on its clock.
+    pub fn update_with(&mut self, dt: f32, jobs: &[JobId]) {
+        self.flush_queue();
         if self.paused {
             return;
         }
         // self.background.update();
         // TODO: allow cancel propagating events based on logic in parent.
 
-
-        for (_, child) in &self.children {
-            child.borrow_mut().update();
+        // Children are updated in paint order.
+        for child in &self.children {
+            child.node.borrow_mut().update();
         }
+        self.actions.update(dt);
+        self.scripts.update(dt, &mut self.actions, jobs);
+        self.flush_queue();
+    }
+}
+
+impl Displayable for Scene {
+    fn update(&mut self) {
+        self.update_with(FRAME_TIME, &[]);
     }
     fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
         // self.background.paint(renderer);
+        let world = parent.then(&self.transform);
 
-        for idx in &self.children_index {
-            match self.children.get(&idx.to_string()) {
-                Some(child) => child.borrow_mut().paint(renderer),
-                None => {}
+        match self.camera {
+            Some(ref camera) => {
+                let camera = camera.borrow();
+                for child in &self.children {
+                    let node = child.node.borrow();
+                    let view = parent.then(&camera.parallax_transform(node.get_parallax()));
+                    node.paint_with(renderer, &view.then(&self.transform));
+                }
+            }
+            None => {
+                for child in &self.children {
+                    child.node.borrow().paint_with(renderer, &world);
+                }
             }
         }
     }
+    fn on_resize(&mut self, w: u32, h: u32) {
+        for child in &self.children {
+            child.node.borrow_mut().on_resize(w, h);
+        }
+    }
     fn on_key_down(&mut self, event: &Event) {
         match event {
             &Event::KeyDown { keycode: Some(Keycode::P), .. } => {
@@ -127,9 +357,11 @@ impl Displayable for Scene {
         }
 
         // TODO: allow cancel propagating events based on logic in parent.
-        for (_, child) in &self.children {
-            child.borrow_mut().on_key_down(event);
+        // Topmost children see the event first.
+        for child in self.children.iter().rev() {
+            child.node.borrow_mut().on_key_down(event);
         }
+        self.flush_queue();
     }
 }
 
@@ -177,3 +409,122 @@ impl Displayable for SceneManager {
         self.on_key_down(event);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Block notes its id in a shared log on every update, and can add or
+    // remove scene children through the queue while it is updated.
+    struct Block {
+        id: u32,
+        log: Rc<RefCell<Vec<u32>>>,
+        queue: Option<SceneQueue>,
+    }
+
+    impl Displayable for Block {
+        fn update(&mut self) {
+            self.log.borrow_mut().push(self.id);
+            if let Some(queue) = self.queue.take() {
+                queue.remove_child("a");
+                queue.add_child("d", -1, block(4, &self.log));
+            }
+        }
+        fn paint(&self, _renderer: &mut Renderer) {}
+    }
+
+    fn block(id: u32, log: &Rc<RefCell<Vec<u32>>>) -> Rc<RefCell<Block>> {
+        Rc::new(RefCell::new(Block { id: id, log: log.clone(), queue: None }))
+    }
+
+    // order is the ids of the children in update, and so paint, order.
+    fn order(scene: &mut Scene, log: &Rc<RefCell<Vec<u32>>>) -> Vec<u32> {
+        log.borrow_mut().clear();
+        scene.update();
+        let ids = log.borrow().clone();
+        ids
+    }
+
+    #[test]
+    fn test_z_order() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut scene = Scene::empty();
+        scene.add_child_z("a", 10, block(1, &log));
+        scene.add_child_z("b", -5, block(2, &log));
+        scene.add_child("c", block(3, &log));
+        assert_eq!(order(&mut scene, &log), vec![2, 3, 1]);
+        assert_eq!(scene.get_z("a"), Ok(10));
+        assert!(scene.get_z("z").is_err());
+    }
+
+    #[test]
+    fn test_equal_z_keeps_insertion_order() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut scene = Scene::empty();
+        for id in 1..6 {
+            scene.add_child_z(&id.to_string(), 0, block(id, &log));
+        }
+        assert_eq!(order(&mut scene, &log), vec![1, 2, 3, 4, 5]);
+        // Adding a name again replaces the child and puts it last.
+        scene.add_child_z("2", 0, block(6, &log));
+        assert_eq!(order(&mut scene, &log), vec![1, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_reorder() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut scene = Scene::empty();
+        scene.add_child_z("a", 0, block(1, &log));
+        scene.add_child_z("b", 0, block(2, &log));
+        scene.add_child_z("c", 1, block(3, &log));
+        scene.reorder("a", 0).unwrap();
+        assert_eq!(order(&mut scene, &log), vec![2, 1, 3]);
+        scene.reorder("c", -1).unwrap();
+        assert_eq!(order(&mut scene, &log), vec![3, 2, 1]);
+        assert!(scene.reorder("z", 0).is_err());
+    }
+
+    #[test]
+    fn test_tags() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut scene = Scene::empty();
+        scene.add_child_z("a", 2, block(1, &log));
+        scene.add_child_z("b", 0, block(2, &log));
+        scene.add_child_z("c", 1, block(3, &log));
+        scene.set_tag("a", "pipe").unwrap();
+        scene.set_tag("b", "pipe").unwrap();
+        assert!(scene.set_tag("z", "pipe").is_err());
+        let pipes = scene.get_children_by_tag("pipe");
+        assert_eq!(pipes.len(), 2);
+        assert!(Rc::ptr_eq(&pipes[0], scene.get_child("b").unwrap()));
+        assert!(Rc::ptr_eq(&pipes[1], scene.get_child("a").unwrap()));
+        assert!(scene.get_children_by_tag("bird").is_empty());
+    }
+
+    #[test]
+    fn test_queue_during_update() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut scene = Scene::empty();
+        scene.add_child("a", block(1, &log));
+        let spawner = block(2, &log);
+        spawner.borrow_mut().queue = Some(scene.get_queue());
+        scene.add_child("b", spawner);
+        // The changes wait until every child has been updated.
+        assert_eq!(order(&mut scene, &log), vec![1, 2]);
+        assert!(scene.get_child("a").is_err());
+        assert_eq!(scene.get_z("d"), Ok(-1));
+        assert_eq!(order(&mut scene, &log), vec![4, 2]);
+    }
+
+    #[test]
+    fn test_queue_flushed_before_update() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut scene = Scene::empty();
+        let queue = scene.get_queue();
+        queue.add_child("a", 0, block(1, &log));
+        queue.add_child("b", 0, block(2, &log));
+        queue.remove_child("a");
+        assert!(scene.get_child("b").is_err());
+        assert_eq!(order(&mut scene, &log), vec![2]);
+    }
+}
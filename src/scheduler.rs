@@ -1,85 +1,270 @@
-// extern crate sdl2;
-
-// use std::ops::{Deref, DerefMut, Fn};
-
-// use std::path::Path;
-// use std::vec::Vec;
-// use std::rc::Rc;
-// use std::cell::RefCell;
-
-// use sdl2::rect::Rect;
-// use sdl2::render::Renderer;
-// use sdl2::render::Texture;
-// use sdl2::render::BlendMode;
-// use sdl2::image::LoadTexture;
-// use sdl2::event::Event;
-// use sdl2::keyboard::Keycode;
-
-// use std::sync::{Arc, Mutex, Once, ONCE_INIT};
-// use std::time::{SystemTime, Duration};
-// use std::{mem, thread};
-
-// #[derive(Clone)]
-// pub struct Scheduler {
-//     // Since we will be used in many threads, we need to protect
-//     // concurrent access
-//     schedules: Vec<Rc<RefCell<Job<'static>>>>,
-// }
-
-// impl Scheduler {
-//     // add code here
-//     pub fn get_scheduler() -> Scheduler {
-//         // Initialize it to a null value
-//         static mut SINGLETON: *const Scheduler = 0 as *const Scheduler;
-//         static ONCE: Once = ONCE_INIT;
-
-//         unsafe {
-//             ONCE.call_once(|| {
-//                 // Make it
-//                 let singleton = Scheduler { schedules: Vec::new() };
-
-//                 // Put it in the heap so it can outlive this call
-//                 SINGLETON = mem::transmute(Box::new(singleton));
-//             });
-
-//             // Now we give out a copy of the data that is safe to use concurrently.
-//             (*SINGLETON).clone()
-//         }
-//     }
-
-//     pub fn unschedule(&mut self) {}
-//     pub fn schedule<F>(&mut self, cb: F, tg: i32, interval: f32) {
-//         self.schedules.push(Rc::new(RefCell::new(Job::new(cb, tg, interval))));
-//     }
-// }
-// trait FnBox {
-//     fn call_box(self: Box<Self>);
-// }
-
-// impl<F: Fn()> FnBox for F {
-//     fn call_box(self: Box<F>) {
-//         (*self)()
-//     }
-// }
-
-// type Caller<'a> = Box<FnBox + 'a>;
-
-// struct Job<'a> {
-//     running: bool,
-//     once: bool,
-//     callback: Caller<'a>,
-//     interval: f32,
-//     target: i32,
-// }
-
-// impl<'a> Job<'a> {
-//     pub fn new<F>(cb: F, tg: i32, interval: f32) -> Job<'a> {
-//         Job {
-//             once: false,
-//             running: false,
-//             callback: Box::new(cb()),
-//             interval: interval,
-//             target: tg,
-//         }
-//     }
-// }
+// The Scheduler runs callbacks after a delay or at intervals of game time.
+// It is owned by whatever drives it, usually the game loop, which steps it
+// once per frame and hands it the state callbacks work on, so jobs need no
+// shared or global state of their own.
+
+// JobId names a scheduled job, for unscheduling it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct JobId(u64);
+
+struct Job<T> {
+    id: JobId,
+    // Any number the owner likes, e.g. one per scene object, to unschedule
+    // or pause a group of jobs at once.
+    target: i32,
+    callback: Box<FnMut(&mut T)>,
+    interval: f32,
+    // Time left until the next call.
+    remaining: f32,
+    // Calls left, None for forever.
+    repeat: Option<u32>,
+    paused: bool,
+}
+
+pub struct Scheduler<T = ()> {
+    jobs: Vec<Job<T>>,
+    next_id: u64,
+    paused: bool,
+}
+
+impl<T> Scheduler<T> {
+    pub fn new() -> Scheduler<T> {
+        Scheduler {
+            jobs: Vec::new(),
+            next_id: 0,
+            paused: false,
+        }
+    }
+
+    // schedule calls `cb` every `interval` seconds until unscheduled.
+    pub fn schedule<F>(&mut self, target: i32, interval: f32, cb: F) -> JobId
+        where F: FnMut(&mut T) + 'static
+    {
+        self.schedule_ex(target, interval, None, interval, cb)
+    }
+
+    // schedule_once calls `cb` a single time, `delay` seconds from now.
+    pub fn schedule_once<F>(&mut self, target: i32, delay: f32, cb: F) -> JobId
+        where F: FnMut(&mut T) + 'static
+    {
+        self.schedule_ex(target, 0.0, Some(1), delay, cb)
+    }
+
+    // schedule_ex calls `cb` first after `delay` seconds, then every
+    // `interval` seconds, `repeat` times in all or forever for None.
+    pub fn schedule_ex<F>(&mut self,
+                          target: i32,
+                          interval: f32,
+                          repeat: Option<u32>,
+                          delay: f32,
+                          cb: F)
+                          -> JobId
+        where F: FnMut(&mut T) + 'static
+    {
+        self.next_id += 1;
+        let id = JobId(self.next_id);
+        if repeat == Some(0) {
+            return id;
+        }
+        self.jobs.push(Job {
+            id: id,
+            target: target,
+            callback: Box::new(cb),
+            interval: interval.max(0.0),
+            remaining: delay.max(0.0),
+            repeat: repeat,
+            paused: false,
+        });
+        id
+    }
+
+    pub fn unschedule(&mut self, id: JobId) {
+        self.jobs.retain(|j| j.id != id);
+    }
+
+    pub fn unschedule_target(&mut self, target: i32) {
+        self.jobs.retain(|j| j.target != target);
+    }
+
+    pub fn unschedule_all(&mut self) {
+        self.jobs.clear();
+    }
+
+    pub fn is_scheduled(&self, id: JobId) -> bool {
+        self.jobs.iter().any(|j| j.id == id)
+    }
+
+    // get_scheduled lists the jobs still to run, for scripts waiting on one.
+    pub fn get_scheduled(&self) -> Vec<JobId> {
+        self.jobs.iter().map(|j| j.id).collect()
+    }
+
+    pub fn has_target(&self, target: i32) -> bool {
+        self.jobs.iter().any(|j| j.target == target)
+    }
+
+    pub fn pause_target(&mut self, target: i32) {
+        for j in self.jobs.iter_mut().filter(|j| j.target == target) {
+            j.paused = true;
+        }
+    }
+
+    pub fn resume_target(&mut self, target: i32) {
+        for j in self.jobs.iter_mut().filter(|j| j.target == target) {
+            j.paused = false;
+        }
+    }
+
+    // pause stops the clock for every job.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // update advances the clock by `dt` seconds, calling every job that
+    // comes due, more than once if its interval is shorter than `dt`.
+    pub fn update(&mut self, dt: f32, state: &mut T) {
+        if self.paused {
+            return;
+        }
+        let mut i = 0;
+        while i < self.jobs.len() {
+            let done = {
+                let job = &mut self.jobs[i];
+                if job.paused {
+                    false
+                } else {
+                    job.remaining -= dt;
+                    while job.remaining <= 0.0 && job.repeat != Some(0) {
+                        (job.callback)(state);
+                        job.repeat = job.repeat.map(|n| n - 1);
+                        if job.interval <= 0.0 {
+                            // Zero intervals run once per update.
+                            job.remaining = 0.0;
+                            break;
+                        }
+                        job.remaining += job.interval;
+                    }
+                    job.repeat == Some(0)
+                }
+            };
+            if done {
+                self.jobs.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Jobs push their target onto the state, so calls can be counted.
+    fn calls(calls: &[i32], target: i32) -> usize {
+        calls.iter().filter(|&&t| t == target).count()
+    }
+
+    #[test]
+    fn test_schedule_once_waits_for_delay() {
+        let mut s: Scheduler<Vec<i32>> = Scheduler::new();
+        let mut state = Vec::new();
+        let id = s.schedule_once(1, 1.0, |v| v.push(1));
+        s.update(0.5, &mut state);
+        assert!(state.is_empty());
+        s.update(0.5, &mut state);
+        assert_eq!(state, vec![1]);
+        assert!(!s.is_scheduled(id));
+        s.update(5.0, &mut state);
+        assert_eq!(state, vec![1]);
+    }
+
+    #[test]
+    fn test_repeat_count() {
+        let mut s: Scheduler<Vec<i32>> = Scheduler::new();
+        let mut state = Vec::new();
+        let id = s.schedule_ex(1, 0.5, Some(3), 0.25, |v| v.push(1));
+        s.update(0.25, &mut state);
+        assert_eq!(calls(&state, 1), 1);
+        s.update(0.5, &mut state);
+        assert_eq!(calls(&state, 1), 2);
+        s.update(10.0, &mut state);
+        assert_eq!(calls(&state, 1), 3);
+        assert!(!s.is_scheduled(id));
+
+        let none = s.schedule_ex(2, 0.5, Some(0), 0.0, |v| v.push(2));
+        assert!(!s.is_scheduled(none));
+    }
+
+    #[test]
+    fn test_interval_shorter_than_dt() {
+        let mut s: Scheduler<Vec<i32>> = Scheduler::new();
+        let mut state = Vec::new();
+        s.schedule(1, 0.25, |v| v.push(1));
+        // Every interval that passed gets its call, carrying the remainder.
+        s.update(0.875, &mut state);
+        assert_eq!(calls(&state, 1), 3);
+        s.update(0.125, &mut state);
+        assert_eq!(calls(&state, 1), 4);
+
+        // A zero interval runs once per update however long it is.
+        s.schedule(2, 0.0, |v| v.push(2));
+        s.update(1.0, &mut state);
+        assert_eq!(calls(&state, 2), 1);
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let mut s: Scheduler<Vec<i32>> = Scheduler::new();
+        let mut state = Vec::new();
+        s.schedule_once(1, 1.0, |v| v.push(1));
+        s.schedule_once(2, 1.0, |v| v.push(2));
+        s.pause();
+        assert!(s.is_paused());
+        s.update(2.0, &mut state);
+        assert!(state.is_empty());
+        s.resume();
+
+        // A paused target keeps its time left.
+        s.pause_target(1);
+        s.update(1.0, &mut state);
+        assert_eq!(state, vec![2]);
+        s.resume_target(1);
+        s.update(0.5, &mut state);
+        assert_eq!(state, vec![2]);
+        s.update(0.5, &mut state);
+        assert_eq!(state, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_unschedule() {
+        let mut s: Scheduler<Vec<i32>> = Scheduler::new();
+        let mut state = Vec::new();
+        let a = s.schedule(1, 1.0, |v| v.push(1));
+        let b = s.schedule(1, 1.0, |v| v.push(1));
+        let c = s.schedule(2, 1.0, |v| v.push(2));
+        let d = s.schedule(3, 1.0, |v| v.push(3));
+
+        s.unschedule(a);
+        assert!(!s.is_scheduled(a));
+        assert!(s.is_scheduled(b));
+        s.unschedule_target(1);
+        assert!(!s.has_target(1));
+        assert!(s.has_target(2));
+        s.update(1.0, &mut state);
+        assert_eq!(state, vec![2, 3]);
+
+        s.unschedule_all();
+        assert!(!s.is_scheduled(c) && !s.is_scheduled(d));
+        s.update(1.0, &mut state);
+        assert_eq!(state, vec![2, 3]);
+    }
+}
@@ -0,0 +1,469 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::mem;
+use std::rc::Rc;
+
+use actions::{Action, ActionId, ActionManager, Target};
+use scheduler::JobId;
+
+// ScriptId names a running script, for stopping or waiting on it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ScriptId(u64);
+
+// Context is what a step sees of the world while it runs.
+pub struct Context<'a> {
+    dt: f32,
+    events: &'a [String],
+    // Kept current through the update: scripts leave it as they finish and
+    // join it as they are started.
+    running: Vec<ScriptId>,
+    actions: &'a mut ActionManager,
+    // The scheduler's jobs still pending, for steps waiting on one.
+    jobs: &'a [JobId],
+    next_id: &'a mut u64,
+    started: Vec<(ScriptId, Script)>,
+}
+
+impl<'a> Context<'a> {
+    // get_dt is the time passed since the last update, in seconds.
+    pub fn get_dt(&self) -> f32 {
+        self.dt
+    }
+
+    // has_event tells whether `name` was signalled since the last update.
+    pub fn has_event(&self, name: &str) -> bool {
+        self.events.iter().any(|e| e == name)
+    }
+
+    pub fn is_running(&self, id: ScriptId) -> bool {
+        self.running.contains(&id)
+    }
+
+    pub fn is_scheduled(&self, id: JobId) -> bool {
+        self.jobs.contains(&id)
+    }
+
+    pub fn get_actions(&mut self) -> &mut ActionManager {
+        self.actions
+    }
+
+    // run starts another script alongside, from the next update.
+    pub fn run(&mut self, script: Script) -> ScriptId {
+        *self.next_id += 1;
+        let id = ScriptId(*self.next_id);
+        self.started.push((id, script));
+        self.running.push(id);
+        id
+    }
+
+    fn finish(&mut self, id: ScriptId) {
+        self.running.retain(|&i| i != id);
+    }
+}
+
+// A step is polled once per update until it returns true.
+type Step = Box<FnMut(&mut Context) -> bool>;
+
+// Script is a linear list of steps, "show this, wait for that, then...",
+// run one after another without threads: each update runs steps until one
+// has to wait, and the next update picks up from there.
+pub struct Script {
+    steps: VecDeque<Step>,
+}
+
+impl Script {
+    pub fn new() -> Script {
+        Script { steps: VecDeque::new() }
+    }
+
+    // step adds a custom step, polled every update until it returns true.
+    pub fn step<F>(mut self, f: F) -> Script
+        where F: FnMut(&mut Context) -> bool + 'static
+    {
+        self.steps.push_back(Box::new(f));
+        self
+    }
+
+    // then runs `f` once.
+    pub fn then<F>(self, mut f: F) -> Script
+        where F: FnMut() + 'static
+    {
+        self.step(move |_| {
+            f();
+            true
+        })
+    }
+
+    // wait waits `secs` seconds of game time, counted from the next update.
+    pub fn wait(self, secs: f32) -> Script {
+        let mut left: Option<f32> = None;
+        self.step(move |ctx| {
+            let l = match left {
+                Some(l) => l - ctx.get_dt(),
+                None => secs,
+            };
+            left = Some(l);
+            l <= 0.0
+        })
+    }
+
+    // wait_until waits for `cond` to hold.
+    pub fn wait_until<F>(self, mut cond: F) -> Script
+        where F: FnMut() -> bool + 'static
+    {
+        self.step(move |_| cond())
+    }
+
+    // wait_event waits for `name` to be signalled, e.g. "flap".
+    pub fn wait_event(self, name: &str) -> Script {
+        let name = name.to_string();
+        self.step(move |ctx| ctx.has_event(&name))
+    }
+
+    // wait_job waits for a scheduler job to run, or to be unscheduled. A
+    // job that repeats forever is waited on until it is unscheduled.
+    pub fn wait_job(self, id: JobId) -> Script {
+        self.step(move |ctx| !ctx.is_scheduled(id))
+    }
+
+    // act runs an action on `target` and waits for it to finish.
+    pub fn act(self, target: Target, action: Box<Action>) -> Script {
+        let mut pending = Some((target, action));
+        let mut id: Option<ActionId> = None;
+        self.step(move |ctx| {
+            if let Some((target, action)) = pending.take() {
+                id = Some(ctx.get_actions().run(target, action));
+            }
+            !id.map_or(false, |id| ctx.get_actions().is_running(id))
+        })
+    }
+
+    // act_async runs an action without waiting for it.
+    pub fn act_async(self, target: Target, action: Box<Action>) -> Script {
+        let mut pending = Some((target, action));
+        self.step(move |ctx| {
+            if let Some((target, action)) = pending.take() {
+                ctx.get_actions().run(target, action);
+            }
+            true
+        })
+    }
+
+    // call runs another script to the end as part of this one.
+    pub fn call(self, mut script: Script) -> Script {
+        self.step(move |ctx| script.poll(ctx))
+    }
+
+    // fork starts another script alongside this one without waiting. The
+    // script gets its id once started, e.g. to join it later.
+    pub fn fork(self, script: Script, id: Rc<Cell<Option<ScriptId>>>) -> Script {
+        let mut script = Some(script);
+        self.step(move |ctx| {
+            if let Some(script) = script.take() {
+                id.set(Some(ctx.run(script)));
+            }
+            true
+        })
+    }
+
+    // fork_and_join starts scripts side by side and waits for all of them.
+    pub fn fork_and_join(self, scripts: Vec<Script>) -> Script {
+        let mut pending = Some(scripts);
+        let mut ids = Vec::new();
+        self.step(move |ctx| {
+            if let Some(scripts) = pending.take() {
+                ids = scripts.into_iter().map(|s| ctx.run(s)).collect();
+            }
+            !ids.iter().any(|&id| ctx.is_running(id))
+        })
+    }
+
+    // join waits for a script started elsewhere to finish.
+    pub fn join(self, id: ScriptId) -> Script {
+        self.step(move |ctx| !ctx.is_running(id))
+    }
+
+    // join_forked waits for a script started by `fork` to finish.
+    pub fn join_forked(self, id: Rc<Cell<Option<ScriptId>>>) -> Script {
+        self.step(move |ctx| !id.get().map_or(false, |id| ctx.is_running(id)))
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    // poll runs steps until one waits, returning true once all are done.
+    fn poll(&mut self, ctx: &mut Context) -> bool {
+        loop {
+            let done = match self.steps.front_mut() {
+                Some(step) => step(ctx),
+                None => return true,
+            };
+            if !done {
+                return false;
+            }
+            self.steps.pop_front();
+        }
+    }
+}
+
+// ScriptRunner runs scripts side by side. Scenes own one and step it after
+// their actions, so scripts freeze with the scene and end with a restart.
+// The game loop steps it with the scheduler's `dt` and pending jobs, so a
+// script's wait and a scheduled delay of the same length end together.
+pub struct ScriptRunner {
+    scripts: Vec<(ScriptId, Script)>,
+    events: Vec<String>,
+    next_id: u64,
+    paused: bool,
+}
+
+impl ScriptRunner {
+    pub fn new() -> ScriptRunner {
+        ScriptRunner {
+            scripts: Vec::new(),
+            events: Vec::new(),
+            next_id: 0,
+            paused: false,
+        }
+    }
+
+    pub fn run(&mut self, script: Script) -> ScriptId {
+        self.next_id += 1;
+        let id = ScriptId(self.next_id);
+        self.scripts.push((id, script));
+        id
+    }
+
+    pub fn stop(&mut self, id: ScriptId) {
+        self.scripts.retain(|&(i, _)| i != id);
+    }
+
+    pub fn stop_all(&mut self) {
+        self.scripts.clear();
+        self.events.clear();
+    }
+
+    pub fn is_running(&self, id: ScriptId) -> bool {
+        self.scripts.iter().any(|&(i, _)| i == id)
+    }
+
+    // signal raises an event for the scripts to see on their next update.
+    pub fn signal(&mut self, name: &str) {
+        self.events.push(name.to_string());
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn update(&mut self, dt: f32, actions: &mut ActionManager, jobs: &[JobId]) {
+        if self.paused {
+            return;
+        }
+        let events = mem::replace(&mut self.events, Vec::new());
+        let mut finished = Vec::new();
+        let started = {
+            let mut ctx = Context {
+                dt: dt,
+                events: &events,
+                running: self.scripts.iter().map(|&(id, _)| id).collect(),
+                actions: actions,
+                jobs: jobs,
+                next_id: &mut self.next_id,
+                started: Vec::new(),
+            };
+            for &mut (id, ref mut script) in &mut self.scripts {
+                if script.poll(&mut ctx) {
+                    finished.push(id);
+                    ctx.finish(id);
+                }
+            }
+            ctx.started
+        };
+        self.scripts.retain(|&(id, _)| !finished.contains(&id));
+        self.scripts.extend(started);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    use actions::{self, Actionable};
+    use scheduler::Scheduler;
+    use transform::Transform;
+
+    type Log = Rc<RefCell<Vec<&'static str>>>;
+
+    fn note(script: Script, log: &Log, what: &'static str) -> Script {
+        let log = log.clone();
+        script.then(move || log.borrow_mut().push(what))
+    }
+
+    fn new_log() -> Log {
+        Rc::new(RefCell::new(Vec::new()))
+    }
+
+    fn step(runner: &mut ScriptRunner, actions: &mut ActionManager, times: u32) {
+        for _ in 0..times {
+            runner.update(0.5, actions, &[]);
+        }
+    }
+
+    #[test]
+    fn test_wait() {
+        let (log, mut runner, mut actions) = (new_log(), ScriptRunner::new(), ActionManager::new());
+        let id = runner.run(note(Script::new().wait(1.0), &log, "done"));
+        // The first update starts the clock, two more use the second up.
+        step(&mut runner, &mut actions, 2);
+        assert!(log.borrow().is_empty());
+        step(&mut runner, &mut actions, 1);
+        assert_eq!(*log.borrow(), vec!["done"]);
+        assert!(!runner.is_running(id));
+    }
+
+    #[test]
+    fn test_wait_event() {
+        let (log, mut runner, mut actions) = (new_log(), ScriptRunner::new(), ActionManager::new());
+        runner.run(note(Script::new().wait_event("flap"), &log, "flapped"));
+        step(&mut runner, &mut actions, 1);
+        runner.signal("bump");
+        step(&mut runner, &mut actions, 1);
+        assert!(log.borrow().is_empty());
+        runner.signal("flap");
+        step(&mut runner, &mut actions, 1);
+        assert_eq!(*log.borrow(), vec!["flapped"]);
+    }
+
+    #[test]
+    fn test_call_runs_in_order() {
+        let (log, mut runner, mut actions) = (new_log(), ScriptRunner::new(), ActionManager::new());
+        let inner = note(note(Script::new(), &log, "inner").wait_event("go"), &log, "inner done");
+        runner.run(note(note(Script::new(), &log, "before").call(inner), &log, "after"));
+        step(&mut runner, &mut actions, 2);
+        assert_eq!(*log.borrow(), vec!["before", "inner"]);
+        runner.signal("go");
+        step(&mut runner, &mut actions, 1);
+        assert_eq!(*log.borrow(), vec!["before", "inner", "inner done", "after"]);
+    }
+
+    #[test]
+    fn test_act_waits_for_action() {
+        struct Dot(Transform);
+        impl Actionable for Dot {
+            fn get_transform(&self) -> Transform {
+                self.0
+            }
+            fn set_transform(&mut self, transform: Transform) {
+                self.0 = transform;
+            }
+            fn get_visible(&self) -> bool {
+                true
+            }
+            fn set_visible(&mut self, _visible: bool) {}
+        }
+
+        let (log, mut runner, mut actions) = (new_log(), ScriptRunner::new(), ActionManager::new());
+        let dot = Rc::new(RefCell::new(Dot(Transform::identity())));
+        runner.run(note(Script::new().act(dot.clone(), actions::move_by(1.0, 10.0, 0.0)), &log, "moved"));
+        // Scenes step actions first, then scripts.
+        for _ in 0..2 {
+            actions.update(0.5);
+            runner.update(0.5, &mut actions, &[]);
+        }
+        assert!(log.borrow().is_empty());
+        actions.update(0.5);
+        runner.update(0.5, &mut actions, &[]);
+        assert_eq!(*log.borrow(), vec!["moved"]);
+        assert_eq!(dot.borrow().0.x, 10.0);
+    }
+
+    #[test]
+    fn test_join_forked() {
+        let (log, mut runner, mut actions) = (new_log(), ScriptRunner::new(), ActionManager::new());
+        let child = note(Script::new().wait_event("go"), &log, "child");
+        let id = Rc::new(Cell::new(None));
+        let id2 = id.clone();
+        runner.run(note(Script::new().fork(child, id.clone()).join_forked(id), &log, "joined"));
+        // The child only starts with the next update, it still counts as running.
+        step(&mut runner, &mut actions, 2);
+        assert!(log.borrow().is_empty());
+        assert!(runner.is_running(id2.get().unwrap()));
+        runner.signal("go");
+        step(&mut runner, &mut actions, 1);
+        assert_eq!(*log.borrow(), vec!["child"]);
+        step(&mut runner, &mut actions, 1);
+        assert_eq!(*log.borrow(), vec!["child", "joined"]);
+    }
+
+    #[test]
+    fn test_fork_and_join() {
+        let (log, mut runner, mut actions) = (new_log(), ScriptRunner::new(), ActionManager::new());
+        let fast = note(Script::new().wait(0.5), &log, "fast");
+        let slow = note(Script::new().wait(1.5), &log, "slow");
+        runner.run(note(Script::new().fork_and_join(vec![slow, fast]), &log, "both"));
+        step(&mut runner, &mut actions, 6);
+        assert_eq!(*log.borrow(), vec!["fast", "slow", "both"]);
+    }
+
+    #[test]
+    fn test_join_by_id() {
+        let (log, mut runner, mut actions) = (new_log(), ScriptRunner::new(), ActionManager::new());
+        let first = runner.run(note(Script::new().wait_event("go"), &log, "first"));
+        runner.run(note(Script::new().join(first), &log, "second"));
+        step(&mut runner, &mut actions, 2);
+        assert!(log.borrow().is_empty());
+        runner.signal("go");
+        // The first finishes before the second is polled, in the same update.
+        step(&mut runner, &mut actions, 1);
+        assert_eq!(*log.borrow(), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn test_pause_and_stop() {
+        let (log, mut runner, mut actions) = (new_log(), ScriptRunner::new(), ActionManager::new());
+        let id = runner.run(note(Script::new().wait(0.5), &log, "done"));
+        runner.pause();
+        step(&mut runner, &mut actions, 4);
+        assert!(log.borrow().is_empty());
+        runner.resume();
+        step(&mut runner, &mut actions, 2);
+        assert_eq!(*log.borrow(), vec!["done"]);
+
+        let id2 = runner.run(Script::new().wait_event("never"));
+        runner.stop(id2);
+        assert!(!runner.is_running(id) && !runner.is_running(id2));
+    }
+
+    #[test]
+    fn test_wait_with_scheduler() {
+        let (log, mut runner, mut actions) = (new_log(), ScriptRunner::new(), ActionManager::new());
+        let mut scheduler: Scheduler = Scheduler::new();
+        let (a, b) = (log.clone(), log.clone());
+        let first = scheduler.schedule_once(0, 0.5, move |_| a.borrow_mut().push("first"));
+        scheduler.schedule_once(0, 1.0, move |_| b.borrow_mut().push("second"));
+        runner.run(note(Script::new().wait_job(first).wait(0.5), &log, "script"));
+        // Both run on one clock, here not a 60 Hz one: the script's wait
+        // starts as the first job runs and ends along with the second.
+        let mut update = || {
+            scheduler.update(0.25, &mut ());
+            runner.update(0.25, &mut actions, &scheduler.get_scheduled());
+        };
+        update();
+        assert!(log.borrow().is_empty());
+        update();
+        assert_eq!(*log.borrow(), vec!["first"]);
+        update();
+        assert_eq!(*log.borrow(), vec!["first"]);
+        update();
+        assert_eq!(*log.borrow(), vec!["first", "second", "script"]);
+    }
+
+}
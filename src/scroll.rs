@@ -0,0 +1,221 @@
+// Wrap-around scrolling shared by every scrolling background.
+//
+// A background is an image drawn as a `tile`, optionally followed by a gap,
+// repeating forever on the scrolling axes. Painting a view of it comes down
+// to one dimensional spans: (tile_start, view_start, len), which say that
+// `len` pixels from `tile_start` in the tile land at `view_start` in the view.
+// Working on spans rather than on "the two halves" keeps textures narrower
+// than the view working, they simply produce more spans.
+
+pub type Span = (u32, u32, u32);
+
+// wrap_spans splits `view` pixels of an endlessly repeating `tile`, scrolled
+// by `phase`, into spans.
+pub fn wrap_spans(phase: i32, tile: u32, view: u32) -> Vec<Span> {
+    let mut spans = Vec::new();
+    if tile == 0 {
+        return spans;
+    }
+    let t = tile as i32;
+    let mut src = ((phase % t + t) % t) as u32;
+    let mut dst = 0;
+    while dst < view {
+        let len = (tile - src).min(view - dst);
+        spans.push((src, dst, len));
+        dst += len;
+        src = 0;
+    }
+    spans
+}
+
+// clip_span is the part of a single `tile` placed at `pos` that lies in `view`.
+pub fn clip_span(pos: i32, tile: u32, view: u32) -> Vec<Span> {
+    let start = pos.max(0);
+    let end = (pos + tile as i32).min(view as i32);
+    if end <= start {
+        return Vec::new();
+    }
+    vec![((start - pos) as u32, start as u32, (end - start) as u32)]
+}
+
+// skip_gap drops the parts of the spans that fall in the gap after a `tile`.
+pub fn skip_gap(spans: Vec<Span>, tile: u32) -> Vec<Span> {
+    spans.into_iter()
+         .filter(|s| s.0 < tile)
+         .map(|s| (s.0, s.1, s.2.min(tile - s.0)))
+         .collect()
+}
+
+// to_texture maps a span in tile pixels to a (start, len) in texture pixels,
+// for tiles drawn stretched from a texture of a different size.
+pub fn to_texture(span: Span, tile: u32, tex: u32) -> (i32, u32) {
+    let scale = tex as f32 / tile as f32;
+    ((span.0 as f32 * scale) as i32, ((span.2 as f32 * scale).round() as u32).max(1))
+}
+
+// wrap_offset brings a scroll position back into [0, period).
+pub fn wrap_offset(v: f32, period: u32) -> f32 {
+    if period == 0 {
+        return v;
+    }
+    let p = period as f32;
+    ((v % p) + p) % p
+}
+
+// Scroller is the scroll state of a background: a sub-pixel position and the
+// gap between repeats.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Scroller {
+    pub offset: (f32, f32),
+    pub gap: (u32, u32),
+}
+
+impl Scroller {
+    pub fn new() -> Scroller {
+        Scroller {
+            offset: (0.0, 0.0),
+            gap: (0, 0),
+        }
+    }
+
+    // advance scrolls by (dx, dy), positive values moving the image left or up.
+    pub fn advance(&mut self, dx: f32, dy: f32, tile: (u32, u32)) {
+        self.offset = (wrap_offset(self.offset.0 + dx, tile.0 + self.gap.0),
+                       wrap_offset(self.offset.1 + dy, tile.1 + self.gap.1));
+    }
+
+    // spans resolves the x and y spans of a `view` sized window. `wrap` tells
+    // which axes repeat and `shift` moves the image on screen, e.g. for a camera.
+    pub fn spans(&self,
+                 tile: (u32, u32),
+                 wrap: (bool, bool),
+                 view: (u32, u32),
+                 shift: (i32, i32))
+                 -> (Vec<Span>, Vec<Span>) {
+        (self.axis(self.offset.0, tile.0, self.gap.0, wrap.0, view.0, shift.0),
+         self.axis(self.offset.1, tile.1, self.gap.1, wrap.1, view.1, shift.1))
+    }
+
+    fn axis(&self, offset: f32, tile: u32, gap: u32, wrap: bool, view: u32, shift: i32) -> Vec<Span> {
+        let offset = offset.round() as i32;
+        if wrap {
+            skip_gap(wrap_spans(offset - shift, tile + gap, view), tile)
+        } else {
+            clip_span(shift - offset, tile, view)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn covered(spans: &[Span]) -> u32 {
+        spans.iter().map(|s| s.2).sum()
+    }
+
+    #[test]
+    fn test_wrap_spans_aligned() {
+        assert_eq!(wrap_spans(0, 288, 288), vec![(0, 0, 288)]);
+    }
+
+    #[test]
+    fn test_wrap_spans_split() {
+        assert_eq!(wrap_spans(100, 288, 288), vec![(100, 0, 188), (0, 188, 100)]);
+    }
+
+    #[test]
+    fn test_wrap_spans_negative_phase() {
+        assert_eq!(wrap_spans(-30, 100, 100), vec![(70, 0, 30), (0, 30, 70)]);
+        assert_eq!(wrap_spans(-130, 100, 100), wrap_spans(-30, 100, 100));
+    }
+
+    #[test]
+    fn test_wrap_spans_phase_past_tile() {
+        assert_eq!(wrap_spans(1000, 288, 288), wrap_spans(1000 % 288, 288, 288));
+    }
+
+    // The old scenes computed `sz.0 - self.width` on u32, which underflows
+    // as soon as the texture is narrower than the window.
+    #[test]
+    fn test_wrap_spans_texture_narrower_than_view() {
+        let spans = wrap_spans(5, 288, 800);
+        assert_eq!(spans, vec![(5, 0, 283), (0, 283, 288), (0, 571, 229)]);
+        assert_eq!(covered(&spans), 800);
+    }
+
+    #[test]
+    fn test_wrap_spans_tiny_tile() {
+        let spans = wrap_spans(0, 1, 4);
+        assert_eq!(spans.len(), 4);
+        assert_eq!(covered(&spans), 4);
+    }
+
+    #[test]
+    fn test_wrap_spans_empty() {
+        assert!(wrap_spans(10, 0, 800).is_empty());
+        assert!(wrap_spans(10, 288, 0).is_empty());
+    }
+
+    #[test]
+    fn test_clip_span() {
+        assert_eq!(clip_span(0, 100, 80), vec![(0, 0, 80)]);
+        assert_eq!(clip_span(-10, 100, 80), vec![(10, 0, 80)]);
+        assert_eq!(clip_span(20, 40, 80), vec![(0, 20, 40)]);
+        assert!(clip_span(90, 100, 80).is_empty());
+        assert!(clip_span(-100, 100, 80).is_empty());
+    }
+
+    #[test]
+    fn test_skip_gap() {
+        // 100px tile with a 50px gap, viewed from 80px in: 20px of image,
+        // 50px of gap, then the next repeat.
+        let spans = skip_gap(wrap_spans(80, 150, 200), 100);
+        assert_eq!(spans, vec![(80, 0, 20), (0, 70, 100)]);
+    }
+
+    #[test]
+    fn test_to_texture() {
+        assert_eq!(to_texture((0, 0, 800), 800, 1920), (0, 1920));
+        assert_eq!(to_texture((10, 0, 50), 800, 1920), (24, 120));
+        assert_eq!(to_texture((0, 0, 1), 800, 100), (0, 1));
+    }
+
+    #[test]
+    fn test_wrap_offset() {
+        assert_eq!(wrap_offset(290.0, 288), 2.0);
+        assert_eq!(wrap_offset(-1.5, 10), 8.5);
+        assert_eq!(wrap_offset(5.0, 0), 5.0);
+    }
+
+    #[test]
+    fn test_scroller_advance_wraps_with_gap() {
+        let mut s = Scroller::new();
+        s.gap = (12, 0);
+        for _ in 0..30 {
+            s.advance(10.0, 0.0, (288, 512));
+        }
+        // 30 steps of 10px is exactly one tile plus its gap.
+        assert_eq!(s.offset, (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_scroller_spans_cover_view() {
+        let mut s = Scroller::new();
+        s.advance(123.0, 0.0, (288, 512));
+        let (xs, ys) = s.spans((288, 512), (true, false), (800, 600), (0, 0));
+        assert_eq!(covered(&xs), 800);
+        assert_eq!(ys, vec![(0, 0, 512)]);
+    }
+
+    #[test]
+    fn test_scroller_shift_is_scrolling_on_wrapped_axis() {
+        let mut a = Scroller::new();
+        a.offset = (40.0, 0.0);
+        let b = Scroller::new();
+        let view = (800, 600);
+        assert_eq!(a.spans((800, 600), (true, false), view, (0, 0)).0,
+                   b.spans((800, 600), (true, false), view, (-40, 0)).0);
+    }
+}
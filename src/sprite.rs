@@ -17,16 +17,19 @@ use sdl2::keyboard::Keycode;
 
 use node::Node;
 use display::{Displayable, Float, FloatToDuration};
-use atlas::TexElement;
+use atlas::{TexElement, Tint};
 use animation::Animation;
+use transform::Transform;
 
 // #[derive(Debug)]
 pub struct Sprite {
-    x: i32,
-    y: i32,
+    transform: Transform,
+    // Kept on the sprite rather than the element, which may be shared.
+    tint: Tint,
     interval: f32,
     lasttime: SystemTime,
-    // visible: bool,
+    // Kept apart from the element's flag, hidden atlas elements still show through sprites.
+    visible: bool,
     running: bool,
     tex: Rc<RefCell<TexElement>>,
 }
@@ -34,34 +37,112 @@ pub struct Sprite {
 impl Sprite {
     pub fn new_from_tex(tex: Rc<RefCell<TexElement>>) -> Sprite {
         Sprite {
-            x: 0,
-            y: 0,
+            transform: Transform::identity(),
+            tint: Tint::white(),
             interval: 0.0,
             lasttime: SystemTime::now(),
-            // visible: true,
+            visible: true,
             running: false,
             tex: tex,
         }
     }
     pub fn new(renderer: &Renderer, path: &str) -> Sprite {
         Sprite {
-            x: 0,
-            y: 0,
+            transform: Transform::identity(),
+            tint: Tint::white(),
             interval: 0.0,
             lasttime: SystemTime::now(),
-            // visible: true,
+            visible: true,
             running: false,
             tex: Rc::new(RefCell::new(TexElement::new(renderer, path))),
         }
     }
 
+    pub fn get_size(&self) -> (u32, u32) {
+        self.tex.borrow().get_size()
+    }
+
+    // get_tex is the element the sprite paints, shared with its atlas.
+    pub fn get_tex(&self) -> Rc<RefCell<TexElement>> {
+        self.tex.clone()
+    }
+
+    pub fn get_visible(&self) -> bool {
+        self.visible
+    }
+
     pub fn set_position(&mut self, x: i32, y: i32) {
-        self.x = x;
-        self.y = y;
+        self.transform.x = x as f32;
+        self.transform.y = y as f32;
     }
 
     pub fn get_position(&self) -> (i32, i32) {
-        (self.x, self.y)
+        (self.transform.x as i32, self.transform.y as i32)
+    }
+
+    pub fn set_scale(&mut self, sx: f32, sy: f32) {
+        self.transform.scale_x = sx;
+        self.transform.scale_y = sy;
+    }
+
+    pub fn get_scale(&self) -> (f32, f32) {
+        (self.transform.scale_x, self.transform.scale_y)
+    }
+
+    pub fn set_rotation(&mut self, degrees: f32) {
+        self.transform.rotation = degrees;
+    }
+
+    pub fn get_rotation(&self) -> f32 {
+        self.transform.rotation
+    }
+
+    pub fn set_anchor(&mut self, ax: f32, ay: f32) {
+        self.transform.anchor_x = ax;
+        self.transform.anchor_y = ay;
+    }
+
+    pub fn get_anchor(&self) -> (f32, f32) {
+        (self.transform.anchor_x, self.transform.anchor_y)
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    pub fn get_transform(&self) -> Transform {
+        self.transform
+    }
+
+    pub fn set_color(&mut self, r: u8, g: u8, b: u8) {
+        self.tint.r = r;
+        self.tint.g = g;
+        self.tint.b = b;
+    }
+
+    pub fn get_color(&self) -> (u8, u8, u8) {
+        (self.tint.r, self.tint.g, self.tint.b)
+    }
+
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.tint.a = alpha;
+    }
+
+    pub fn get_alpha(&self) -> u8 {
+        self.tint.a
+    }
+
+    pub fn set_tint(&mut self, tint: Tint) {
+        self.tint = tint;
+    }
+
+    pub fn get_tint(&self) -> Tint {
+        self.tint
+    }
+
+    // paint_src copies part of the sprite's texture in screen space with the sprite's tint.
+    pub fn paint_src(&self, renderer: &mut Renderer, src: Option<Rect>, dst: Rect) {
+        self.tex.borrow().paint_src(renderer, src, dst, &self.tint);
     }
 
     pub fn start(&mut self) {
@@ -89,15 +170,15 @@ impl Sprite {
         self.interval
     }
 
-    // pub fn hide<'a>(&'a mut self) -> &'a mut Sprite {
-    //     self.visible = false;
-    //     self
-    // }
+    pub fn hide<'a>(&'a mut self) -> &'a mut Sprite {
+        self.visible = false;
+        self
+    }
 
-    // pub fn show<'a>(&'a mut self) -> &'a mut Sprite {
-    //     self.visible = true;
-    //     self
-    // }
+    pub fn show<'a>(&'a mut self) -> &'a mut Sprite {
+        self.visible = true;
+        self
+    }
 
     // pub fn reset(&mut self, start_x: i32, start_y: i32) {
     // self.x = start_x;
@@ -115,9 +196,12 @@ impl Displayable for Sprite {
     fn update(&mut self) {}
 
     fn paint(&self, renderer: &mut Renderer) {
-        if self.get_visible() {
-            let rect = Rect::new(self.x, self.y, self.width(), self.height());
-            self.paint_ex(renderer, rect);
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        if self.visible {
+            self.tex.borrow().paint_tinted(renderer, &parent.then(&self.transform), &self.tint);
             // renderer.copy_ex(&self.texture, None, Some(rect), 0.0, None, false, false)
             //         .expect("Single star particle should have rendered.");
         }
@@ -133,23 +217,6 @@ impl Displayable for Sprite {
     }
 }
 
-impl Deref for Sprite {
-    type Target = TexElement;
-
-    fn deref<'a>(&'a self) -> &'a TexElement {
-        // FIXME
-        &(*self.tex)
-    }
-}
-
-impl DerefMut for Sprite {
-    fn deref_mut<'a>(&'a mut self) -> &'a mut TexElement {
-        // FIXME
-        &mut (*self.tex)
-    }
-}
-
-
 // 自动移动屏幕
 pub struct AutoPan {
     max_w: u32,
@@ -172,12 +239,10 @@ impl Displayable for AutoPan {
 
     fn paint(&self, renderer: &mut Renderer) {
         if self.get_visible() {
-            let sz = self.sprite.get_visible_size();
-            let rect = Rect::new(self.x, self.y, sz.0, sz.1);
-            // self.paint_ex(renderer, rect);
-            let texture = self.sprite.get_texture();
-            renderer.copy_ex(texture, None, Some(rect), 0.0, None, false, false)
-                    .expect("Single star particle should have rendered.");
+            let src = self.sprite.get_tex().borrow().get_visible_rect();
+            let pos = self.sprite.get_position();
+            let rect = Rect::new(pos.0, pos.1, src.width(), src.height());
+            self.sprite.paint_src(renderer, Some(src), rect);
         }
     }
     fn on_key_down(&mut self, event: &Event) {
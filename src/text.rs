@@ -0,0 +1,483 @@
+extern crate sdl2;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::path::Path;
+use std::rc::Rc;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Renderer;
+use sdl2::ttf::{self, Font, FontStyle, Sdl2TtfContext};
+
+use display::Displayable;
+use atlas::{TexElement, Tint};
+use transform::Transform;
+use ui::{Widget, Align};
+
+pub const DEFAULT_FONT: &'static str = "res/fonts/default.ttf";
+// Past this many rendered strings, those no label shows any more are dropped.
+const MAX_STRINGS: usize = 256;
+
+// FontKey names a loaded font: its file, point size and style bits.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FontKey {
+    pub path: String,
+    pub size: u16,
+    pub style: i32,
+}
+
+impl FontKey {
+    pub fn new(path: &str, size: u16) -> FontKey {
+        FontKey {
+            path: path.to_string(),
+            size: size,
+            style: ttf::STYLE_NORMAL.bits(),
+        }
+    }
+
+    pub fn with_style(mut self, style: FontStyle) -> FontKey {
+        self.style = style.bits();
+        self
+    }
+}
+
+// TextStyle is how a label draws its text. The outline is drawn around
+// the letters and the shadow under them, offset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextStyle {
+    pub font: FontKey,
+    pub color: Tint,
+    pub outline: Option<(u16, Tint)>,
+    pub shadow: Option<(i32, i32, Tint)>,
+    // Extra pixels between lines, on top of the font's own spacing.
+    pub line_spacing: i32,
+}
+
+impl TextStyle {
+    pub fn new(font: FontKey) -> TextStyle {
+        TextStyle {
+            font: font,
+            color: Tint::white(),
+            outline: None,
+            shadow: None,
+            line_spacing: 0,
+        }
+    }
+}
+
+// One string rendered in one font, color and outline width.
+#[derive(PartialEq, Eq, Hash)]
+struct StringKey {
+    font: FontKey,
+    text: String,
+    color: Tint,
+    outline: u16,
+}
+
+// TextSystem loads fonts through a TTF context made once by the game, and
+// caches the fonts it loads and the strings it renders, so a string drawn
+// every frame is rendered once.
+pub struct TextSystem<'ttf> {
+    ttf: &'ttf Sdl2TtfContext,
+    fonts: HashMap<FontKey, Rc<RefCell<Font<'ttf, 'static>>>>,
+    strings: HashMap<StringKey, Rc<RefCell<TexElement>>>,
+}
+
+impl<'ttf> TextSystem<'ttf> {
+    pub fn new(ttf: &'ttf Sdl2TtfContext) -> TextSystem<'ttf> {
+        TextSystem {
+            ttf: ttf,
+            fonts: HashMap::new(),
+            strings: HashMap::new(),
+        }
+    }
+
+    // get_font loads a font once, then hands out the cached one.
+    pub fn get_font(&mut self, key: &FontKey) -> Result<Rc<RefCell<Font<'ttf, 'static>>>, String> {
+        if let Some(font) = self.fonts.get(key) {
+            return Ok(font.clone());
+        }
+        let mut font = self.ttf.load_font(Path::new(&key.path), key.size)?;
+        font.set_style(FontStyle::from_bits_truncate(key.style));
+        let font = Rc::new(RefCell::new(font));
+        self.fonts.insert(key.clone(), font.clone());
+        Ok(font)
+    }
+
+    // render renders a line of text, or hands out the cached rendering.
+    // A non zero outline renders only the outline, a little larger.
+    pub fn render(&mut self,
+                  renderer: &Renderer,
+                  font: &FontKey,
+                  text: &str,
+                  color: Tint,
+                  outline: u16)
+                  -> Result<Rc<RefCell<TexElement>>, String> {
+        let key = StringKey {
+            font: font.clone(),
+            text: text.to_string(),
+            color: color,
+            outline: outline,
+        };
+        if let Some(tex) = self.strings.get(&key) {
+            return Ok(tex.clone());
+        }
+        let font = self.get_font(font)?;
+        let mut font = font.borrow_mut();
+        font.set_outline_width(outline);
+        let surface = font.render(text)
+                          .blended(Color::RGBA(color.r, color.g, color.b, color.a))
+                          .map_err(|e| e.to_string());
+        font.set_outline_width(0);
+        let surface = surface?;
+        let texture = renderer.create_texture_from_surface(&surface)
+                              .map_err(|e| e.to_string())?;
+        let rect = Rect::new(0, 0, surface.width(), surface.height());
+        let tex = Rc::new(RefCell::new(TexElement::new_from_texture(Rc::new(RefCell::new(texture)), rect)));
+        if self.strings.len() >= MAX_STRINGS {
+            prune_unused(&mut self.strings);
+        }
+        self.strings.insert(key, tex.clone());
+        Ok(tex)
+    }
+
+    // measure is how wide a line of text is, without rendering it.
+    pub fn measure(&mut self, font: &FontKey, text: &str) -> Result<u32, String> {
+        let font = self.get_font(font)?;
+        let (w, _) = font.borrow().size_of(text).map_err(|e| e.to_string())?;
+        Ok(w)
+    }
+
+    // get_line_height is the distance between two lines of a font.
+    pub fn get_line_height(&mut self, font: &FontKey) -> Result<i32, String> {
+        let font = self.get_font(font)?;
+        let height = font.borrow().recommended_line_spacing();
+        Ok(height)
+    }
+
+    // clear_strings forgets the rendered strings, e.g. after changing language.
+    pub fn clear_strings(&mut self) {
+        self.strings.clear();
+    }
+}
+
+// prune_unused drops the cached values nothing else holds on to.
+fn prune_unused<K: Eq + Hash, V>(cache: &mut HashMap<K, Rc<V>>) {
+    cache.retain(|_, v| Rc::strong_count(v) > 1);
+}
+
+// is_cjk tells whether a character is Chinese, Japanese or Korean, or
+// their punctuation. Such text has no spaces and may break between any two
+// of them.
+fn is_cjk(c: char) -> bool {
+    match c as u32 {
+        0x3000..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xFF00..=0xFFEF => true,
+        _ => false,
+    }
+}
+
+// split_breaks splits a paragraph where a line may break: at spaces, which
+// the pieces after them keep in front, and around CJK characters.
+fn split_breaks(paragraph: &str) -> Vec<String> {
+    let mut pieces: Vec<String> = Vec::new();
+    let mut piece = String::new();
+    let mut last_cjk = false;
+    for c in paragraph.chars() {
+        let cjk = is_cjk(c);
+        if !piece.is_empty() && (c == ' ' || cjk || last_cjk) {
+            pieces.push(piece);
+            piece = String::new();
+        }
+        piece.push(c);
+        last_cjk = cjk;
+    }
+    pieces.push(piece);
+    pieces
+}
+
+// wrap breaks text into lines no wider than `max_width`, as measured by
+// `measure`, at spaces and between CJK characters, dropping the spaces at
+// the breaks. A word wider than a line gets a line to itself and newlines
+// always break.
+pub fn wrap<F: FnMut(&str) -> u32>(text: &str, max_width: Option<u32>, mut measure: F) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let max_width = match max_width {
+            Some(w) => w,
+            None => {
+                lines.push(paragraph.to_string());
+                continue;
+            }
+        };
+        let mut line = String::new();
+        for piece in split_breaks(paragraph) {
+            let candidate = if line.is_empty() {
+                piece.trim_start().to_string()
+            } else {
+                format!("{}{}", line, piece)
+            };
+            if line.is_empty() || measure(&candidate) <= max_width {
+                line = candidate;
+            } else {
+                lines.push(line.trim_end().to_string());
+                line = piece.trim_start().to_string();
+            }
+        }
+        lines.push(line.trim_end().to_string());
+    }
+    lines
+}
+
+// A rendered piece of a line, painted at an offset from the line.
+struct Layer {
+    tex: Rc<RefCell<TexElement>>,
+    dx: i32,
+    dy: i32,
+}
+
+struct Line {
+    // Position of the line in the label's box.
+    x: i32,
+    y: i32,
+    layers: Vec<Layer>,
+}
+
+// Label is text on screen, wrapped to a width and aligned within it, with
+// an optional outline and shadow. Changing the text renders it again
+// through the text system's cache.
+pub struct Label {
+    text: String,
+    style: TextStyle,
+    align: Align,
+    max_width: Option<u32>,
+    rect: Rect,
+    lines: Vec<Line>,
+    tint: Tint,
+    visible: bool,
+}
+
+impl Label {
+    pub fn new(text: &mut TextSystem,
+               renderer: &Renderer,
+               s: &str,
+               style: TextStyle)
+               -> Result<Label, String> {
+        let mut label = Label {
+            text: String::new(),
+            style: style,
+            align: Align::Left,
+            max_width: None,
+            rect: Rect::new(0, 0, 1, 1),
+            lines: Vec::new(),
+            tint: Tint::white(),
+            visible: true,
+        };
+        label.set_text(text, renderer, s)?;
+        Ok(label)
+    }
+
+    // set_text changes the text, keeping the label's top left corner.
+    pub fn set_text(&mut self, text: &mut TextSystem, renderer: &Renderer, s: &str) -> Result<(), String> {
+        self.text = s.to_string();
+        self.render(text, renderer)
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    // set_max_width wraps the text at `w` pixels, or not at all with None.
+    pub fn set_max_width(&mut self,
+                         text: &mut TextSystem,
+                         renderer: &Renderer,
+                         w: Option<u32>)
+                         -> Result<(), String> {
+        self.max_width = w;
+        self.render(text, renderer)
+    }
+
+    pub fn set_style(&mut self, text: &mut TextSystem, renderer: &Renderer, style: TextStyle) -> Result<(), String> {
+        self.style = style;
+        self.render(text, renderer)
+    }
+
+    // set_align aligns the lines with each other within the label.
+    pub fn set_align(&mut self, align: Align) {
+        self.align = align;
+        self.align_lines();
+    }
+
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.tint.a = alpha;
+    }
+
+    pub fn hide<'a>(&'a mut self) -> &'a mut Label {
+        self.visible = false;
+        self
+    }
+
+    pub fn show<'a>(&'a mut self) -> &'a mut Label {
+        self.visible = true;
+        self
+    }
+
+    fn render(&mut self, text: &mut TextSystem, renderer: &Renderer) -> Result<(), String> {
+        let style = self.style.clone();
+        let font = &style.font;
+        let mut failed = None;
+        let wrapped = wrap(&self.text, self.max_width, |s| {
+            match text.measure(font, s) {
+                Ok(w) => w,
+                Err(e) => {
+                    failed = Some(e);
+                    0
+                }
+            }
+        });
+        if let Some(e) = failed {
+            return Err(e);
+        }
+        let line_height = text.get_line_height(font)? + style.line_spacing;
+
+        self.lines.clear();
+        for (i, s) in wrapped.iter().enumerate() {
+            let mut layers = Vec::new();
+            // An empty line renders nothing but still takes its height.
+            if !s.is_empty() {
+                if let Some((dx, dy, color)) = style.shadow {
+                    let tex = text.render(renderer, font, s, color, 0)?;
+                    layers.push(Layer { tex: tex, dx: dx, dy: dy });
+                }
+                if let Some((width, color)) = style.outline {
+                    let tex = text.render(renderer, font, s, color, width)?;
+                    let w = width as i32;
+                    layers.push(Layer { tex: tex, dx: -w, dy: -w });
+                }
+                let tex = text.render(renderer, font, s, style.color, 0)?;
+                layers.push(Layer { tex: tex, dx: 0, dy: 0 });
+            }
+            self.lines.push(Line {
+                x: 0,
+                y: line_height * i as i32,
+                layers: layers,
+            });
+        }
+        self.align_lines();
+        Ok(())
+    }
+
+    // align_lines places the lines across the label and sizes its box.
+    fn align_lines(&mut self) {
+        let widths: Vec<u32> = self.lines
+                                   .iter()
+                                   .map(|l| l.layers.last().map_or(0, |t| t.tex.borrow().get_size().0))
+                                   .collect();
+        let w = self.max_width.unwrap_or(widths.iter().cloned().max().unwrap_or(0));
+        for (line, lw) in self.lines.iter_mut().zip(widths) {
+            line.x = match self.align {
+                Align::Left => 0,
+                Align::Center => (w as i32 - lw as i32) / 2,
+                Align::Right => w as i32 - lw as i32,
+            };
+        }
+        let h = match self.lines.last() {
+            Some(line) => {
+                let last = line.layers.last().map_or(0, |t| t.tex.borrow().get_size().1);
+                line.y + last as i32
+            }
+            None => 0,
+        };
+        self.rect.set_width(w.max(1));
+        self.rect.set_height((h as u32).max(1));
+    }
+}
+
+impl Widget for Label {
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+    fn set_position(&mut self, x: i32, y: i32) {
+        self.rect.set_x(x);
+        self.rect.set_y(y);
+    }
+    fn get_visible(&self) -> bool {
+        self.visible
+    }
+}
+
+impl Displayable for Label {
+    fn update(&mut self) {}
+
+    fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        if !self.visible {
+            return;
+        }
+        for line in &self.lines {
+            for layer in &line.layers {
+                let local = Transform::new(self.rect.x() + line.x + layer.dx,
+                                           self.rect.y() + line.y + layer.dy);
+                layer.tex.borrow().paint_tinted(renderer, &parent.then(&local), &self.tint);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Every character is 10 pixels wide.
+    fn width(s: &str) -> u32 {
+        s.chars().count() as u32 * 10
+    }
+
+    #[test]
+    fn test_wrap_unlimited() {
+        assert_eq!(wrap("Quit to title", None, width), vec!["Quit to title"]);
+    }
+
+    #[test]
+    fn test_wrap_at_spaces() {
+        assert_eq!(wrap("Quit to title", Some(70), width), vec!["Quit to", "title"]);
+    }
+
+    #[test]
+    fn test_wrap_long_word() {
+        assert_eq!(wrap("a congratulations b", Some(50), width),
+                   vec!["a", "congratulations", "b"]);
+    }
+
+    #[test]
+    fn test_wrap_cjk() {
+        assert_eq!(wrap("返回标题", Some(30), width), vec!["返回标", "题"]);
+        assert_eq!(wrap("Ghost 幽灵", Some(70), width), vec!["Ghost 幽", "灵"]);
+    }
+
+    #[test]
+    fn test_wrap_trims_line_ends() {
+        assert_eq!(wrap("ab  cd", Some(30), width), vec!["ab", "cd"]);
+        assert_eq!(wrap("Quit to ", Some(100), width), vec!["Quit to"]);
+    }
+
+    #[test]
+    fn test_prune_unused() {
+        let mut cache = HashMap::new();
+        let shown = Rc::new(1);
+        cache.insert("shown", shown.clone());
+        cache.insert("gone", Rc::new(2));
+        prune_unused(&mut cache);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains_key("shown"));
+    }
+
+    #[test]
+    fn test_wrap_newlines() {
+        assert_eq!(wrap("Game\n\nOver", Some(100), width), vec!["Game", "", "Over"]);
+    }
+}
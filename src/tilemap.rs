@@ -0,0 +1,910 @@
+extern crate sdl2;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::rc::Rc;
+
+use sdl2::rect::Rect;
+use sdl2::render::Renderer;
+
+use serde_json::{self, Value};
+use xml::reader::{EventReader, XmlEvent};
+use xml::attribute::OwnedAttribute;
+
+use atlas::{TexElement, Tint};
+use display::Displayable;
+use transform::Transform;
+
+// Tiled keeps the flip flags in the top bits of a gid.
+const FLIPPED_H: u32 = 0x80000000;
+const FLIPPED_V: u32 = 0x40000000;
+const FLIPPED_D: u32 = 0x20000000;
+const GID_MASK: u32 = !(FLIPPED_H | FLIPPED_V | FLIPPED_D);
+
+// Tileset is a range of gids, either cut from a grid image or, for a tile
+// carrying an `atlas` property, taken from the game atlas by name.
+#[derive(Debug, Clone)]
+pub struct Tileset {
+    pub name: String,
+    pub first_gid: u32,
+    pub tile_w: u32,
+    pub tile_h: u32,
+    pub spacing: u32,
+    pub margin: u32,
+    pub count: u32,
+    pub columns: u32,
+    pub image: Option<String>,
+    pub atlas: HashMap<u32, String>,
+}
+
+impl Tileset {
+    fn new() -> Tileset {
+        Tileset {
+            name: String::new(),
+            first_gid: 1,
+            tile_w: 0,
+            tile_h: 0,
+            spacing: 0,
+            margin: 0,
+            count: 0,
+            columns: 0,
+            image: None,
+            atlas: HashMap::new(),
+        }
+    }
+
+    // tile_rect is where the local tile `id` sits in the tileset image.
+    pub fn tile_rect(&self, id: u32) -> Rect {
+        let columns = self.columns.max(1);
+        let (col, row) = (id % columns, id / columns);
+        Rect::new((self.margin + col * (self.tile_w + self.spacing)) as i32,
+                  (self.margin + row * (self.tile_h + self.spacing)) as i32,
+                  self.tile_w,
+                  self.tile_h)
+    }
+}
+
+// TileLayer is a grid of gids, row by row, 0 being an empty cell.
+#[derive(Debug, Clone)]
+pub struct TileLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub visible: bool,
+    pub opacity: f32,
+    pub data: Vec<u32>,
+}
+
+impl TileLayer {
+    fn new() -> TileLayer {
+        TileLayer {
+            name: String::new(),
+            width: 0,
+            height: 0,
+            visible: true,
+            opacity: 1.0,
+            data: Vec::new(),
+        }
+    }
+
+    // get_gid is the tile at (col, row) without its flip flags, 0 if empty.
+    pub fn get_gid(&self, col: u32, row: u32) -> u32 {
+        if col >= self.width || row >= self.height {
+            return 0;
+        }
+        self.data.get((row * self.width + col) as usize).map_or(0, |g| g & GID_MASK)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Rect,
+    Ellipse,
+    Point,
+    Polygon(Vec<(f32, f32)>),
+    Polyline(Vec<(f32, f32)>),
+}
+
+// MapObject is a designer placed object, e.g. a spawn point or a collision
+// shape. Polygon points are relative to (x, y).
+#[derive(Debug, Clone)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    pub kind: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub shape: Shape,
+    pub properties: HashMap<String, String>,
+}
+
+impl MapObject {
+    fn new() -> MapObject {
+        MapObject {
+            id: 0,
+            name: String::new(),
+            kind: String::new(),
+            x: 0.0,
+            y: 0.0,
+            width: 0.0,
+            height: 0.0,
+            shape: Shape::Rect,
+            properties: HashMap::new(),
+        }
+    }
+
+    pub fn get_property(&self, name: &str) -> Option<&str> {
+        self.properties.get(name).map(|v| v.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub objects: Vec<MapObject>,
+}
+
+// MapData is a parsed map, independent of any renderer.
+#[derive(Debug, Clone)]
+pub struct MapData {
+    pub width: u32,
+    pub height: u32,
+    pub tile_w: u32,
+    pub tile_h: u32,
+    pub tilesets: Vec<Tileset>,
+    pub layers: Vec<TileLayer>,
+    pub objects: Vec<ObjectLayer>,
+}
+
+impl MapData {
+    fn new() -> MapData {
+        MapData {
+            width: 0,
+            height: 0,
+            tile_w: 0,
+            tile_h: 0,
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+            objects: Vec::new(),
+        }
+    }
+
+    // load reads a Tiled map, JSON when the extension says so and TMX otherwise.
+    pub fn load(path: &str) -> Result<MapData, String> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| format!("{}: {}", path, e))?;
+        if path.ends_with(".json") {
+            parse_json(&text)
+        } else {
+            parse_tmx(&text)
+        }
+    }
+}
+
+fn attr<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes.iter().find(|a| a.name.local_name == name).map(|a| a.value.as_str())
+}
+
+fn attr_u32(attributes: &[OwnedAttribute], name: &str) -> Result<u32, String> {
+    match attr(attributes, name) {
+        Some(v) => v.parse::<u32>().map_err(|_| format!("bad {} '{}'", name, v)),
+        None => Ok(0),
+    }
+}
+
+fn attr_f32(attributes: &[OwnedAttribute], name: &str) -> Result<f32, String> {
+    match attr(attributes, name) {
+        Some(v) => v.parse::<f32>().map_err(|_| format!("bad {} '{}'", name, v)),
+        None => Ok(0.0),
+    }
+}
+
+fn parse_points(points: &str) -> Result<Vec<(f32, f32)>, String> {
+    points.split_whitespace()
+          .map(|p| {
+              let mut xy = p.split(',').map(|v| v.parse::<f32>());
+              match (xy.next(), xy.next()) {
+                  (Some(Ok(x)), Some(Ok(y))) => Ok((x, y)),
+                  _ => Err(format!("bad point '{}'", p)),
+              }
+          })
+          .collect()
+}
+
+fn parse_csv(text: &str) -> Result<Vec<u32>, String> {
+    text.split(',')
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse::<u32>().map_err(|_| format!("bad gid '{}'", v)))
+        .collect()
+}
+
+// decode_base64 decodes standard base64, ignoring whitespace.
+fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for c in text.bytes().filter(|c| !(*c as char).is_whitespace()) {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return Err(format!("bad base64 character '{}'", c as char)),
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+// parse_base64 reads little endian gids, Tiled's base64 layout.
+fn parse_base64(text: &str) -> Result<Vec<u32>, String> {
+    let bytes = decode_base64(text)?;
+    Ok(bytes.chunks(4)
+            .filter(|b| b.len() == 4)
+            .map(|b| b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+            .collect())
+}
+
+fn parse_data(encoding: &str, compression: &str, text: &str) -> Result<Vec<u32>, String> {
+    if !compression.is_empty() {
+        return Err(format!("{} compressed layers are not supported, save as CSV", compression));
+    }
+    match encoding {
+        "csv" => parse_csv(text),
+        "base64" => parse_base64(text),
+        _ => Err(format!("unknown layer encoding '{}'", encoding)),
+    }
+}
+
+// parse_tmx parses a map saved by Tiled as XML, with embedded tilesets.
+pub fn parse_tmx(text: &str) -> Result<MapData, String> {
+    let mut map = MapData::new();
+    let mut tileset: Option<Tileset> = None;
+    let mut tile_id: Option<u32> = None;
+    let mut layer: Option<TileLayer> = None;
+    let mut encoding: Option<(String, String)> = None;
+    let mut group: Option<ObjectLayer> = None;
+    let mut object: Option<MapObject> = None;
+
+    for event in EventReader::new(text.as_bytes()) {
+        match event.map_err(|e| e.to_string())? {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                let a = &attributes[..];
+                match name.local_name.as_str() {
+                    "map" => {
+                        map.width = attr_u32(a, "width")?;
+                        map.height = attr_u32(a, "height")?;
+                        map.tile_w = attr_u32(a, "tilewidth")?;
+                        map.tile_h = attr_u32(a, "tileheight")?;
+                    }
+                    "tileset" => {
+                        if let Some(source) = attr(a, "source") {
+                            return Err(format!("external tileset '{}' is not supported, embed it",
+                                               source));
+                        }
+                        let mut t = Tileset::new();
+                        t.name = attr(a, "name").unwrap_or("").to_string();
+                        t.first_gid = attr_u32(a, "firstgid")?;
+                        t.tile_w = attr_u32(a, "tilewidth")?;
+                        t.tile_h = attr_u32(a, "tileheight")?;
+                        t.spacing = attr_u32(a, "spacing")?;
+                        t.margin = attr_u32(a, "margin")?;
+                        t.count = attr_u32(a, "tilecount")?;
+                        t.columns = attr_u32(a, "columns")?;
+                        tileset = Some(t);
+                    }
+                    "image" => {
+                        if let Some(ref mut t) = tileset {
+                            if tile_id.is_none() {
+                                t.image = attr(a, "source").map(|s| s.to_string());
+                            }
+                        }
+                    }
+                    "tile" => {
+                        if let Some(ref mut l) = layer {
+                            l.data.push(attr_u32(a, "gid")?);
+                        } else if tileset.is_some() {
+                            tile_id = Some(attr_u32(a, "id")?);
+                        }
+                    }
+                    "property" => {
+                        let key = attr(a, "name").unwrap_or("").to_string();
+                        let value = attr(a, "value").unwrap_or("").to_string();
+                        if let Some(ref mut o) = object {
+                            o.properties.insert(key, value);
+                        } else if let (Some(t), Some(id)) = (tileset.as_mut(), tile_id) {
+                            if key == "atlas" {
+                                t.atlas.insert(id, value);
+                            }
+                        }
+                    }
+                    "layer" => {
+                        let mut l = TileLayer::new();
+                        l.name = attr(a, "name").unwrap_or("").to_string();
+                        l.width = attr_u32(a, "width")?;
+                        l.height = attr_u32(a, "height")?;
+                        l.visible = attr(a, "visible") != Some("0");
+                        l.opacity = attr(a, "opacity").map_or(Ok(1.0), |_| attr_f32(a, "opacity"))?;
+                        layer = Some(l);
+                    }
+                    "data" => {
+                        if let Some(e) = attr(a, "encoding") {
+                            encoding = Some((e.to_string(),
+                                             attr(a, "compression").unwrap_or("").to_string()));
+                        }
+                    }
+                    "objectgroup" => {
+                        group = Some(ObjectLayer {
+                            name: attr(a, "name").unwrap_or("").to_string(),
+                            objects: Vec::new(),
+                        });
+                    }
+                    "object" => {
+                        let mut o = MapObject::new();
+                        o.id = attr_u32(a, "id")?;
+                        o.name = attr(a, "name").unwrap_or("").to_string();
+                        o.kind = attr(a, "type").unwrap_or("").to_string();
+                        o.x = attr_f32(a, "x")?;
+                        o.y = attr_f32(a, "y")?;
+                        o.width = attr_f32(a, "width")?;
+                        o.height = attr_f32(a, "height")?;
+                        object = Some(o);
+                    }
+                    "ellipse" | "point" | "polygon" | "polyline" => {
+                        if let Some(ref mut o) = object {
+                            let points = attr(a, "points").unwrap_or("");
+                            o.shape = match name.local_name.as_str() {
+                                "ellipse" => Shape::Ellipse,
+                                "point" => Shape::Point,
+                                "polygon" => Shape::Polygon(parse_points(points)?),
+                                _ => Shape::Polyline(parse_points(points)?),
+                            };
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::Characters(text) => {
+                if let (Some(l), Some(&(ref e, ref c))) = (layer.as_mut(), encoding.as_ref()) {
+                    let mut data = parse_data(e, c, &text)?;
+                    l.data.append(&mut data);
+                }
+            }
+            XmlEvent::EndElement { name } => {
+                match name.local_name.as_str() {
+                    "tileset" => {
+                        if let Some(t) = tileset.take() {
+                            map.tilesets.push(t);
+                        }
+                    }
+                    "tile" => tile_id = None,
+                    "data" => encoding = None,
+                    "layer" => {
+                        if let Some(l) = layer.take() {
+                            if l.data.len() != (l.width * l.height) as usize {
+                                return Err(format!("layer '{}' has {} tiles, expected {}",
+                                                   l.name,
+                                                   l.data.len(),
+                                                   l.width * l.height));
+                            }
+                            map.layers.push(l);
+                        }
+                    }
+                    "object" => {
+                        if let (Some(o), Some(g)) = (object.take(), group.as_mut()) {
+                            g.objects.push(o);
+                        }
+                    }
+                    "objectgroup" => {
+                        if let Some(g) = group.take() {
+                            map.objects.push(g);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(map)
+}
+
+fn json_u32(v: &Value, key: &str) -> u32 {
+    v[key].as_u64().unwrap_or(0) as u32
+}
+
+fn json_f32(v: &Value, key: &str) -> f32 {
+    v[key].as_f64().unwrap_or(0.0) as f32
+}
+
+fn json_str(v: &Value, key: &str) -> String {
+    v[key].as_str().unwrap_or("").to_string()
+}
+
+// json_properties reads both the old object form and the newer list form of
+// Tiled's custom properties, stringifying the values.
+fn json_properties(v: &Value) -> HashMap<String, String> {
+    let text = |v: &Value| match *v {
+        Value::String(ref s) => s.clone(),
+        ref other => other.to_string(),
+    };
+    let mut props = HashMap::new();
+    match v["properties"] {
+        Value::Object(ref m) => {
+            for (k, v) in m {
+                props.insert(k.clone(), text(v));
+            }
+        }
+        Value::Array(ref list) => {
+            for p in list {
+                props.insert(json_str(p, "name"), text(&p["value"]));
+            }
+        }
+        _ => {}
+    }
+    props
+}
+
+fn json_points(v: &Value) -> Vec<(f32, f32)> {
+    v.as_array()
+     .map_or(Vec::new(), |ps| ps.iter().map(|p| (json_f32(p, "x"), json_f32(p, "y"))).collect())
+}
+
+// parse_json parses a map saved by Tiled as JSON, with embedded tilesets.
+pub fn parse_json(text: &str) -> Result<MapData, String> {
+    let root: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let mut map = MapData::new();
+    map.width = json_u32(&root, "width");
+    map.height = json_u32(&root, "height");
+    map.tile_w = json_u32(&root, "tilewidth");
+    map.tile_h = json_u32(&root, "tileheight");
+
+    for ts in root["tilesets"].as_array().unwrap_or(&Vec::new()) {
+        if let Some(source) = ts["source"].as_str() {
+            return Err(format!("external tileset '{}' is not supported, embed it", source));
+        }
+        let mut t = Tileset::new();
+        t.name = json_str(ts, "name");
+        t.first_gid = json_u32(ts, "firstgid");
+        t.tile_w = json_u32(ts, "tilewidth");
+        t.tile_h = json_u32(ts, "tileheight");
+        t.spacing = json_u32(ts, "spacing");
+        t.margin = json_u32(ts, "margin");
+        t.count = json_u32(ts, "tilecount");
+        t.columns = json_u32(ts, "columns");
+        t.image = ts["image"].as_str().map(|s| s.to_string());
+        match ts["tiles"] {
+            // Tiled 1.2 and later: a list of tiles with an id each.
+            Value::Array(ref tiles) => {
+                for tile in tiles {
+                    if let Some(name) = json_properties(tile).remove("atlas") {
+                        t.atlas.insert(json_u32(tile, "id"), name);
+                    }
+                }
+            }
+            // Older versions: an object keyed by id.
+            Value::Object(ref tiles) => {
+                for (id, tile) in tiles {
+                    if let (Ok(id), Some(name)) = (id.parse::<u32>(),
+                                                   json_properties(tile).remove("atlas")) {
+                        t.atlas.insert(id, name);
+                    }
+                }
+            }
+            _ => {}
+        }
+        map.tilesets.push(t);
+    }
+
+    for l in root["layers"].as_array().unwrap_or(&Vec::new()) {
+        match l["type"].as_str() {
+            Some("tilelayer") => {
+                let mut layer = TileLayer::new();
+                layer.name = json_str(l, "name");
+                layer.width = json_u32(l, "width");
+                layer.height = json_u32(l, "height");
+                layer.visible = l["visible"].as_bool().unwrap_or(true);
+                layer.opacity = l["opacity"].as_f64().unwrap_or(1.0) as f32;
+                layer.data = match l["data"] {
+                    Value::Array(ref gids) => gids.iter().map(|g| g.as_u64().unwrap_or(0) as u32).collect(),
+                    Value::String(ref s) => {
+                        parse_data(l["encoding"].as_str().unwrap_or("base64"),
+                                   l["compression"].as_str().unwrap_or(""),
+                                   s)?
+                    }
+                    _ => Vec::new(),
+                };
+                if layer.data.len() != (layer.width * layer.height) as usize {
+                    return Err(format!("layer '{}' has {} tiles, expected {}",
+                                       layer.name,
+                                       layer.data.len(),
+                                       layer.width * layer.height));
+                }
+                map.layers.push(layer);
+            }
+            Some("objectgroup") => {
+                let mut group = ObjectLayer {
+                    name: json_str(l, "name"),
+                    objects: Vec::new(),
+                };
+                for o in l["objects"].as_array().unwrap_or(&Vec::new()) {
+                    let mut object = MapObject::new();
+                    object.id = json_u32(o, "id");
+                    object.name = json_str(o, "name");
+                    object.kind = json_str(o, "type");
+                    object.x = json_f32(o, "x");
+                    object.y = json_f32(o, "y");
+                    object.width = json_f32(o, "width");
+                    object.height = json_f32(o, "height");
+                    object.properties = json_properties(o);
+                    object.shape = if o["ellipse"].as_bool().unwrap_or(false) {
+                        Shape::Ellipse
+                    } else if o["point"].as_bool().unwrap_or(false) {
+                        Shape::Point
+                    } else if o["polygon"].is_array() {
+                        Shape::Polygon(json_points(&o["polygon"]))
+                    } else if o["polyline"].is_array() {
+                        Shape::Polyline(json_points(&o["polyline"]))
+                    } else {
+                        Shape::Rect
+                    };
+                    group.objects.push(object);
+                }
+                map.objects.push(group);
+            }
+            _ => {}
+        }
+    }
+    Ok(map)
+}
+
+// visible_range is the [start, end) of the `count` cells of `cell` pixels laid
+// out from `origin` that overlap a `view` pixels long screen.
+fn visible_range(origin: f32, cell: f32, view: u32, count: u32) -> (u32, u32) {
+    if cell <= 0.0 {
+        return (0, 0);
+    }
+    let start = (-origin / cell).floor().max(0.0) as u32;
+    let end = ((view as f32 - origin) / cell).ceil().max(0.0) as u32;
+    (start.min(count), end.min(count))
+}
+
+// overhang is how many cells past its own a tile `size` pixels long reaches
+// on a grid of `cell` pixels.
+fn overhang(size: u32, cell: u32) -> u32 {
+    if cell == 0 || size <= cell {
+        return 0;
+    }
+    (size + cell - 1) / cell - 1
+}
+
+// TileMap paints the tile layers of a Tiled map and exposes its object
+// layers to the game. Only the tiles on screen are drawn, so maps can be
+// much larger than the view; rotating the map disables the culling.
+pub struct TileMap {
+    visible: bool,
+    transform: Transform,
+    parallax: f32,
+    view: (u32, u32),
+    data: MapData,
+    tiles: HashMap<u32, Rc<RefCell<TexElement>>>,
+    // Cells the largest tiles stick out of their own, right and up.
+    overhang: (u32, u32),
+}
+
+impl TileMap {
+    // load reads the map at `path`, cutting grid tilesets from their images,
+    // found next to the map, and taking `atlas` tiles from `atlas`.
+    pub fn load(renderer: &Renderer,
+                path: &str,
+                atlas: &HashMap<String, Rc<RefCell<TexElement>>>,
+                w: u32,
+                h: u32)
+                -> Result<TileMap, String> {
+        let data = MapData::load(path)?;
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        let mut tiles = HashMap::new();
+
+        for t in &data.tilesets {
+            if let Some(ref image) = t.image {
+                let file = dir.join(image);
+                let texture = TexElement::load_texture(renderer, &file.to_string_lossy())?;
+                let texture = Rc::new(RefCell::new(texture));
+                for id in 0..t.count {
+                    let tex = TexElement::new_from_texture(texture.clone(), t.tile_rect(id));
+                    tiles.insert(t.first_gid + id, Rc::new(RefCell::new(tex)));
+                }
+            }
+            for (id, name) in &t.atlas {
+                match atlas.get(name) {
+                    Some(tex) => {
+                        tiles.insert(t.first_gid + id, tex.clone());
+                    }
+                    None => return Err(format!("{}: no atlas element '{}'", path, name)),
+                }
+            }
+        }
+
+        let (mut max_w, mut max_h) = (0, 0);
+        for tex in tiles.values() {
+            let (tw, th) = tex.borrow().get_size();
+            max_w = max_w.max(tw);
+            max_h = max_h.max(th);
+        }
+        let overhang = (overhang(max_w, data.tile_w), overhang(max_h, data.tile_h));
+
+        Ok(TileMap {
+            visible: true,
+            transform: Transform::identity(),
+            parallax: 1.0,
+            view: (w, h),
+            data: data,
+            tiles: tiles,
+            overhang: overhang,
+        })
+    }
+
+    pub fn get_data(&self) -> &MapData {
+        &self.data
+    }
+
+    // get_size is the size of the map in pixels.
+    pub fn get_size(&self) -> (u32, u32) {
+        (self.data.width * self.data.tile_w, self.data.height * self.data.tile_h)
+    }
+
+    pub fn get_layer(&self, name: &str) -> Option<&TileLayer> {
+        self.data.layers.iter().find(|l| l.name == name)
+    }
+
+    pub fn set_layer_visible(&mut self, name: &str, visible: bool) {
+        for l in self.data.layers.iter_mut().filter(|l| l.name == name) {
+            l.visible = visible;
+        }
+    }
+
+    pub fn get_object_layer(&self, name: &str) -> Option<&ObjectLayer> {
+        self.data.objects.iter().find(|l| l.name == name)
+    }
+
+    // get_objects returns the objects of the given type across all object
+    // layers, e.g. "spawn" or "solid".
+    pub fn get_objects(&self, kind: &str) -> Vec<&MapObject> {
+        self.data
+            .objects
+            .iter()
+            .flat_map(|l| l.objects.iter())
+            .filter(|o| o.kind == kind)
+            .collect()
+    }
+
+    pub fn get_object(&self, name: &str) -> Option<&MapObject> {
+        self.data.objects.iter().flat_map(|l| l.objects.iter()).find(|o| o.name == name)
+    }
+
+    // tile_at is the cell under a point in map pixels.
+    pub fn tile_at(&self, x: f32, y: f32) -> Option<(u32, u32)> {
+        let (w, h) = self.get_size();
+        if x < 0.0 || y < 0.0 || x >= w as f32 || y >= h as f32 {
+            return None;
+        }
+        Some((x as u32 / self.data.tile_w, y as u32 / self.data.tile_h))
+    }
+
+    pub fn set_view(&mut self, w: u32, h: u32) {
+        self.view = (w, h);
+    }
+
+    pub fn set_parallax(&mut self, factor: f32) {
+        self.parallax = factor;
+    }
+
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        self.transform.x = x as f32;
+        self.transform.y = y as f32;
+    }
+
+    pub fn get_position(&self) -> (i32, i32) {
+        (self.transform.x as i32, self.transform.y as i32)
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    pub fn get_transform(&self) -> Transform {
+        self.transform
+    }
+
+    pub fn hide<'a>(&'a mut self) -> &'a mut TileMap {
+        self.visible = false;
+        self
+    }
+
+    pub fn show<'a>(&'a mut self) -> &'a mut TileMap {
+        self.visible = true;
+        self
+    }
+
+    pub fn get_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn paint_layer(&self, renderer: &mut Renderer, world: &Transform, layer: &TileLayer) {
+        let (tw, th) = (self.data.tile_w, self.data.tile_h);
+        let (cols, rows) = if world.rotation == 0.0 {
+            // Tiles larger than the grid stick out of their cell to the
+            // right and up, so cells left of and below the view may still
+            // reach into it.
+            let (c0, c1) = visible_range(world.x, tw as f32 * world.scale_x.abs(), self.view.0, layer.width);
+            let (r0, r1) = visible_range(world.y, th as f32 * world.scale_y.abs(), self.view.1, layer.height);
+            let (ox, oy) = self.overhang;
+            ((c0.saturating_sub(ox), c1), (r0, (r1 + oy).min(layer.height)))
+        } else {
+            ((0, layer.width), (0, layer.height))
+        };
+        let tint = Tint::rgba(255, 255, 255, (layer.opacity * 255.0) as u8);
+
+        for row in rows.0..rows.1 {
+            for col in cols.0..cols.1 {
+                let raw = layer.data[(row * layer.width + col) as usize];
+                let tex = match self.tiles.get(&(raw & GID_MASK)) {
+                    Some(tex) => tex.borrow(),
+                    None => continue,
+                };
+                // Tiled aligns tiles to the bottom left of their cell.
+                let (_, h) = tex.get_size();
+                let mut local = Transform::new((col * tw) as i32, (row * th) as i32 + th as i32 - h as i32);
+                if raw & FLIPPED_H != 0 {
+                    local.scale_x = -1.0;
+                }
+                if raw & FLIPPED_V != 0 {
+                    local.scale_y = -1.0;
+                }
+                tex.paint_tinted(renderer, &world.then(&local), &tint);
+            }
+        }
+    }
+}
+
+impl Displayable for TileMap {
+    fn get_parallax(&self) -> f32 {
+        self.parallax
+    }
+
+    fn on_resize(&mut self, w: u32, h: u32) {
+        self.set_view(w, h);
+    }
+
+    fn update(&mut self) {}
+
+    fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        if self.visible {
+            let world = parent.then(&self.transform);
+            for layer in self.data.layers.iter().filter(|l| l.visible) {
+                self.paint_layer(renderer, &world, layer);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TMX: &'static str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map width="2" height="2" tilewidth="40" tileheight="40">
+ <tileset firstgid="1" name="pipes" tilewidth="52" tileheight="320" tilecount="2" columns="0">
+  <tile id="1"><properties><property name="atlas" value="pipe_up"/></properties></tile>
+ </tileset>
+ <layer name="csv" width="2" height="2">
+  <data encoding="csv">1,0,
+0,2</data>
+ </layer>
+ <layer name="base64" width="2" height="2" visible="0" opacity="0.5">
+  <data encoding="base64">AQAAAAAAAAACAACAAAAAAA==</data>
+ </layer>
+ <objectgroup name="course">
+  <object id="1" name="spawn" type="spawn" x="120" y="300"><point/></object>
+  <object id="2" type="solid" x="10" y="20">
+   <properties><property name="kind" value="pipe"/></properties>
+   <polygon points="0,0 5,0 5,8"/>
+  </object>
+ </objectgroup>
+</map>"#;
+
+    const JSON: &'static str = r#"{
+ "width": 2, "height": 2, "tilewidth": 40, "tileheight": 40,
+ "tilesets": [{ "firstgid": 1, "name": "pipes", "tilewidth": 52, "tileheight": 320,
+                "tilecount": 2, "columns": 0,
+                "tiles": [{ "id": 1, "properties": [{ "name": "atlas", "value": "pipe_up" }] }] }],
+ "layers": [
+  { "type": "tilelayer", "name": "csv", "width": 2, "height": 2, "data": [1, 0, 0, 2] },
+  { "type": "tilelayer", "name": "base64", "width": 2, "height": 2, "visible": false,
+    "opacity": 0.5, "encoding": "base64", "data": "AQAAAAAAAAACAACAAAAAAA==" },
+  { "type": "objectgroup", "name": "course", "objects": [
+    { "id": 1, "name": "spawn", "type": "spawn", "x": 120, "y": 300, "point": true },
+    { "id": 2, "name": "", "type": "solid", "x": 10, "y": 20, "properties": { "kind": "pipe" },
+      "polygon": [{ "x": 0, "y": 0 }, { "x": 5, "y": 0 }, { "x": 5, "y": 8 }] } ] } ]
+}"#;
+
+    fn check(map: &MapData) {
+        assert_eq!((map.width, map.height, map.tile_w, map.tile_h), (2, 2, 40, 40));
+        let t = &map.tilesets[0];
+        assert_eq!((t.first_gid, t.tile_w, t.tile_h, t.count), (1, 52, 320, 2));
+        assert_eq!(t.atlas.get(&1).map(|s| s.as_str()), Some("pipe_up"));
+
+        assert_eq!(map.layers[0].data, vec![1, 0, 0, 2]);
+        let b = &map.layers[1];
+        assert!(!b.visible);
+        assert_eq!(b.opacity, 0.5);
+        assert_eq!(b.data, vec![1, 0, 2 | FLIPPED_H, 0]);
+        assert_eq!(b.get_gid(0, 1), 2);
+
+        let objects = &map.objects[0].objects;
+        assert_eq!(objects[0].shape, Shape::Point);
+        assert_eq!((objects[0].x, objects[0].y), (120.0, 300.0));
+        assert_eq!(objects[1].kind, "solid");
+        assert_eq!(objects[1].get_property("kind"), Some("pipe"));
+        assert_eq!(objects[1].shape, Shape::Polygon(vec![(0.0, 0.0), (5.0, 0.0), (5.0, 8.0)]));
+    }
+
+    #[test]
+    fn test_parse_tmx() {
+        check(&parse_tmx(TMX).unwrap());
+    }
+
+    #[test]
+    fn test_parse_json() {
+        check(&parse_json(JSON).unwrap());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_tmx(&TMX.replace("1,0,\n0,2", "1,0,0")).is_err());
+        assert!(parse_tmx(&TMX.replace("encoding=\"csv\"", "encoding=\"csv\" compression=\"zlib\"")).is_err());
+        assert!(parse_tmx(&TMX.replace("name=\"pipes\"", "source=\"pipes.tsx\"")).is_err());
+        assert!(parse_tmx(&TMX.replace("1,0,", "1,x,")).is_err());
+        assert!(parse_json(&JSON.replace("[1, 0, 0, 2]", "[1, 0]")).is_err());
+        assert!(parse_json("{").is_err());
+    }
+
+    #[test]
+    fn test_parse_course() {
+        let map = parse_tmx(include_str!("../res/maps/course_01.tmx")).unwrap();
+        assert_eq!((map.width, map.height), (40, 20));
+        assert!(map.objects.iter().flat_map(|l| l.objects.iter()).any(|o| o.kind == "spawn"));
+    }
+
+    #[test]
+    fn test_culling_covers_oversized_tiles() {
+        assert_eq!(overhang(40, 40), 0);
+        assert_eq!(overhang(52, 40), 1);
+        assert_eq!(overhang(320, 40), 7);
+        // A 600 pixel view shows rows 0 to 14 of a 40 pixel grid, the 320
+        // pixel pipes standing in rows 15 to 19 still reach into it.
+        let (r0, r1) = visible_range(0.0, 40.0, 600, 20);
+        assert_eq!((r0, r1), (0, 15));
+        assert_eq!((r1 + overhang(320, 40)).min(20), 20);
+        assert_eq!(visible_range(-100.0, 40.0, 800, 40), (2, 23));
+    }
+}
@@ -0,0 +1,152 @@
+use sdl2::rect::{Point, Rect};
+
+// Transform is the local placement of a node relative to its parent.
+// Rotation is in degrees, clockwise like `Renderer::copy_ex`, and the anchor
+// is the pivot inside the node as a fraction of its size: (0, 0) is the
+// top-left corner, (0.5, 0.5) the middle.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub anchor_x: f32,
+    pub anchor_y: f32,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            x: 0.0,
+            y: 0.0,
+            rotation: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            anchor_x: 0.0,
+            anchor_y: 0.0,
+        }
+    }
+
+    pub fn new(x: i32, y: i32) -> Transform {
+        let mut t = Transform::identity();
+        t.x = x as f32;
+        t.y = y as f32;
+        t
+    }
+
+    // apply maps a point in this node's space to its parent's space.
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let (sx, sy) = (x * self.scale_x, y * self.scale_y);
+        if self.rotation == 0.0 {
+            return (self.x + sx, self.y + sy);
+        }
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        (self.x + sx * cos - sy * sin, self.y + sx * sin + sy * cos)
+    }
+
+    // then composes a child's local transform below this world transform,
+    // giving the child's world transform.
+    pub fn then(&self, local: &Transform) -> Transform {
+        let (x, y) = self.apply(local.x, local.y);
+        Transform {
+            x: x,
+            y: y,
+            rotation: self.rotation + local.rotation,
+            scale_x: self.scale_x * local.scale_x,
+            scale_y: self.scale_y * local.scale_y,
+            anchor_x: local.anchor_x,
+            anchor_y: local.anchor_y,
+        }
+    }
+
+    // dest_rect resolves the screen rect of a `w`x`h` node placed with this
+    // world transform, and the pivot to rotate it around relative to the rect.
+    pub fn dest_rect(&self, w: u32, h: u32) -> (Rect, Point) {
+        let sw = w as f32 * self.scale_x.abs();
+        let sh = h as f32 * self.scale_y.abs();
+        let px = self.anchor_x * sw;
+        let py = self.anchor_y * sh;
+        (Rect::new((self.x - px).round() as i32,
+                   (self.y - py).round() as i32,
+                   sw.round() as u32,
+                   sh.round() as u32),
+         Point::new(px.round() as i32, py.round() as i32))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < EPSILON && (a.1 - b.1).abs() < EPSILON
+    }
+
+    #[test]
+    fn test_then_translates() {
+        let world = Transform::new(10, 20).then(&Transform::new(5, -3));
+        assert_eq!((world.x, world.y), (15.0, 17.0));
+        let grandchild = world.then(&Transform::new(1, 1));
+        assert_eq!((grandchild.x, grandchild.y), (16.0, 18.0));
+    }
+
+    #[test]
+    fn test_then_scales_and_rotates() {
+        let mut parent = Transform::new(100, 50);
+        parent.scale_x = 2.0;
+        parent.scale_y = 2.0;
+        parent.rotation = 90.0;
+        let mut local = Transform::new(10, 0);
+        local.rotation = 15.0;
+        local.scale_x = 0.5;
+        local.anchor_x = 0.5;
+        let world = parent.then(&local);
+        // Clockwise, so the child's right is the parent's down.
+        assert!(close((world.x, world.y), (100.0, 70.0)));
+        assert_eq!(world.rotation, 105.0);
+        assert_eq!((world.scale_x, world.scale_y), (1.0, 2.0));
+        assert_eq!((world.anchor_x, world.anchor_y), (0.5, 0.0));
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut t = Transform::new(4, 4);
+        assert_eq!(t.apply(1.0, 2.0), (5.0, 6.0));
+        t.rotation = 180.0;
+        assert!(close(t.apply(1.0, 2.0), (3.0, 2.0)));
+    }
+
+    #[test]
+    fn test_dest_rect_about_anchor() {
+        let mut t = Transform::new(100, 100);
+        t.scale_x = 2.0;
+        t.scale_y = 2.0;
+        t.anchor_x = 0.5;
+        t.anchor_y = 0.5;
+        let rect = (Rect::new(90, 94, 20, 12), Point::new(10, 6));
+        assert_eq!(t.dest_rect(10, 6), rect);
+        // Rotation turns the rect around the pivot, it doesn't move it.
+        t.rotation = 45.0;
+        assert_eq!(t.dest_rect(10, 6), rect);
+        // A flipped node keeps its size.
+        t.rotation = 0.0;
+        t.scale_x = -2.0;
+        assert_eq!(t.dest_rect(10, 6), rect);
+    }
+
+    #[test]
+    fn test_dest_rect_rounds() {
+        let mut t = Transform::identity();
+        t.x = 10.4;
+        t.y = 10.6;
+        t.scale_x = 1.5;
+        t.scale_y = 1.5;
+        assert_eq!(t.dest_rect(3, 3), (Rect::new(10, 11, 5, 5), Point::new(0, 0)));
+        t.anchor_x = 1.0;
+        t.anchor_y = 1.0;
+        assert_eq!(t.dest_rect(3, 3), (Rect::new(6, 6, 5, 5), Point::new(5, 5)));
+    }
+}
@@ -0,0 +1,1084 @@
+extern crate sdl2;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use sdl2::controller::Button as PadButton;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+use sdl2::rect::Rect;
+use sdl2::render::Renderer;
+
+use display::Displayable;
+use sprite::Sprite;
+use atlas::{TexElement, Tint};
+use transform::Transform;
+use bmfont::BitmapFont;
+
+// Buttons sink by this many pixels while held, like the original game.
+const PRESS_OFFSET: f32 = 3.0;
+// The focused widget is drawn this much larger.
+const FOCUS_SCALE: f32 = 1.1;
+// Tint of a disabled button without an image of its own.
+const DISABLED_GREY: u8 = 128;
+
+pub type Callback = Box<FnMut()>;
+
+// Widget is a Displayable placed in a box on screen, that can take the
+// focus and be clicked. Widgets are laid out and hit tested in the space
+// they are painted in, the screen for menus.
+pub trait Widget: Displayable {
+    // get_rect is the widget's box, for clicks and layout.
+    fn get_rect(&self) -> Rect;
+
+    // set_position moves the top left corner of the box.
+    fn set_position(&mut self, x: i32, y: i32);
+
+    // set_center moves the box so it is centered on (x, y).
+    fn set_center(&mut self, x: i32, y: i32) {
+        let r = self.get_rect();
+        self.set_position(x - r.width() as i32 / 2, y - r.height() as i32 / 2);
+    }
+
+    fn get_visible(&self) -> bool {
+        true
+    }
+
+    // is_focusable tells whether keys and gamepads can move onto the widget.
+    fn is_focusable(&self) -> bool {
+        false
+    }
+
+    fn set_focused(&mut self, _focused: bool) {}
+
+    // activate is a press from the keyboard or a gamepad.
+    fn activate(&mut self) {}
+
+    // adjust steps a value left or right, returning false if the widget
+    // has none, so the keys move the focus instead.
+    fn adjust(&mut self, _steps: i32) -> bool {
+        false
+    }
+}
+
+// Sprites are laid out by their box too, whatever their anchor.
+impl Widget for Sprite {
+    fn get_rect(&self) -> Rect {
+        let (w, h) = self.get_size();
+        let (sx, sy) = self.get_scale();
+        let (w, h) = ((w as f32 * sx) as u32, (h as f32 * sy) as u32);
+        let (ax, ay) = self.get_anchor();
+        let (x, y) = self.get_position();
+        Rect::new(x - (ax * w as f32) as i32, y - (ay * h as f32) as i32, w, h)
+    }
+    fn set_position(&mut self, x: i32, y: i32) {
+        let r = Widget::get_rect(self);
+        let (ax, ay) = self.get_anchor();
+        Sprite::set_position(self,
+                             x + (ax * r.width() as f32) as i32,
+                             y + (ay * r.height() as f32) as i32);
+    }
+    fn get_visible(&self) -> bool {
+        Sprite::get_visible(self)
+    }
+}
+
+// paint_in paints `tex` stretched over `rect`, offset by the parent's position
+// and scaled by `scale` around the middle of the box.
+fn paint_in(renderer: &mut Renderer,
+            tex: &TexElement,
+            parent: &Transform,
+            rect: Rect,
+            dy: f32,
+            scale: f32,
+            tint: &Tint) {
+    let (w, h) = tex.get_size();
+    let mut local = Transform::identity();
+    local.x = rect.x() as f32 + rect.width() as f32 / 2.0;
+    local.y = rect.y() as f32 + rect.height() as f32 / 2.0 + dy;
+    local.scale_x = scale * rect.width() as f32 / w as f32;
+    local.scale_y = scale * rect.height() as f32 / h as f32;
+    local.anchor_x = 0.5;
+    local.anchor_y = 0.5;
+    tex.paint_tinted(renderer, &parent.then(&local), tint);
+}
+
+
+// Button is an image button with optional pressed and disabled images.
+// Without them it sinks while held and greys out while disabled.
+pub struct Button {
+    rect: Rect,
+    normal: Rc<RefCell<TexElement>>,
+    pressed: Option<Rc<RefCell<TexElement>>>,
+    disabled: Option<Rc<RefCell<TexElement>>>,
+    tint: Tint,
+    visible: bool,
+    enabled: bool,
+    focused: bool,
+    held: bool,
+    on_click: Option<Callback>,
+}
+
+impl Button {
+    pub fn new(normal: Rc<RefCell<TexElement>>) -> Button {
+        let (w, h) = normal.borrow().get_size();
+        Button {
+            rect: Rect::new(0, 0, w, h),
+            normal: normal,
+            pressed: None,
+            disabled: None,
+            tint: Tint::white(),
+            visible: true,
+            enabled: true,
+            focused: false,
+            held: false,
+            on_click: None,
+        }
+    }
+
+    // set_image swaps the normal image, e.g. to relabel a text button, and
+    // resizes the button from its top left corner.
+    pub fn set_image(&mut self, tex: Rc<RefCell<TexElement>>) {
+        let (w, h) = tex.borrow().get_size();
+        self.rect.set_width(w);
+        self.rect.set_height(h);
+        self.normal = tex;
+    }
+
+    pub fn set_pressed_image(&mut self, tex: Rc<RefCell<TexElement>>) {
+        self.pressed = Some(tex);
+    }
+
+    pub fn set_disabled_image(&mut self, tex: Rc<RefCell<TexElement>>) {
+        self.disabled = Some(tex);
+    }
+
+    // on_click sets what a click, or Enter on the focused button, does.
+    pub fn on_click<F: FnMut() + 'static>(&mut self, f: F) {
+        self.on_click = Some(Box::new(f));
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.held = false;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_color(&mut self, r: u8, g: u8, b: u8) {
+        self.tint = Tint::rgba(r, g, b, self.tint.a);
+    }
+
+    pub fn hide<'a>(&'a mut self) -> &'a mut Button {
+        self.visible = false;
+        self.held = false;
+        self
+    }
+
+    pub fn show<'a>(&'a mut self) -> &'a mut Button {
+        self.visible = true;
+        self
+    }
+
+    pub fn click(&mut self) {
+        if !self.enabled || !self.visible {
+            return;
+        }
+        if let Some(ref mut f) = self.on_click {
+            f();
+        }
+    }
+}
+
+impl Widget for Button {
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+    fn set_position(&mut self, x: i32, y: i32) {
+        self.rect.set_x(x);
+        self.rect.set_y(y);
+    }
+    fn get_visible(&self) -> bool {
+        self.visible
+    }
+    fn is_focusable(&self) -> bool {
+        self.visible && self.enabled
+    }
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+    fn activate(&mut self) {
+        self.click();
+    }
+}
+
+impl Displayable for Button {
+    fn update(&mut self) {}
+
+    fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        if !self.visible {
+            return;
+        }
+        let mut tint = self.tint;
+        let tex = if !self.enabled {
+            match self.disabled {
+                Some(ref tex) => tex,
+                None => {
+                    tint = tint.modulate(&Tint::rgba(DISABLED_GREY, DISABLED_GREY, DISABLED_GREY, 255));
+                    &self.normal
+                }
+            }
+        } else if self.held {
+            self.pressed.as_ref().unwrap_or(&self.normal)
+        } else {
+            &self.normal
+        };
+        let dy = if self.held { PRESS_OFFSET } else { 0.0 };
+        let scale = if self.focused { FOCUS_SCALE } else { 1.0 };
+        paint_in(renderer, &tex.borrow(), parent, self.rect, dy, scale, &tint);
+    }
+
+    fn on_mouse_down(&mut self, event: &Event) {
+        if let &Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } = event {
+            self.held = self.visible && self.enabled && self.rect.contains((x, y));
+        }
+    }
+
+    fn on_mouse_up(&mut self, event: &Event) {
+        if let &Event::MouseButtonUp { mouse_btn: MouseButton::Left, x, y, .. } = event {
+            // A press only counts when released over the button.
+            if self.held && self.rect.contains((x, y)) {
+                self.held = false;
+                self.click();
+            }
+            self.held = false;
+        }
+    }
+}
+
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// NumberLabel shows a number in a bitmap font, such as the atlas digits.
+// Its position is the point the number is aligned to horizontally, and
+// its vertical middle.
+pub struct NumberLabel {
+    x: i32,
+    y: i32,
+    align: Align,
+    scale: f32,
+    value: u32,
+    font: Rc<BitmapFont>,
+    visible: bool,
+}
+
+impl NumberLabel {
+    pub fn new(font: Rc<BitmapFont>) -> NumberLabel {
+        NumberLabel {
+            x: 0,
+            y: 0,
+            align: Align::Left,
+            scale: 1.0,
+            value: 0,
+            font: font,
+            visible: true,
+        }
+    }
+
+    // from_atlas uses the atlas font, font_048 to font_057.
+    pub fn from_atlas(atlas: &HashMap<String, Rc<RefCell<TexElement>>>) -> NumberLabel {
+        let font = BitmapFont::from_atlas(atlas, "font_", "0123456789").unwrap();
+        NumberLabel::new(Rc::new(font))
+    }
+
+    pub fn set_value(&mut self, value: u32) {
+        self.value = value;
+    }
+
+    pub fn get_value(&self) -> u32 {
+        self.value
+    }
+
+    pub fn set_align(&mut self, align: Align) {
+        self.align = align;
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+
+    pub fn hide<'a>(&'a mut self) -> &'a mut NumberLabel {
+        self.visible = false;
+        self
+    }
+
+    pub fn show<'a>(&'a mut self) -> &'a mut NumberLabel {
+        self.visible = true;
+        self
+    }
+
+    fn size(&self) -> (u32, u32) {
+        let (w, h) = self.font.measure(&self.value.to_string());
+        ((w as f32 * self.scale) as u32, (h as f32 * self.scale) as u32)
+    }
+}
+
+impl Widget for NumberLabel {
+    fn get_rect(&self) -> Rect {
+        let (w, h) = self.size();
+        let left = match self.align {
+            Align::Left => self.x,
+            Align::Center => self.x - w as i32 / 2,
+            Align::Right => self.x - w as i32,
+        };
+        Rect::new(left, self.y - h as i32 / 2, w.max(1), h.max(1))
+    }
+    fn set_position(&mut self, x: i32, y: i32) {
+        self.x = x;
+        self.y = y;
+    }
+    // The position already names the alignment point.
+    fn set_center(&mut self, x: i32, y: i32) {
+        self.set_position(x, y);
+    }
+    fn get_visible(&self) -> bool {
+        self.visible
+    }
+}
+
+impl Displayable for NumberLabel {
+    fn update(&mut self) {}
+
+    fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        if !self.visible {
+            return;
+        }
+        let r = self.get_rect();
+        self.font.paint_text(renderer,
+                             parent,
+                             &self.value.to_string(),
+                             r.x() as f32,
+                             r.y() as f32,
+                             self.scale,
+                             &Tint::white());
+    }
+}
+
+
+// Panel is a nine-slice box: the corners of its image keep their size and
+// the edges and middle stretch to fill it. It follows its parent's position
+// but not its scale or rotation.
+pub struct Panel {
+    rect: Rect,
+    tex: Rc<RefCell<TexElement>>,
+    // Widths of the left, top, right and bottom borders of the image.
+    insets: (u32, u32, u32, u32),
+    tint: Tint,
+    visible: bool,
+}
+
+impl Panel {
+    pub fn new(tex: Rc<RefCell<TexElement>>, insets: (u32, u32, u32, u32), w: u32, h: u32) -> Panel {
+        Panel {
+            rect: Rect::new(0, 0, w, h),
+            tex: tex,
+            insets: insets,
+            tint: Tint::white(),
+            visible: true,
+        }
+    }
+
+    pub fn set_size(&mut self, w: u32, h: u32) {
+        self.rect.set_width(w);
+        self.rect.set_height(h);
+    }
+
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.tint.a = alpha;
+    }
+
+    pub fn hide<'a>(&'a mut self) -> &'a mut Panel {
+        self.visible = false;
+        self
+    }
+
+    pub fn show<'a>(&'a mut self) -> &'a mut Panel {
+        self.visible = true;
+        self
+    }
+}
+
+// slices cuts a span of `total` into start border, middle and end border,
+// the borders shrinking evenly if the span is too small to hold them.
+fn slices(start: u32, end: u32, total: u32) -> [(i32, u32); 3] {
+    let (start, end) = if start + end > total {
+        (start * total / (start + end).max(1), end * total / (start + end).max(1))
+    } else {
+        (start, end)
+    };
+    let middle = total - start - end;
+    [(0, start), (start as i32, middle), ((start + middle) as i32, end)]
+}
+
+impl Widget for Panel {
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+    fn set_position(&mut self, x: i32, y: i32) {
+        self.rect.set_x(x);
+        self.rect.set_y(y);
+    }
+    fn get_visible(&self) -> bool {
+        self.visible
+    }
+}
+
+impl Displayable for Panel {
+    fn update(&mut self) {}
+
+    fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        if !self.visible {
+            return;
+        }
+        let tex = self.tex.borrow();
+        let src = tex.get_rect();
+        let (l, t, r, b) = self.insets;
+        let (ox, oy) = parent.apply(self.rect.x() as f32, self.rect.y() as f32);
+        let src_cols = slices(l, r, src.width());
+        let src_rows = slices(t, b, src.height());
+        let dst_cols = slices(l, r, self.rect.width());
+        let dst_rows = slices(t, b, self.rect.height());
+        for row in 0..3 {
+            for col in 0..3 {
+                let (sx, sw) = src_cols[col];
+                let (sy, sh) = src_rows[row];
+                let (dx, dw) = dst_cols[col];
+                let (dy, dh) = dst_rows[row];
+                if sw == 0 || sh == 0 || dw == 0 || dh == 0 {
+                    continue;
+                }
+                tex.paint_src(renderer,
+                              Some(Rect::new(src.x() + sx, src.y() + sy, sw, sh)),
+                              Rect::new(ox as i32 + dx, oy as i32 + dy, dw, dh),
+                              &self.tint);
+            }
+        }
+    }
+}
+
+
+// Toggle switches between an on and an off image when clicked.
+pub struct Toggle {
+    rect: Rect,
+    on: Rc<RefCell<TexElement>>,
+    off: Rc<RefCell<TexElement>>,
+    value: bool,
+    visible: bool,
+    focused: bool,
+    held: bool,
+    on_change: Option<Box<FnMut(bool)>>,
+}
+
+impl Toggle {
+    // new sizes the box to fit the larger of the two images.
+    pub fn new(on: Rc<RefCell<TexElement>>, off: Rc<RefCell<TexElement>>, value: bool) -> Toggle {
+        let (on_w, on_h) = on.borrow().get_size();
+        let (off_w, off_h) = off.borrow().get_size();
+        let (w, h) = (on_w.max(off_w), on_h.max(off_h));
+        Toggle {
+            rect: Rect::new(0, 0, w, h),
+            on: on,
+            off: off,
+            value: value,
+            visible: true,
+            focused: false,
+            held: false,
+            on_change: None,
+        }
+    }
+
+    // set_images swaps both images, resizing the box to fit them.
+    pub fn set_images(&mut self, on: Rc<RefCell<TexElement>>, off: Rc<RefCell<TexElement>>) {
+        let (on_w, on_h) = on.borrow().get_size();
+        let (off_w, off_h) = off.borrow().get_size();
+        self.rect.set_width(on_w.max(off_w));
+        self.rect.set_height(on_h.max(off_h));
+        self.on = on;
+        self.off = off;
+    }
+
+    pub fn on_change<F: FnMut(bool) + 'static>(&mut self, f: F) {
+        self.on_change = Some(Box::new(f));
+    }
+
+    // set_value changes the value without calling back.
+    pub fn set_value(&mut self, value: bool) {
+        self.value = value;
+    }
+
+    pub fn get_value(&self) -> bool {
+        self.value
+    }
+
+    pub fn hide<'a>(&'a mut self) -> &'a mut Toggle {
+        self.visible = false;
+        self
+    }
+
+    pub fn show<'a>(&'a mut self) -> &'a mut Toggle {
+        self.visible = true;
+        self
+    }
+
+    pub fn flip(&mut self) {
+        self.value = !self.value;
+        let value = self.value;
+        if let Some(ref mut f) = self.on_change {
+            f(value);
+        }
+    }
+}
+
+impl Widget for Toggle {
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+    fn set_position(&mut self, x: i32, y: i32) {
+        self.rect.set_x(x);
+        self.rect.set_y(y);
+    }
+    fn get_visible(&self) -> bool {
+        self.visible
+    }
+    fn is_focusable(&self) -> bool {
+        self.visible
+    }
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+    fn activate(&mut self) {
+        self.flip();
+    }
+}
+
+impl Displayable for Toggle {
+    fn update(&mut self) {}
+
+    fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        if !self.visible {
+            return;
+        }
+        let tex = if self.value { &self.on } else { &self.off };
+        let tex = tex.borrow();
+        // Each image keeps its own size, centered in the box.
+        let (w, h) = tex.get_size();
+        let c = self.rect.center();
+        let rect = Rect::new(c.x() - w as i32 / 2, c.y() - h as i32 / 2, w, h);
+        let dy = if self.held { PRESS_OFFSET } else { 0.0 };
+        let scale = if self.focused { FOCUS_SCALE } else { 1.0 };
+        paint_in(renderer, &tex, parent, rect, dy, scale, &Tint::white());
+    }
+
+    fn on_mouse_down(&mut self, event: &Event) {
+        if let &Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } = event {
+            self.held = self.visible && self.rect.contains((x, y));
+        }
+    }
+
+    fn on_mouse_up(&mut self, event: &Event) {
+        if let &Event::MouseButtonUp { mouse_btn: MouseButton::Left, x, y, .. } = event {
+            if self.held && self.rect.contains((x, y)) {
+                self.flip();
+            }
+            self.held = false;
+        }
+    }
+}
+
+
+// Slider picks a value from 0 to 1 by dragging its knob along the track,
+// or in steps with left and right while focused.
+pub struct Slider {
+    rect: Rect,
+    track: Rc<RefCell<TexElement>>,
+    knob: Rc<RefCell<TexElement>>,
+    value: f32,
+    step: f32,
+    visible: bool,
+    focused: bool,
+    dragging: bool,
+    on_change: Option<Box<FnMut(f32)>>,
+}
+
+impl Slider {
+    pub fn new(track: Rc<RefCell<TexElement>>, knob: Rc<RefCell<TexElement>>, value: f32) -> Slider {
+        let (w, h) = track.borrow().get_size();
+        Slider {
+            rect: Rect::new(0, 0, w, h),
+            track: track,
+            knob: knob,
+            value: value.max(0.0).min(1.0),
+            step: 0.1,
+            visible: true,
+            focused: false,
+            dragging: false,
+            on_change: None,
+        }
+    }
+
+    pub fn on_change<F: FnMut(f32) + 'static>(&mut self, f: F) {
+        self.on_change = Some(Box::new(f));
+    }
+
+    // set_step is how far one key press moves the value.
+    pub fn set_step(&mut self, step: f32) {
+        self.step = step;
+    }
+
+    // set_width stretches the track.
+    pub fn set_width(&mut self, w: u32) {
+        self.rect.set_width(w);
+    }
+
+    pub fn get_value(&self) -> f32 {
+        self.value
+    }
+
+    // set_value changes the value, calling back if it did change.
+    pub fn set_value(&mut self, value: f32) {
+        let value = value.max(0.0).min(1.0);
+        if value == self.value {
+            return;
+        }
+        self.value = value;
+        if let Some(ref mut f) = self.on_change {
+            f(value);
+        }
+    }
+
+    pub fn hide<'a>(&'a mut self) -> &'a mut Slider {
+        self.visible = false;
+        self.dragging = false;
+        self
+    }
+
+    pub fn show<'a>(&'a mut self) -> &'a mut Slider {
+        self.visible = true;
+        self
+    }
+
+    fn drag_to(&mut self, x: i32) {
+        let t = (x - self.rect.x()) as f32 / self.rect.width() as f32;
+        self.set_value(t);
+    }
+}
+
+impl Widget for Slider {
+    fn get_rect(&self) -> Rect {
+        self.rect
+    }
+    fn set_position(&mut self, x: i32, y: i32) {
+        self.rect.set_x(x);
+        self.rect.set_y(y);
+    }
+    fn get_visible(&self) -> bool {
+        self.visible
+    }
+    fn is_focusable(&self) -> bool {
+        self.visible
+    }
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+    fn adjust(&mut self, steps: i32) -> bool {
+        let value = self.value + self.step * steps as f32;
+        self.set_value(value);
+        true
+    }
+}
+
+impl Displayable for Slider {
+    fn update(&mut self) {}
+
+    fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        if !self.visible {
+            return;
+        }
+        paint_in(renderer, &self.track.borrow(), parent, self.rect, 0.0, 1.0, &Tint::white());
+        let knob = self.knob.borrow();
+        let (kw, kh) = knob.get_size();
+        let cx = self.rect.x() + (self.value * self.rect.width() as f32) as i32;
+        let cy = self.rect.y() + self.rect.height() as i32 / 2;
+        let rect = Rect::new(cx - kw as i32 / 2, cy - kh as i32 / 2, kw, kh);
+        let scale = if self.focused { FOCUS_SCALE } else { 1.0 };
+        paint_in(renderer, &knob, parent, rect, 0.0, scale, &Tint::white());
+    }
+
+    fn on_mouse_down(&mut self, event: &Event) {
+        if let &Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } = event {
+            if self.visible && self.rect.contains((x, y)) {
+                self.dragging = true;
+                self.drag_to(x);
+            }
+        }
+    }
+
+    fn on_mouse_move(&mut self, event: &Event) {
+        if let &Event::MouseMotion { x, .. } = event {
+            if self.dragging {
+                self.drag_to(x);
+            }
+        }
+    }
+
+    fn on_mouse_up(&mut self, event: &Event) {
+        if let &Event::MouseButtonUp { mouse_btn: MouseButton::Left, .. } = event {
+            self.dragging = false;
+        }
+    }
+}
+
+
+// Move is what a key or gamepad button asks of the focus.
+enum Move {
+    Prev,
+    Next,
+    Left,
+    Right,
+    Activate,
+}
+
+// Ui is a group of widgets painted in order, with one focus moved by the
+// arrow keys or the gamepad's d-pad and pressed with Enter, Space or A.
+// Left and right adjust a focused slider rather than moving on. The mouse
+// focuses whatever focusable widget it passes over.
+pub struct Ui {
+    visible: bool,
+    widgets: Vec<Rc<RefCell<Widget>>>,
+    focus: Option<usize>,
+}
+
+impl Ui {
+    pub fn new() -> Ui {
+        Ui {
+            visible: true,
+            widgets: Vec::new(),
+            focus: None,
+        }
+    }
+
+    // add adds a widget on top of the others, handing it back to keep.
+    pub fn add<W: Widget + 'static>(&mut self, widget: W) -> Rc<RefCell<W>> {
+        let widget = Rc::new(RefCell::new(widget));
+        self.widgets.push(widget.clone());
+        widget
+    }
+
+    pub fn hide<'a>(&'a mut self) -> &'a mut Ui {
+        self.visible = false;
+        self
+    }
+
+    pub fn show<'a>(&'a mut self) -> &'a mut Ui {
+        self.visible = true;
+        self
+    }
+
+    pub fn get_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn get_focus(&self) -> Option<usize> {
+        self.focus
+    }
+
+    // set_focus moves the focus to the widget added `idx`th, or nowhere.
+    pub fn set_focus(&mut self, idx: Option<usize>) {
+        if let Some(old) = self.focus {
+            self.widgets[old].borrow_mut().set_focused(false);
+        }
+        self.focus = match idx {
+            Some(i) if i < self.widgets.len() => Some(i),
+            _ => None,
+        };
+        if let Some(new) = self.focus {
+            self.widgets[new].borrow_mut().set_focused(true);
+        }
+    }
+
+    // focus_first focuses the first focusable widget.
+    pub fn focus_first(&mut self) {
+        let first = self.widgets.iter().position(|w| w.borrow().is_focusable());
+        self.set_focus(first);
+    }
+
+    // step moves the focus to the next focusable widget forwards or back,
+    // wrapping around.
+    fn step(&mut self, forward: bool) {
+        let n = self.widgets.len();
+        if n == 0 {
+            return;
+        }
+        let start = match self.focus {
+            Some(i) => i,
+            None if forward => n - 1,
+            None => 0,
+        };
+        for k in 1..n + 1 {
+            let i = if forward { (start + k) % n } else { (start + n - k) % n };
+            if self.widgets[i].borrow().is_focusable() {
+                self.set_focus(Some(i));
+                return;
+            }
+        }
+    }
+
+    // adjust steps the focused widget's value, or moves the focus if it has none.
+    fn adjust(&mut self, steps: i32) {
+        let focused = self.focus.map(|i| self.widgets[i].clone());
+        if !focused.map_or(false, |w| w.borrow_mut().adjust(steps)) {
+            self.step(steps > 0);
+        }
+    }
+
+    fn apply(&mut self, m: Move) {
+        match m {
+            Move::Prev => self.step(false),
+            Move::Next => self.step(true),
+            Move::Left => self.adjust(-1),
+            Move::Right => self.adjust(1),
+            Move::Activate => {
+                if let Some(i) = self.focus {
+                    self.widgets[i].clone().borrow_mut().activate();
+                }
+            }
+        }
+    }
+}
+
+impl Displayable for Ui {
+    fn update(&mut self) {
+        for w in &self.widgets {
+            w.borrow_mut().update();
+        }
+    }
+
+    fn paint(&self, renderer: &mut Renderer) {
+        self.paint_with(renderer, &Transform::identity());
+    }
+
+    fn paint_with(&self, renderer: &mut Renderer, parent: &Transform) {
+        if !self.visible {
+            return;
+        }
+        for w in &self.widgets {
+            w.borrow().paint_with(renderer, parent);
+        }
+    }
+
+    // Menus stay in place while the camera moves.
+    fn get_parallax(&self) -> f32 {
+        0.0
+    }
+
+    // on_key_down takes gamepad button events as well as keys.
+    fn on_key_down(&mut self, event: &Event) {
+        if !self.visible {
+            return;
+        }
+        let m = match event {
+            &Event::KeyDown { keycode: Some(Keycode::Up), .. } |
+            &Event::ControllerButtonDown { button: PadButton::DPadUp, .. } => Move::Prev,
+            &Event::KeyDown { keycode: Some(Keycode::Down), .. } |
+            &Event::ControllerButtonDown { button: PadButton::DPadDown, .. } => Move::Next,
+            &Event::KeyDown { keycode: Some(Keycode::Left), .. } |
+            &Event::ControllerButtonDown { button: PadButton::DPadLeft, .. } => Move::Left,
+            &Event::KeyDown { keycode: Some(Keycode::Right), .. } |
+            &Event::ControllerButtonDown { button: PadButton::DPadRight, .. } => Move::Right,
+            &Event::KeyDown { keycode: Some(Keycode::Return), .. } |
+            &Event::KeyDown { keycode: Some(Keycode::Space), .. } |
+            &Event::ControllerButtonDown { button: PadButton::A, .. } => Move::Activate,
+            _ => return,
+        };
+        self.apply(m);
+    }
+
+    fn on_mouse_down(&mut self, event: &Event) {
+        if !self.visible {
+            return;
+        }
+        for w in &self.widgets {
+            w.borrow_mut().on_mouse_down(event);
+        }
+    }
+
+    fn on_mouse_up(&mut self, event: &Event) {
+        // Released even while hidden, so nothing stays held.
+        for w in &self.widgets {
+            w.borrow_mut().on_mouse_up(event);
+        }
+    }
+
+    fn on_mouse_move(&mut self, event: &Event) {
+        if !self.visible {
+            return;
+        }
+        for w in &self.widgets {
+            w.borrow_mut().on_mouse_move(event);
+        }
+        if let &Event::MouseMotion { x, y, .. } = event {
+            let hovered = self.widgets.iter().position(|w| {
+                let w = w.borrow();
+                w.is_focusable() && w.get_rect().contains((x, y))
+            });
+            if hovered.is_some() && hovered != self.focus {
+                self.set_focus(hovered);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use sdl2::render::Renderer;
+
+    use display::Displayable;
+
+    // Block stands in for a widget: `value` makes it a slider.
+    struct Block {
+        visible: bool,
+        focused: bool,
+        value: Option<i32>,
+    }
+
+    impl Displayable for Block {
+        fn update(&mut self) {}
+        fn paint(&self, _renderer: &mut Renderer) {}
+    }
+
+    impl Widget for Block {
+        fn get_rect(&self) -> Rect {
+            Rect::new(0, 0, 10, 10)
+        }
+        fn set_position(&mut self, _x: i32, _y: i32) {}
+        fn get_visible(&self) -> bool {
+            self.visible
+        }
+        fn is_focusable(&self) -> bool {
+            self.visible
+        }
+        fn set_focused(&mut self, focused: bool) {
+            self.focused = focused;
+        }
+        fn adjust(&mut self, steps: i32) -> bool {
+            match self.value {
+                Some(v) => {
+                    self.value = Some(v + steps);
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+
+    fn block(value: Option<i32>) -> Block {
+        Block {
+            visible: true,
+            focused: false,
+            value: value,
+        }
+    }
+
+    #[test]
+    fn test_focus_wraps_around() {
+        let mut ui = Ui::new();
+        let first = ui.add(block(None));
+        ui.add(block(None));
+        ui.apply(Move::Next);
+        assert_eq!(ui.get_focus(), Some(0));
+        assert!(first.borrow().focused);
+        ui.apply(Move::Next);
+        ui.apply(Move::Next);
+        assert_eq!(ui.get_focus(), Some(0));
+        ui.apply(Move::Prev);
+        assert_eq!(ui.get_focus(), Some(1));
+        assert!(!first.borrow().focused);
+    }
+
+    #[test]
+    fn test_focus_skips_hidden() {
+        let mut ui = Ui::new();
+        ui.add(block(None));
+        let hidden = ui.add(block(None));
+        ui.add(block(None));
+        hidden.borrow_mut().visible = false;
+        ui.focus_first();
+        ui.apply(Move::Next);
+        assert_eq!(ui.get_focus(), Some(2));
+        ui.apply(Move::Prev);
+        assert_eq!(ui.get_focus(), Some(0));
+
+        let mut empty = Ui::new();
+        empty.add(block(None)).borrow_mut().visible = false;
+        empty.focus_first();
+        empty.apply(Move::Next);
+        assert_eq!(empty.get_focus(), None);
+    }
+
+    #[test]
+    fn test_left_right_adjust_sliders_only() {
+        let mut ui = Ui::new();
+        ui.add(block(None));
+        let slider = ui.add(block(Some(5)));
+        ui.focus_first();
+        // Left and right move the focus off a widget without a value...
+        ui.apply(Move::Right);
+        assert_eq!(ui.get_focus(), Some(1));
+        // ...and step a slider's value while it keeps the focus.
+        ui.apply(Move::Right);
+        ui.apply(Move::Right);
+        ui.apply(Move::Left);
+        assert_eq!(ui.get_focus(), Some(1));
+        assert_eq!(slider.borrow().value, Some(6));
+    }
+
+    #[test]
+    fn test_slices() {
+        assert_eq!(slices(4, 6, 30), [(0, 4), (4, 20), (24, 6)]);
+        assert_eq!(slices(4, 6, 10), [(0, 4), (4, 0), (4, 6)]);
+        // Too small for the borders, they shrink in proportion.
+        assert_eq!(slices(4, 6, 5), [(0, 2), (2, 0), (2, 3)]);
+        assert_eq!(slices(0, 0, 0), [(0, 0), (0, 0), (0, 0)]);
+    }
+}
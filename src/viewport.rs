@@ -0,0 +1,250 @@
+extern crate sdl2;
+
+use sdl2::event::{Event, WindowEvent};
+use sdl2::rect::Rect;
+use sdl2::render::Renderer;
+
+// ScaleMode decides how the design resolution is fitted into the window.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScaleMode {
+    // Stretch to the window, ignoring the aspect ratio.
+    Stretch,
+    // Scale as much as fits keeping the aspect ratio, with black bars.
+    Letterbox,
+    // Like Letterbox but only whole multiples, for crisp pixel art.
+    IntegerScale,
+    // Keep the aspect ratio and grow the logical size on the longer axis
+    // instead of adding bars, the scene must lay itself out for it.
+    Expand,
+}
+
+// Viewport maps a fixed design resolution onto the window, for rendering
+// and for pointer coordinates, and tracks window resizes.
+pub struct Viewport {
+    mode: ScaleMode,
+    design_w: u32,
+    design_h: u32,
+    window_w: u32,
+    window_h: u32,
+    logical_w: u32,
+    logical_h: u32,
+    scale_x: f32,
+    scale_y: f32,
+    offset_x: i32,
+    offset_y: i32,
+}
+
+impl Viewport {
+    pub fn new(design_w: u32, design_h: u32, mode: ScaleMode) -> Viewport {
+        let mut vp = Viewport {
+            mode: mode,
+            design_w: design_w,
+            design_h: design_h,
+            window_w: design_w,
+            window_h: design_h,
+            logical_w: design_w,
+            logical_h: design_h,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            offset_x: 0,
+            offset_y: 0,
+        };
+        vp.resize(design_w, design_h);
+        vp
+    }
+
+    pub fn set_mode(&mut self, mode: ScaleMode) {
+        self.mode = mode;
+        let (w, h) = (self.window_w, self.window_h);
+        self.resize(w, h);
+    }
+
+    pub fn get_mode(&self) -> ScaleMode {
+        self.mode
+    }
+
+    // resize recomputes the scale and bars for a new window size.
+    pub fn resize(&mut self, window_w: u32, window_h: u32) {
+        self.window_w = window_w.max(1);
+        self.window_h = window_h.max(1);
+        let fx = self.window_w as f32 / self.design_w as f32;
+        let fy = self.window_h as f32 / self.design_h as f32;
+
+        let (sx, sy) = match self.mode {
+            ScaleMode::Stretch => (fx, fy),
+            ScaleMode::Letterbox | ScaleMode::Expand => (fx.min(fy), fx.min(fy)),
+            ScaleMode::IntegerScale => {
+                let s = fx.min(fy).floor().max(1.0);
+                (s, s)
+            }
+        };
+        self.scale_x = sx;
+        self.scale_y = sy;
+
+        match self.mode {
+            ScaleMode::Expand => {
+                self.logical_w = (self.window_w as f32 / sx).round() as u32;
+                self.logical_h = (self.window_h as f32 / sy).round() as u32;
+            }
+            _ => {
+                self.logical_w = self.design_w;
+                self.logical_h = self.design_h;
+            }
+        }
+        self.offset_x = (self.window_w as i32 - (self.logical_w as f32 * sx).round() as i32) / 2;
+        self.offset_y = (self.window_h as i32 - (self.logical_h as f32 * sy).round() as i32) / 2;
+    }
+
+    // on_event handles window resizes, returning true when the logical size changed.
+    pub fn on_event(&mut self, event: &Event) -> bool {
+        match event {
+            &Event::Window { win_event: WindowEvent::Resized(w, h), .. } |
+            &Event::Window { win_event: WindowEvent::SizeChanged(w, h), .. } => {
+                let before = self.get_logical_size();
+                self.resize(w as u32, h as u32);
+                before != self.get_logical_size()
+            }
+            _ => false,
+        }
+    }
+
+    // get_logical_size is the size scenes should lay themselves out in.
+    pub fn get_logical_size(&self) -> (u32, u32) {
+        (self.logical_w, self.logical_h)
+    }
+
+    pub fn get_design_size(&self) -> (u32, u32) {
+        (self.design_w, self.design_h)
+    }
+
+    pub fn get_scale(&self) -> (f32, f32) {
+        (self.scale_x, self.scale_y)
+    }
+
+    // get_screen_rect is the window area the logical screen is drawn into.
+    pub fn get_screen_rect(&self) -> Rect {
+        Rect::new(self.offset_x,
+                  self.offset_y,
+                  (self.logical_w as f32 * self.scale_x).round() as u32,
+                  (self.logical_h as f32 * self.scale_y).round() as u32)
+    }
+
+    // apply sets up the renderer so that drawing in logical coordinates lands
+    // in the screen rect. Clearing still covers the whole window, painting the bars.
+    pub fn apply(&self, renderer: &mut Renderer) {
+        renderer.set_scale(self.scale_x, self.scale_y)
+                .expect("renderer scale should have been set.");
+        // SDL multiplies the viewport by the scale, so it is given in logical units.
+        renderer.set_viewport(Some(Rect::new((self.offset_x as f32 / self.scale_x) as i32,
+                                             (self.offset_y as f32 / self.scale_y) as i32,
+                                             self.logical_w,
+                                             self.logical_h)));
+    }
+
+    // to_logical maps a window point, e.g. a mouse click, to logical coordinates.
+    pub fn to_logical(&self, x: i32, y: i32) -> (i32, i32) {
+        (((x - self.offset_x) as f32 / self.scale_x).floor() as i32,
+         ((y - self.offset_y) as f32 / self.scale_y).floor() as i32)
+    }
+
+    // map_event rewrites the pointer coordinates of mouse events to logical ones.
+    pub fn map_event(&self, event: &Event) -> Event {
+        let mut event = event.clone();
+        match event {
+            Event::MouseButtonDown { ref mut x, ref mut y, .. } |
+            Event::MouseButtonUp { ref mut x, ref mut y, .. } |
+            Event::MouseMotion { ref mut x, ref mut y, .. } => {
+                let (lx, ly) = self.to_logical(*x, *y);
+                *x = lx;
+                *y = ly;
+            }
+            _ => {}
+        }
+        event
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn resized(w: i32, h: i32) -> Event {
+        Event::Window { timestamp: 0, window_id: 0, win_event: WindowEvent::Resized(w, h) }
+    }
+
+    #[test]
+    fn test_letterbox_bars() {
+        let mut vp = Viewport::new(800, 600, ScaleMode::Letterbox);
+        assert_eq!(vp.get_screen_rect(), Rect::new(0, 0, 800, 600));
+        // Wider than the design: bars left and right.
+        vp.resize(1000, 600);
+        assert_eq!(vp.get_scale(), (1.0, 1.0));
+        assert_eq!(vp.get_logical_size(), (800, 600));
+        assert_eq!(vp.get_screen_rect(), Rect::new(100, 0, 800, 600));
+        // Taller: bars above and below.
+        vp.resize(1600, 1600);
+        assert_eq!(vp.get_scale(), (2.0, 2.0));
+        assert_eq!(vp.get_screen_rect(), Rect::new(0, 200, 1600, 1200));
+    }
+
+    #[test]
+    fn test_to_logical() {
+        let mut vp = Viewport::new(800, 600, ScaleMode::Letterbox);
+        vp.resize(1000, 600);
+        assert_eq!(vp.to_logical(100, 0), (0, 0));
+        assert_eq!(vp.to_logical(899, 599), (799, 599));
+        // The bars lie outside the logical screen.
+        assert_eq!(vp.to_logical(99, 0), (-1, 0));
+        vp.resize(1600, 1000);
+        let rect = vp.get_screen_rect();
+        assert_eq!(rect, Rect::new(133, 0, 1333, 1000));
+        assert_eq!(vp.to_logical(133, 0), (0, 0));
+        assert_eq!(vp.to_logical(rect.right() - 1, 999), (799, 599));
+    }
+
+    #[test]
+    fn test_stretch() {
+        let mut vp = Viewport::new(800, 600, ScaleMode::Stretch);
+        vp.resize(1600, 600);
+        assert_eq!(vp.get_scale(), (2.0, 1.0));
+        assert_eq!(vp.get_screen_rect(), Rect::new(0, 0, 1600, 600));
+        assert_eq!(vp.to_logical(1599, 599), (799, 599));
+    }
+
+    #[test]
+    fn test_integer_scale() {
+        let mut vp = Viewport::new(800, 600, ScaleMode::IntegerScale);
+        vp.resize(1700, 1300);
+        assert_eq!(vp.get_scale(), (2.0, 2.0));
+        assert_eq!(vp.get_screen_rect(), Rect::new(50, 50, 1600, 1200));
+        assert_eq!(vp.to_logical(51, 53), (0, 1));
+        // Never below 1x, a small window crops instead.
+        vp.resize(400, 300);
+        assert_eq!(vp.get_scale(), (1.0, 1.0));
+        assert_eq!(vp.get_screen_rect(), Rect::new(-200, -150, 800, 600));
+    }
+
+    #[test]
+    fn test_expand() {
+        let mut vp = Viewport::new(800, 600, ScaleMode::Expand);
+        vp.resize(1000, 600);
+        assert_eq!(vp.get_logical_size(), (1000, 600));
+        assert_eq!(vp.get_screen_rect(), Rect::new(0, 0, 1000, 600));
+        vp.resize(1600, 1500);
+        assert_eq!(vp.get_scale(), (2.0, 2.0));
+        assert_eq!(vp.get_logical_size(), (800, 750));
+        assert_eq!(vp.to_logical(1599, 1499), (799, 749));
+        assert_eq!(vp.get_design_size(), (800, 600));
+    }
+
+    #[test]
+    fn test_on_event() {
+        let mut vp = Viewport::new(800, 600, ScaleMode::Letterbox);
+        assert!(!vp.on_event(&resized(1000, 600)));
+        assert_eq!(vp.get_screen_rect(), Rect::new(100, 0, 800, 600));
+        vp.set_mode(ScaleMode::Expand);
+        assert_eq!(vp.get_logical_size(), (1000, 600));
+        assert!(vp.on_event(&resized(1200, 600)));
+        assert!(!vp.on_event(&resized(1200, 600)));
+    }
+}